@@ -8,7 +8,7 @@ fn cleanup_path<P: AsRef<std::path::Path>>(p: P) {
     match std::fs::remove_file(p) {
         Ok(_) => {}
         Err(e) if e.kind() == ErrorKind::NotFound => {}
-        Err(e) if e.kind() == ErrorKind::IsADirectory => {
+        Err(_) if p.is_dir() => {
             if let Err(e) = std::fs::remove_dir_all(p) {
                 if e.kind() != ErrorKind::NotFound {
                     panic!("cleanup: {e}");
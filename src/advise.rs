@@ -42,7 +42,6 @@ impl MemoryMappedFile {
 
         let total = self.current_len()?;
         let (start, end) = slice_range(offset, len, total)?;
-        let length = end - start;
 
         // Get the base pointer for the mapping
         let ptr = match &self.inner.map {
@@ -54,15 +53,36 @@ impl MemoryMappedFile {
             crate::mmap::MapVariant::Cow(m) => m.as_ptr(),
         };
 
-        // SAFETY: We've validated the range is within bounds
-        let addr = unsafe { ptr.add(start) };
-
         #[cfg(unix)]
         {
             use libc::{
                 madvise, MADV_DONTNEED, MADV_NORMAL, MADV_RANDOM, MADV_SEQUENTIAL, MADV_WILLNEED,
             };
 
+            // madvise requires a page-aligned address. Widen the range to whole pages, except
+            // for DontNeed, which is narrowed so it never discards pages outside the request
+            // (on private mappings that would drop modifications). Alignment is computed on
+            // absolute addresses: a window starts wherever its file offset falls in a page,
+            // but the page below it is part of the same mapping.
+            let page = crate::utils::page_size();
+            let base = ptr as usize;
+            let (start, end) = (base + start, base + end);
+            let (start, end) = if advice == MmapAdvice::DontNeed {
+                let end = if end == base + total as usize {
+                    end
+                } else {
+                    end - end % page
+                };
+                (start.div_ceil(page) * page, end)
+            } else {
+                (start - start % page, end)
+            };
+            if start >= end {
+                return Ok(());
+            }
+            let length = end - start;
+            let addr = start as *const u8;
+
             let advice_flag = match advice {
                 MmapAdvice::Normal => MADV_NORMAL,
                 MmapAdvice::Random => MADV_RANDOM,
@@ -82,6 +102,10 @@ impl MemoryMappedFile {
 
         #[cfg(windows)]
         {
+            // SAFETY: We've validated the range is within bounds
+            let addr = unsafe { ptr.add(start) };
+            let length = end - start;
+
             // Windows only supports prefetching (WillNeed equivalent)
             if matches!(advice, MmapAdvice::WillNeed) {
                 use std::mem;
@@ -207,4 +231,66 @@ mod tests {
 
         fs::remove_file(&path).expect("cleanup");
     }
+
+    #[test]
+    #[cfg(feature = "advise")]
+    fn test_advise_unaligned_ranges() {
+        let path = tmp_path("advise_unaligned");
+        let _ = fs::remove_file(&path);
+
+        let page = crate::utils::page_size() as u64;
+        let mmap = create_mmap(&path, page * 4).expect("create");
+        mmap.update_region(0, &[7u8; 100]).expect("write");
+        for advice in [
+            MmapAdvice::Sequential,
+            MmapAdvice::WillNeed,
+            MmapAdvice::DontNeed,
+        ] {
+            mmap.advise(10, page * 2, advice).expect("unaligned advise");
+            mmap.advise(page * 4 - 1, 1, advice).expect("last byte");
+        }
+        // DontNeed never reaches outside the requested range
+        mmap.advise(1, 10, MmapAdvice::DontNeed)
+            .expect("sub-page dontneed");
+        let mut buf = [0u8; 100];
+        mmap.read_into(0, &mut buf).expect("read");
+        assert_eq!(buf, [7u8; 100]);
+
+        fs::remove_file(&path).expect("cleanup");
+    }
+
+    #[test]
+    #[cfg(feature = "advise")]
+    fn test_advise_windowed_mapping() {
+        let path = tmp_path("advise_windowed");
+        let _ = fs::remove_file(&path);
+
+        let page = crate::utils::page_size() as u64;
+        create_mmap(&path, page * 4).expect("create");
+        // The window's base address is 3 bytes into a page
+        let mmap = MemoryMappedFile::builder(&path)
+            .mode(crate::MmapMode::ReadWrite)
+            .offset(3)
+            .len(page * 2)
+            .open()
+            .expect("open window");
+        mmap.update_region(0, &[9u8; 64]).expect("write");
+        for advice in [
+            MmapAdvice::Normal,
+            MmapAdvice::Random,
+            MmapAdvice::Sequential,
+            MmapAdvice::WillNeed,
+            MmapAdvice::DontNeed,
+        ] {
+            mmap.advise(0, 64, advice).expect("advise window start");
+            mmap.advise(page, page, advice)
+                .expect("advise to window end");
+        }
+        let mut buf = [0u8; 64];
+        mmap.read_into(0, &mut buf).expect("read");
+        assert_eq!(buf, [9u8; 64]);
+
+        drop(mmap);
+        fs::remove_file(&path).expect("cleanup");
+    }
 }
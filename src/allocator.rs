@@ -0,0 +1,501 @@
+//! Offset-based slab allocator managing free space inside a memory-mapped file.
+//!
+//! Allocations are rounded up to power-of-two size classes (16 B to 1 GiB). Each class keeps
+//! a free list threaded through the freed blocks themselves, with the list heads persisted in
+//! a 256-byte header, so the allocator state survives reopening. Small classes are carved from
+//! 64 KiB slabs; the file grows on demand through [`MemoryMappedFile::resize`].
+
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::errors::{MmapIoError, Result};
+use crate::mmap::{MemoryMappedFile, MmapMode};
+use crate::pod::read_pod;
+use crate::segment::{Segment, SegmentMut};
+
+const MAGIC: [u8; 8] = *b"MMAPALOC";
+const VERSION: u32 = 1;
+// Header layout: magic (8) | version (4) | reserved (4) | heap end (8) | root (8)
+//                | free-list heads, one per class (8 each) | reserved
+const HEADER_LEN: u64 = 256;
+const HEAP_END_OFFSET: u64 = 16;
+const ROOT_OFFSET: u64 = 24;
+const HEADS_OFFSET: u64 = 32;
+const MIN_CLASS_SHIFT: u32 = 4;
+const MAX_CLASS_SHIFT: u32 = 30;
+const CLASSES: usize = (MAX_CLASS_SHIFT - MIN_CLASS_SHIFT + 1) as usize;
+const SLAB_LEN: u64 = 64 * 1024;
+const MAX_ALIGN: u64 = 4096;
+// Every allocation is preceded by an 8-byte tag: magic (2) | class (2) | distance to block start (4)
+const TAG_LEN: u64 = 8;
+const TAG_ALLOCATED: u16 = 0xA11C;
+const TAG_FREE: u16 = 0xF4EE;
+
+/// Slab allocator handing out offsets inside a memory-mapped file.
+///
+/// Offsets stay valid across remaps and processes, which makes them suitable as links in
+/// persistent data structures. A root offset can be stored in the header to find the
+/// structure again after reopening.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mmap_io::allocator::MmapAllocator;
+///
+/// let mut heap = MmapAllocator::create("heap.bin")?;
+/// let node = heap.alloc(64, 8)?;
+/// heap.mmap().update_region(node, b"node payload")?;
+/// heap.set_root(node)?;
+/// heap.flush()?;
+/// drop(heap);
+///
+/// let heap = MmapAllocator::open("heap.bin")?;
+/// assert_eq!(heap.root(), node);
+/// # Ok::<(), mmap_io::MmapIoError>(())
+/// ```
+#[derive(Debug)]
+pub struct MmapAllocator {
+    mmap: Arc<MemoryMappedFile>,
+    heap_end: u64,
+    root: u64,
+    heads: [u64; CLASSES],
+}
+
+impl MmapAllocator {
+    /// Create a new allocator file (truncating if it exists).
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from `MemoryMappedFile::create_rw`.
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::new_in(MemoryMappedFile::create_rw(path, HEADER_LEN)?)
+    }
+
+    /// Open an existing allocator file read-write.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidFormat` if the file has no valid allocator header.
+    /// Returns errors from `MemoryMappedFile::open_rw`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_mmap(MemoryMappedFile::open_rw(path)?)
+    }
+
+    /// Format an existing read-write mapping as an empty allocator.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidMode` if the mapping is not read-write.
+    /// Returns errors from `MemoryMappedFile::resize` or `update_region`.
+    pub fn new_in(mmap: MemoryMappedFile) -> Result<Self> {
+        if mmap.mode() != MmapMode::ReadWrite {
+            return Err(MmapIoError::InvalidMode(
+                "MmapAllocator requires ReadWrite mode",
+            ));
+        }
+        if mmap.current_len()? < HEADER_LEN {
+            mmap.resize(HEADER_LEN)?;
+        }
+        let mut header = [0u8; HEADER_LEN as usize];
+        header[..8].copy_from_slice(&MAGIC);
+        header[8..12].copy_from_slice(&VERSION.to_le_bytes());
+        header[16..24].copy_from_slice(&HEADER_LEN.to_le_bytes());
+        mmap.update_region(0, &header)?;
+        Ok(Self {
+            mmap: Arc::new(mmap),
+            heap_end: HEADER_LEN,
+            root: 0,
+            heads: [0; CLASSES],
+        })
+    }
+
+    /// Open an allocator previously stored in `mmap`. Read-only mappings can resolve offsets
+    /// but not allocate.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidFormat` if the header is missing or inconsistent with the
+    /// mapping's length.
+    pub fn from_mmap(mmap: MemoryMappedFile) -> Result<Self> {
+        let total = mmap.current_len()?;
+        if total < HEADER_LEN
+            || read_pod::<[u8; 8]>(&mmap, 0)? != MAGIC
+            || u32::from_le(read_pod(&mmap, 8)?) != VERSION
+        {
+            return Err(MmapIoError::InvalidFormat(
+                "missing or unsupported MmapAllocator header".into(),
+            ));
+        }
+        let heap_end = u64::from_le(read_pod(&mmap, HEAP_END_OFFSET)?);
+        let root = u64::from_le(read_pod(&mmap, ROOT_OFFSET)?);
+        let mut heads: [u64; CLASSES] = read_pod(&mmap, HEADS_OFFSET)?;
+        for head in &mut heads {
+            *head = u64::from_le(*head);
+        }
+        if heap_end < HEADER_LEN
+            || heap_end > total
+            || heads
+                .iter()
+                .any(|&h| h != 0 && (h < HEADER_LEN || h >= heap_end))
+        {
+            return Err(MmapIoError::InvalidFormat(format!(
+                "MmapAllocator header inconsistent with file length {total}"
+            )));
+        }
+        Ok(Self {
+            mmap: Arc::new(mmap),
+            heap_end,
+            root,
+            heads,
+        })
+    }
+
+    /// Shared handle to the underlying mapping, for building segments or reading data.
+    #[must_use]
+    pub fn mmap(&self) -> &Arc<MemoryMappedFile> {
+        &self.mmap
+    }
+
+    /// Offset of the first byte not yet handed to any slab.
+    #[must_use]
+    pub fn heap_end(&self) -> u64 {
+        self.heap_end
+    }
+
+    /// Root offset stored in the header (0 if unset).
+    #[must_use]
+    pub fn root(&self) -> u64 {
+        self.root
+    }
+
+    /// Store a root offset in the header so the structure can be found after reopening.
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from `MemoryMappedFile::update_region`.
+    pub fn set_root(&mut self, offset: u64) -> Result<()> {
+        self.mmap
+            .update_region(ROOT_OFFSET, &offset.to_le_bytes())?;
+        self.root = offset;
+        Ok(())
+    }
+
+    /// Allocate `size` bytes aligned to `align` and return their offset in the file.
+    ///
+    /// The contents of the block are unspecified (zero for freshly grown space).
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::Misaligned` if `align` is not a power of two or exceeds 4096.
+    /// Returns `MmapIoError::ResizeFailed` if the request exceeds the largest size class.
+    /// Returns errors from `MemoryMappedFile::resize` or `update_region`.
+    pub fn alloc(&mut self, size: u64, align: u64) -> Result<u64> {
+        if !align.is_power_of_two() || align > MAX_ALIGN {
+            return Err(MmapIoError::Misaligned {
+                required: align,
+                offset: 0,
+            });
+        }
+        // Blocks are aligned to min(block size, 4096), so the user data can start exactly
+        // `prefix` bytes in and still be aligned.
+        let prefix = align.max(TAG_LEN);
+        let class = size
+            .max(1)
+            .checked_add(prefix)
+            .and_then(class_for)
+            .ok_or_else(|| {
+                MmapIoError::ResizeFailed(format!("allocation of {size} bytes is too large"))
+            })?;
+
+        let block = match self.heads[class] {
+            0 => self.carve(class)?,
+            head => {
+                let next = u64::from_le(read_pod(&self.mmap, head + TAG_LEN)?);
+                self.set_head(class, next)?;
+                head
+            }
+        };
+        let offset = block + prefix;
+        self.mmap
+            .update_region(offset - TAG_LEN, &tag(TAG_ALLOCATED, class, prefix))?;
+        Ok(offset)
+    }
+
+    /// Return the allocation at `offset` to its size class free list.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidMode` if `offset` is not a live allocation (including
+    /// double frees).
+    /// Returns errors from `MemoryMappedFile::update_region`.
+    pub fn free(&mut self, offset: u64) -> Result<()> {
+        let (class, block) = self.lookup(offset)?;
+        // Mark the user tag first so a second free is detected even when the block tag and
+        // user tag live at different offsets.
+        self.mmap
+            .update_region(offset - TAG_LEN, &tag(TAG_FREE, class, 0))?;
+        let mut link = [0u8; 16];
+        link[..8].copy_from_slice(&tag(TAG_FREE, class, 0));
+        link[8..].copy_from_slice(&self.heads[class].to_le_bytes());
+        self.mmap.update_region(block, &link)?;
+        self.set_head(class, block)
+    }
+
+    /// Number of bytes usable at `offset` (at least the size originally requested).
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidMode` if `offset` is not a live allocation.
+    pub fn usable_size(&self, offset: u64) -> Result<u64> {
+        let (class, block) = self.lookup(offset)?;
+        Ok(block + class_size(class) - offset)
+    }
+
+    /// Immutable segment over `len` bytes at `offset`.
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from `Segment::new`.
+    pub fn segment(&self, offset: u64, len: u64) -> Result<Segment> {
+        Segment::new(Arc::clone(&self.mmap), offset, len)
+    }
+
+    /// Mutable segment over `len` bytes at `offset`.
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from `SegmentMut::new`.
+    pub fn segment_mut(&self, offset: u64, len: u64) -> Result<SegmentMut> {
+        SegmentMut::new(Arc::clone(&self.mmap), offset, len)
+    }
+
+    /// Flush allocator state and data to disk.
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from `MemoryMappedFile::flush`.
+    pub fn flush(&self) -> Result<()> {
+        self.mmap.flush()
+    }
+
+    /// Validate the tag in front of `offset` and return its class and block start.
+    fn lookup(&self, offset: u64) -> Result<(usize, u64)> {
+        if offset < HEADER_LEN + TAG_LEN || offset > self.heap_end {
+            return Err(MmapIoError::InvalidMode(
+                "offset is not a live MmapAllocator allocation",
+            ));
+        }
+        let raw: [u8; 8] = read_pod(&self.mmap, offset - TAG_LEN)?;
+        let magic = u16::from_le_bytes([raw[0], raw[1]]);
+        let class = usize::from(u16::from_le_bytes([raw[2], raw[3]]));
+        let prefix = u64::from(u32::from_le_bytes([raw[4], raw[5], raw[6], raw[7]]));
+        if magic != TAG_ALLOCATED || class >= CLASSES || prefix < TAG_LEN || prefix > offset {
+            return Err(MmapIoError::InvalidMode(
+                "offset is not a live MmapAllocator allocation",
+            ));
+        }
+        Ok((class, offset - prefix))
+    }
+
+    /// Carve a new slab for `class` at the end of the heap, return its first block and put
+    /// the rest on the free list.
+    fn carve(&mut self, class: usize) -> Result<u64> {
+        self.check_writable()?;
+        let block_size = class_size(class);
+        let start = align_up(self.heap_end, block_size.min(MAX_ALIGN));
+        let blocks = (SLAB_LEN / block_size).max(1);
+        let end = start + blocks * block_size;
+
+        let total = self.mmap.current_len()?;
+        if end > total {
+            self.mmap.resize(end.max(total.saturating_mul(2)))?;
+        }
+        if blocks > 1 {
+            // Chain blocks 1..n, the last one pointing at the current head (normally 0)
+            let mut slab = vec![0u8; ((blocks - 1) * block_size) as usize];
+            for i in 1..blocks {
+                let at = ((i - 1) * block_size) as usize;
+                let next = if i + 1 < blocks {
+                    start + (i + 1) * block_size
+                } else {
+                    self.heads[class]
+                };
+                slab[at..at + 8].copy_from_slice(&tag(TAG_FREE, class, 0));
+                slab[at + 8..at + 16].copy_from_slice(&next.to_le_bytes());
+            }
+            self.mmap.update_region(start + block_size, &slab)?;
+            self.set_head(class, start + block_size)?;
+        }
+        self.mmap
+            .update_region(HEAP_END_OFFSET, &end.to_le_bytes())?;
+        self.heap_end = end;
+        Ok(start)
+    }
+
+    fn set_head(&mut self, class: usize, block: u64) -> Result<()> {
+        self.mmap
+            .update_region(HEADS_OFFSET + class as u64 * 8, &block.to_le_bytes())?;
+        self.heads[class] = block;
+        Ok(())
+    }
+
+    fn check_writable(&self) -> Result<()> {
+        if self.mmap.mode() == MmapMode::ReadWrite {
+            Ok(())
+        } else {
+            Err(MmapIoError::InvalidMode(
+                "MmapAllocator allocation requires ReadWrite mode",
+            ))
+        }
+    }
+}
+
+fn class_for(request: u64) -> Option<usize> {
+    let size = request
+        .max(1 << MIN_CLASS_SHIFT)
+        .checked_next_power_of_two()?;
+    let shift = size.trailing_zeros();
+    (shift <= MAX_CLASS_SHIFT).then(|| (shift - MIN_CLASS_SHIFT) as usize)
+}
+
+fn class_size(class: usize) -> u64 {
+    1 << (class as u32 + MIN_CLASS_SHIFT)
+}
+
+fn align_up(offset: u64, align: u64) -> u64 {
+    (offset + align - 1) & !(align - 1)
+}
+
+fn tag(magic: u16, class: usize, prefix: u64) -> [u8; 8] {
+    let mut raw = [0u8; 8];
+    raw[..2].copy_from_slice(&magic.to_le_bytes());
+    raw[2..4].copy_from_slice(&(class as u16).to_le_bytes());
+    raw[4..].copy_from_slice(&(prefix as u32).to_le_bytes());
+    raw
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn tmp_path(name: &str) -> PathBuf {
+        let mut p = std::env::temp_dir();
+        p.push(format!(
+            "mmap_io_allocator_test_{}_{}",
+            name,
+            std::process::id()
+        ));
+        p
+    }
+
+    #[test]
+    fn test_alloc_free_reuse_and_alignment() {
+        let path = tmp_path("reuse");
+        let _ = fs::remove_file(&path);
+
+        let mut heap = MmapAllocator::create(&path).expect("create");
+        let a = heap.alloc(10, 8).expect("alloc");
+        let b = heap.alloc(10, 8).expect("alloc");
+        assert_ne!(a, b);
+        assert!(heap.usable_size(a).expect("size") >= 10);
+        assert_eq!(b - a, 32, "same-class blocks come from one slab");
+
+        heap.free(a).expect("free");
+        assert_eq!(heap.alloc(12, 1).expect("realloc"), a);
+
+        for align in [1, 2, 16, 64, 512, 4096] {
+            let off = heap.alloc(100, align).expect("aligned alloc");
+            assert_eq!(off % align, 0, "align {align}");
+            let ptr = heap
+                .mmap()
+                .as_slice_guarded(off, 100)
+                .expect("slice")
+                .as_ptr();
+            assert_eq!(ptr as usize % align as usize, 0);
+        }
+        assert!(heap.alloc(8, 3).is_err());
+        assert!(heap.alloc(8, 8192).is_err());
+
+        fs::remove_file(&path).expect("cleanup");
+    }
+
+    #[test]
+    fn test_double_free_and_bogus_offsets_rejected() {
+        let path = tmp_path("double_free");
+        let _ = fs::remove_file(&path);
+
+        let mut heap = MmapAllocator::create(&path).expect("create");
+        let a = heap.alloc(32, 64).expect("alloc");
+        heap.free(a).expect("free");
+        assert!(matches!(heap.free(a), Err(MmapIoError::InvalidMode(_))));
+        assert!(heap.free(0).is_err());
+        assert!(heap.free(heap.heap_end() + 64).is_err());
+        let b = heap.alloc(8, 8).expect("alloc");
+        assert!(heap.free(b + 8).is_err());
+
+        fs::remove_file(&path).expect("cleanup");
+    }
+
+    #[test]
+    fn test_growth_and_reopen_keep_free_lists() {
+        let path = tmp_path("reopen");
+        let _ = fs::remove_file(&path);
+
+        let mut heap = MmapAllocator::create(&path).expect("create");
+        let big = heap.alloc(3 << 20, 4096).expect("large alloc");
+        heap.segment_mut(big, 5)
+            .expect("segment")
+            .write(b"large")
+            .expect("write");
+        let mut small = Vec::new();
+        for i in 0..5000u64 {
+            let off = heap.alloc(24, 8).expect("alloc");
+            heap.mmap()
+                .update_region(off, &i.to_le_bytes())
+                .expect("write");
+            small.push(off);
+        }
+        for &off in small.iter().step_by(2) {
+            heap.free(off).expect("free");
+        }
+        heap.set_root(small[1]).expect("root");
+        heap.flush().expect("flush");
+        let heap_end = heap.heap_end();
+        drop(heap);
+
+        let mut heap = MmapAllocator::open(&path).expect("reopen");
+        assert_eq!(heap.root(), small[1]);
+        assert_eq!(heap.heap_end(), heap_end);
+        let mut buf = [0u8; 5];
+        heap.mmap().read_into(big, &mut buf).expect("read");
+        assert_eq!(&buf, b"large");
+        for (i, &off) in small.iter().enumerate().skip(1).step_by(2) {
+            let value: u64 = read_pod(heap.mmap(), off).expect("read");
+            assert_eq!(value, i as u64);
+        }
+        // Freed blocks are handed out again before the heap grows
+        for _ in 0..2500 {
+            let off = heap.alloc(24, 8).expect("alloc");
+            assert!(small.contains(&off));
+        }
+        assert_eq!(heap.heap_end(), heap_end);
+
+        fs::remove_file(&path).expect("cleanup");
+    }
+
+    #[test]
+    fn test_open_rejects_invalid_files() {
+        let path = tmp_path("invalid");
+        let _ = fs::remove_file(&path);
+
+        crate::create_mmap(&path, 4096).expect("create");
+        assert!(matches!(
+            MmapAllocator::open(&path),
+            Err(MmapIoError::InvalidFormat(_))
+        ));
+
+        fs::remove_file(&path).expect("cleanup");
+    }
+}
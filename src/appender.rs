@@ -0,0 +1,561 @@
+//! Lock-free concurrent append reservation with a commit watermark.
+//!
+//! Layout:
+//!
+//! ```text
+//! header   magic "MMAPAPND" (8) | version (4) | reserved (4) | tail (8) | committed (8) | reserved (32)
+//! data     reserved slots, back to back
+//! ```
+//!
+//! A writer reserves space with one `fetch_add` on the tail and then writes its slot while
+//! holding only the mapping's read lock, so writers never wait for each other. A reservation
+//! that ends past the file length grows the file; a mutex makes sure only one thread resizes
+//! for any given shortfall, and the rest find the file already large enough.
+//!
+//! Slots may be written in any order, but they commit in reservation order: a slot waits for
+//! every earlier slot to commit and then advances the committed watermark to its own end.
+//! Everything below the watermark is complete. On open, reservations that never committed
+//! are discarded by resetting the tail to the watermark.
+
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+use parking_lot::Mutex;
+
+use crate::errors::{MmapIoError, Result};
+use crate::mmap::MemoryMappedFile;
+use crate::segment::SegmentMut;
+
+const MAGIC: [u8; 8] = *b"MMAPAPND";
+const VERSION: u32 = 1;
+const HEADER_LEN: u64 = 64;
+const TAIL_OFFSET: usize = 16;
+const COMMITTED_OFFSET: usize = 24;
+/// Busy-wait iterations before a waiting commit starts yielding.
+const SPIN_LIMIT: u32 = 64;
+
+/// Appender that lets many threads reserve and fill slots of one mapped file concurrently.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::sync::Arc;
+/// use std::thread;
+/// use mmap_io::appender::ConcurrentAppender;
+///
+/// let appender = Arc::new(ConcurrentAppender::create("ingest.bin", 1 << 20)?);
+/// let workers: Vec<_> = (0..4u64)
+///     .map(|id| {
+///         let appender = Arc::clone(&appender);
+///         thread::spawn(move || -> Result<(), mmap_io::MmapIoError> {
+///             let slot = appender.reserve(8)?;
+///             slot.write(&id.to_le_bytes())?;
+///             slot.commit()
+///         })
+///     })
+///     .collect();
+/// for worker in workers {
+///     worker.join().expect("worker panicked")?;
+/// }
+/// assert_eq!(appender.committed()?, appender.data_start() + 32);
+/// appender.flush()?;
+/// # Ok::<(), mmap_io::MmapIoError>(())
+/// ```
+#[derive(Debug)]
+pub struct ConcurrentAppender {
+    file: Arc<MemoryMappedFile>,
+    grow: Mutex<()>,
+    poisoned: AtomicBool,
+}
+
+impl ConcurrentAppender {
+    /// Create a new appender at `path` with room for `capacity` data bytes before the first
+    /// growth (truncating any existing file).
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from `MemoryMappedFile::create_rw`, `update_region` or `flush`.
+    pub fn create<P: AsRef<Path>>(path: P, capacity: u64) -> Result<Self> {
+        let file = MemoryMappedFile::create_rw(path, HEADER_LEN + capacity)?;
+        let mut header = [0u8; HEADER_LEN as usize];
+        header[..8].copy_from_slice(&MAGIC);
+        header[8..12].copy_from_slice(&VERSION.to_le_bytes());
+        header[TAIL_OFFSET..TAIL_OFFSET + 8].copy_from_slice(&HEADER_LEN.to_le_bytes());
+        header[COMMITTED_OFFSET..COMMITTED_OFFSET + 8].copy_from_slice(&HEADER_LEN.to_le_bytes());
+        file.update_region(0, &header)?;
+        file.flush()?;
+        Ok(Self::new(file))
+    }
+
+    /// Open an existing appender, discarding reservations that were never committed.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidFormat` if the header is missing, unsupported or
+    /// inconsistent with the file length.
+    /// Returns errors from `MemoryMappedFile::open_rw`, `update_region` or `flush_range`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = MemoryMappedFile::open_rw(path)?;
+        let total = file.current_len()?;
+        if total < HEADER_LEN {
+            return Err(invalid("file too small for appender header"));
+        }
+        let mut header = [0u8; HEADER_LEN as usize];
+        file.read_into(0, &mut header)?;
+        if header[..8] != MAGIC || read_u32(&header, 8) != VERSION {
+            return Err(invalid("missing or unsupported appender header"));
+        }
+        let committed = read_u64(&header, COMMITTED_OFFSET);
+        if committed < HEADER_LEN || committed > total {
+            return Err(invalid("appender watermark outside the file"));
+        }
+        if read_u64(&header, TAIL_OFFSET) != committed {
+            file.update_region(TAIL_OFFSET as u64, &committed.to_le_bytes())?;
+            file.flush_range(0, HEADER_LEN)?;
+        }
+        Ok(Self::new(file))
+    }
+
+    fn new(file: MemoryMappedFile) -> Self {
+        Self {
+            file: Arc::new(file),
+            grow: Mutex::new(()),
+            poisoned: AtomicBool::new(false),
+        }
+    }
+
+    /// Offset of the first data byte.
+    #[must_use]
+    pub fn data_start(&self) -> u64 {
+        HEADER_LEN
+    }
+
+    /// Offset just past the last reserved byte, committed or not.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::Truncated` if the mapping refuses access after a recovered
+    /// `SIGBUS`.
+    pub fn reserved(&self) -> Result<u64> {
+        self.counters(|tail, _| tail.load(Ordering::Acquire))
+    }
+
+    /// Commit watermark: every byte below this offset belongs to a committed slot.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::Truncated` if the mapping refuses access after a recovered
+    /// `SIGBUS`.
+    pub fn committed(&self) -> Result<u64> {
+        self.counters(|_, committed| committed.load(Ordering::Acquire))
+    }
+
+    /// Current file length.
+    #[must_use]
+    pub fn capacity(&self) -> u64 {
+        self.file.len()
+    }
+
+    /// The underlying mapping, e.g. for reading committed data with `read_into`.
+    #[must_use]
+    pub fn mmap(&self) -> &Arc<MemoryMappedFile> {
+        &self.file
+    }
+
+    /// Reserve `len` bytes at the tail and return the slot to fill.
+    ///
+    /// The reservation itself is a single atomic add. If the slot ends past the file length,
+    /// the file is grown to at least twice its length first.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::ResizeFailed` if an earlier reservation could not grow the file.
+    /// Returns errors from `MemoryMappedFile::resize`; the appender is then unusable, because
+    /// the failed slot leaves a gap the watermark cannot pass.
+    /// Returns `MmapIoError::Truncated` if the mapping refuses access after a recovered
+    /// `SIGBUS`.
+    pub fn reserve(&self, len: u64) -> Result<AppendSlot<'_>> {
+        self.check_poisoned()?;
+        let offset = self.counters(|tail, _| tail.fetch_add(len, Ordering::AcqRel))?;
+        let end = offset + len;
+        let segment = self
+            .ensure_capacity(end)
+            .and_then(|()| SegmentMut::new(Arc::clone(&self.file), offset, len))
+            .inspect_err(|_| self.poisoned.store(true, Ordering::Release))?;
+        Ok(AppendSlot {
+            appender: self,
+            segment,
+            committed: false,
+        })
+    }
+
+    /// Flush the file, including the header with the commit watermark.
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from `MemoryMappedFile::flush`.
+    pub fn flush(&self) -> Result<()> {
+        self.file.flush()
+    }
+
+    fn ensure_capacity(&self, end: u64) -> Result<()> {
+        if end <= self.file.current_len()? {
+            return Ok(());
+        }
+        let _grow = self.grow.lock();
+        // Another thread may have grown the file while this one waited
+        let total = self.file.current_len()?;
+        if end > total {
+            self.file.resize(end.max(total.saturating_mul(2)))?;
+        }
+        Ok(())
+    }
+
+    /// Wait until every slot before `offset` has committed, then publish `end`.
+    fn commit(&self, offset: u64, end: u64) -> Result<()> {
+        let mut spins = 0u32;
+        loop {
+            let published = self.counters(|_, committed| {
+                committed
+                    .compare_exchange(offset, end, Ordering::AcqRel, Ordering::Acquire)
+                    .is_ok()
+            })?;
+            if published {
+                return Ok(());
+            }
+            self.check_poisoned()?;
+            if spins < SPIN_LIMIT {
+                spins += 1;
+                std::hint::spin_loop();
+            } else {
+                thread::yield_now();
+            }
+        }
+    }
+
+    fn check_poisoned(&self) -> Result<()> {
+        if self.poisoned.load(Ordering::Acquire) {
+            return Err(MmapIoError::ResizeFailed(
+                "an earlier reservation failed to grow the appender".into(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Run `f` on the tail and watermark counters in the header.
+    fn counters<R>(&self, f: impl FnOnce(&AtomicU64, &AtomicU64) -> R) -> Result<R> {
+        self.file.with_shared_ptr(|base, _| {
+            // SAFETY: the header is at least HEADER_LEN bytes (checked on create and open,
+            // and the file never shrinks), the mapping is page-aligned so both offsets are
+            // 8-byte aligned, and the read lock held by `with_shared_ptr` keeps the mapping
+            // in place while the references are used. The counters are only ever accessed
+            // atomically.
+            let (tail, committed) = unsafe {
+                (
+                    &*base.add(TAIL_OFFSET).cast::<AtomicU64>(),
+                    &*base.add(COMMITTED_OFFSET).cast::<AtomicU64>(),
+                )
+            };
+            f(tail, committed)
+        })
+    }
+}
+
+/// A reserved range of a [`ConcurrentAppender`], committed when [`commit`](Self::commit) is
+/// called or the slot is dropped.
+///
+/// Unwritten bytes of a committed slot read as whatever the file held before, usually zeros.
+/// A slot that is leaked instead of dropped blocks the watermark and every later commit.
+#[derive(Debug)]
+pub struct AppendSlot<'a> {
+    appender: &'a ConcurrentAppender,
+    segment: SegmentMut,
+    committed: bool,
+}
+
+impl AppendSlot<'_> {
+    /// Offset of the slot in the file.
+    #[must_use]
+    pub fn offset(&self) -> u64 {
+        self.segment.offset()
+    }
+
+    /// Length of the slot.
+    #[must_use]
+    pub fn len(&self) -> u64 {
+        self.segment.len()
+    }
+
+    /// Whether the slot is empty.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.segment.is_empty()
+    }
+
+    /// The slot as a segment. Its `write` takes the mapping's write lock; prefer
+    /// [`write_at`](Self::write_at) on hot paths.
+    #[must_use]
+    pub fn segment(&self) -> &SegmentMut {
+        &self.segment
+    }
+
+    /// Write `data` at the start of the slot.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::OutOfBounds` if `data` is longer than the slot.
+    pub fn write(&self, data: &[u8]) -> Result<()> {
+        self.write_at(0, data)
+    }
+
+    /// Write `data` at `pos` bytes into the slot, holding only the mapping's read lock.
+    ///
+    /// Writes go straight to the mapping and are not counted by the mapping's flush policy.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::OutOfBounds` if the write does not fit in the slot.
+    pub fn write_at(&self, pos: u64, data: &[u8]) -> Result<()> {
+        let len = data.len() as u64;
+        if pos.checked_add(len).map_or(true, |end| end > self.len()) {
+            return Err(MmapIoError::OutOfBounds {
+                offset: pos,
+                len,
+                total: self.len(),
+            });
+        }
+        let start = self.offset() + pos;
+        self.appender.file.with_shared_ptr(|base, map_len| {
+            debug_assert!(start + len <= map_len as u64);
+            // SAFETY: the range lies inside this slot, which `reserve` made sure fits in the
+            // file, and no other slot overlaps it. The read lock held by `with_shared_ptr`
+            // keeps the mapping from being replaced during the copy.
+            unsafe {
+                std::ptr::copy_nonoverlapping(data.as_ptr(), base.add(start as usize), data.len());
+            }
+        })
+    }
+
+    /// Mark the slot complete. Blocks until every earlier slot has committed, then advances
+    /// the watermark past this slot.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::ResizeFailed` if an earlier slot can never commit because its
+    /// growth failed.
+    /// Returns `MmapIoError::Truncated` if the mapping refuses access after a recovered
+    /// `SIGBUS`.
+    pub fn commit(mut self) -> Result<()> {
+        self.committed = true;
+        self.appender
+            .commit(self.offset(), self.offset() + self.len())
+    }
+}
+
+impl Drop for AppendSlot<'_> {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = self
+                .appender
+                .commit(self.offset(), self.offset() + self.len());
+        }
+    }
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().expect("8 bytes"))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().expect("4 bytes"))
+}
+
+fn invalid(msg: &str) -> MmapIoError {
+    MmapIoError::InvalidFormat(msg.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    fn tmp_path(name: &str) -> PathBuf {
+        let mut p = std::env::temp_dir();
+        p.push(format!(
+            "mmap_io_appender_test_{}_{}",
+            name,
+            std::process::id()
+        ));
+        p
+    }
+
+    #[test]
+    fn test_concurrent_reservations_grow_and_commit() {
+        let path = tmp_path("concurrent");
+        let _ = fs::remove_file(&path);
+
+        const THREADS: u64 = 8;
+        const PER_THREAD: u64 = 2000;
+        // Far too small, so several growths happen while threads are writing
+        let appender = ConcurrentAppender::create(&path, 256).expect("create");
+        thread::scope(|s| {
+            for t in 0..THREADS {
+                let appender = &appender;
+                s.spawn(move || {
+                    for i in 0..PER_THREAD {
+                        let slot = appender.reserve(16).expect("reserve");
+                        slot.write(&t.to_le_bytes()).expect("write");
+                        slot.write_at(8, &i.to_le_bytes()).expect("write_at");
+                        slot.commit().expect("commit");
+                    }
+                });
+            }
+        });
+
+        let end = HEADER_LEN + THREADS * PER_THREAD * 16;
+        assert_eq!(appender.reserved().expect("counter"), end);
+        assert_eq!(appender.committed().expect("counter"), end);
+        assert!(appender.capacity() >= end);
+
+        let mut seen = vec![0u64; THREADS as usize];
+        let mut record = [0u8; 16];
+        for offset in (HEADER_LEN..end).step_by(16) {
+            appender
+                .mmap()
+                .read_into(offset, &mut record)
+                .expect("read");
+            let t = read_u64(&record, 0) as usize;
+            // Each thread's records appear in the order it wrote them
+            assert_eq!(read_u64(&record, 8), seen[t]);
+            seen[t] += 1;
+        }
+        assert!(seen.iter().all(|&n| n == PER_THREAD));
+
+        drop(appender);
+        fs::remove_file(&path).expect("cleanup");
+    }
+
+    #[test]
+    fn test_watermark_waits_for_earlier_slots() {
+        let path = tmp_path("watermark");
+        let _ = fs::remove_file(&path);
+
+        let appender = ConcurrentAppender::create(&path, 4096).expect("create");
+        let first = appender.reserve(100).expect("reserve");
+        let second = appender.reserve(50).expect("reserve");
+        second.write(b"second").expect("write");
+
+        thread::scope(|s| {
+            let waiter = s.spawn(|| second.commit());
+            thread::sleep(Duration::from_millis(50));
+            assert_eq!(appender.committed().expect("counter"), HEADER_LEN);
+            assert!(!waiter.is_finished());
+
+            first.write(b"first").expect("write");
+            first.commit().expect("commit");
+            waiter.join().expect("join").expect("commit");
+        });
+        assert_eq!(appender.committed().expect("counter"), HEADER_LEN + 150);
+
+        // Dropping a slot commits it too
+        drop(appender.reserve(10).expect("reserve"));
+        assert_eq!(appender.committed().expect("counter"), HEADER_LEN + 160);
+
+        drop(appender);
+        fs::remove_file(&path).expect("cleanup");
+    }
+
+    #[test]
+    fn test_reopen_discards_uncommitted_reservations() {
+        let path = tmp_path("reopen");
+        let _ = fs::remove_file(&path);
+
+        let appender = ConcurrentAppender::create(&path, 4096).expect("create");
+        appender
+            .reserve(32)
+            .expect("reserve")
+            .commit()
+            .expect("commit");
+        // Simulate a writer that died between reserving and committing
+        std::mem::forget(appender.reserve(64).expect("reserve"));
+        assert_eq!(appender.reserved().expect("counter"), HEADER_LEN + 96);
+        appender.flush().expect("flush");
+        drop(appender);
+
+        let appender = ConcurrentAppender::open(&path).expect("open");
+        assert_eq!(appender.committed().expect("counter"), HEADER_LEN + 32);
+        assert_eq!(appender.reserved().expect("counter"), HEADER_LEN + 32);
+        let slot = appender.reserve(8).expect("reserve");
+        assert_eq!(slot.offset(), HEADER_LEN + 32);
+        slot.commit().expect("commit");
+
+        drop(appender);
+        fs::remove_file(&path).expect("cleanup");
+    }
+
+    #[test]
+    fn test_slot_bounds() {
+        let path = tmp_path("bounds");
+        let _ = fs::remove_file(&path);
+
+        let appender = ConcurrentAppender::create(&path, 4096).expect("create");
+        let slot = appender.reserve(8).expect("reserve");
+        assert!(matches!(
+            slot.write(&[0u8; 9]),
+            Err(MmapIoError::OutOfBounds { .. })
+        ));
+        assert!(matches!(
+            slot.write_at(4, &[0u8; 5]),
+            Err(MmapIoError::OutOfBounds { .. })
+        ));
+        slot.write_at(4, &[1u8; 4]).expect("write_at");
+        assert_eq!(slot.segment().len(), 8);
+        drop(slot);
+
+        drop(appender);
+        fs::remove_file(&path).expect("cleanup");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_truncated_file_fails_instead_of_panicking() {
+        let path = tmp_path("truncated");
+        let _ = fs::remove_file(&path);
+        let page = crate::utils::page_size() as u64;
+
+        let appender = ConcurrentAppender::create(&path, 4 * page).expect("create");
+        let slot = appender.reserve(8).expect("reserve");
+        fs::OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .and_then(|f| f.set_len(page))
+            .expect("truncate");
+        crate::sigbus::install_handler().expect("install handler");
+        let mut buf = [0u8; 8];
+        assert!(matches!(
+            appender.mmap().read_into(2 * page, &mut buf),
+            Err(MmapIoError::Truncated { .. })
+        ));
+
+        assert!(matches!(
+            appender.reserve(8),
+            Err(MmapIoError::Truncated { .. })
+        ));
+        assert!(matches!(
+            appender.committed(),
+            Err(MmapIoError::Truncated { .. })
+        ));
+        assert!(matches!(
+            slot.write(b"lost"),
+            Err(MmapIoError::Truncated { .. })
+        ));
+        assert!(matches!(slot.commit(), Err(MmapIoError::Truncated { .. })));
+
+        drop(appender);
+        fs::remove_file(&path).expect("cleanup");
+    }
+}
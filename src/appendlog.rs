@@ -0,0 +1,506 @@
+//! Append-only log of length-prefixed, checksummed records.
+//!
+//! Layout:
+//!
+//! ```text
+//! header   magic "MMAPALOG" (8) | version (4) | reserved (4)
+//! records  length (4) | CRC-32C of length and bytes (4) | bytes, each record 8-byte aligned
+//! ```
+//!
+//! The end of the log is not stored anywhere. [`AppendLog::open`] walks the records from the
+//! start and stops at the first one whose length or checksum does not match, which is where
+//! a crash interrupted an append. Everything after that point is zeroed, so stale bytes are
+//! never read back as records once new ones are appended over them. The file grows ahead of
+//! the data according to a [`LogGrowth`] policy, so most appends do not call `resize`.
+
+use std::path::Path;
+
+use crate::errors::{MmapIoError, Result};
+use crate::hash::crc32c;
+use crate::mmap::{MappedSlice, MemoryMappedFile};
+use crate::utils::align_up;
+
+const MAGIC: [u8; 8] = *b"MMAPALOG";
+const VERSION: u32 = 1;
+const HEADER_LEN: u64 = 16;
+const RECORD_HEADER_LEN: u64 = 8;
+const RECORD_ALIGN: u64 = 8;
+const INITIAL_FILE_LEN: u64 = 64 * 1024;
+
+/// How an [`AppendLog`] grows its file when an append does not fit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogGrowth {
+    /// Double the file length, or grow to fit the record if that is larger.
+    #[default]
+    Double,
+    /// Grow by whole extents of this many bytes. `Extent(0)` grows exactly to fit.
+    Extent(u64),
+}
+
+/// Append-only record log over a growable mapped file.
+///
+/// Appends are not flushed; call [`flush`](Self::flush) to make them durable. Records that
+/// were only partly written when the process died are dropped on the next open.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mmap_io::appendlog::{AppendLog, LogGrowth};
+///
+/// let mut log = AppendLog::create("events.log")?.growth(LogGrowth::Extent(1 << 20));
+/// let first = log.append(b"started")?;
+/// log.append(b"stopped")?;
+/// log.flush()?;
+///
+/// for record in log.iter_from(first)? {
+///     let (offset, bytes) = record?;
+///     println!("{offset}: {} bytes", bytes.len());
+/// }
+/// # Ok::<(), mmap_io::MmapIoError>(())
+/// ```
+#[derive(Debug)]
+pub struct AppendLog {
+    file: MemoryMappedFile,
+    growth: LogGrowth,
+    end: u64,
+    records: u64,
+}
+
+impl AppendLog {
+    /// Create a new, empty log at `path` (truncating any existing file).
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from `MemoryMappedFile::create_rw`, `update_region` or `flush`.
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = MemoryMappedFile::create_rw(path, INITIAL_FILE_LEN)?;
+        let mut header = [0u8; HEADER_LEN as usize];
+        header[..8].copy_from_slice(&MAGIC);
+        header[8..12].copy_from_slice(&VERSION.to_le_bytes());
+        file.update_region(0, &header)?;
+        file.flush()?;
+        Ok(Self {
+            file,
+            growth: LogGrowth::default(),
+            end: HEADER_LEN,
+            records: 0,
+        })
+    }
+
+    /// Open an existing log, keeping every record up to the first torn or corrupt one and
+    /// zeroing the rest of the file.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidFormat` if the file header is missing or unsupported.
+    /// Returns errors from `MemoryMappedFile::open_rw` or from zeroing the torn tail.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = MemoryMappedFile::open_rw(path)?;
+        let total = file.current_len()?;
+        if total < HEADER_LEN {
+            return Err(invalid("file too small for append log header"));
+        }
+        let mut header = [0u8; HEADER_LEN as usize];
+        file.read_into(0, &mut header)?;
+        if header[..8] != MAGIC || read_u32(&header, 8) != VERSION {
+            return Err(invalid("missing or unsupported append log header"));
+        }
+        let mut log = Self {
+            file,
+            growth: LogGrowth::default(),
+            end: HEADER_LEN,
+            records: 0,
+        };
+        loop {
+            let Some(data) = log.record_at(log.end, total)? else {
+                break;
+            };
+            let len = data.len() as u64;
+            drop(data);
+            log.end = record_end(log.end, len);
+            log.records += 1;
+        }
+        log.discard_tail(total)?;
+        Ok(log)
+    }
+
+    /// Set the growth policy (builder style).
+    #[must_use]
+    pub fn growth(mut self, policy: LogGrowth) -> Self {
+        self.growth = policy;
+        self
+    }
+
+    /// Number of records in the log.
+    #[must_use]
+    pub fn len(&self) -> u64 {
+        self.records
+    }
+
+    /// Whether the log holds no records.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.records == 0
+    }
+
+    /// Offset just past the last record; the next append is written here.
+    #[must_use]
+    pub fn end(&self) -> u64 {
+        self.end
+    }
+
+    /// Current file length, including space reserved for future appends.
+    #[must_use]
+    pub fn capacity(&self) -> u64 {
+        self.file.len()
+    }
+
+    /// The underlying mapping.
+    #[must_use]
+    pub fn mmap(&self) -> &MemoryMappedFile {
+        &self.file
+    }
+
+    /// Append `data` as one record and return the record's offset.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidMode` if `data` is longer than `u32::MAX` bytes.
+    /// Returns errors from `MemoryMappedFile::resize` or `update_region`.
+    pub fn append(&mut self, data: &[u8]) -> Result<u64> {
+        let len = u32::try_from(data.len())
+            .map_err(|_| MmapIoError::InvalidMode("record longer than u32::MAX bytes"))?;
+        let offset = self.end;
+        let end = record_end(offset, u64::from(len));
+        self.reserve(end)?;
+        if !data.is_empty() {
+            self.file.update_region(offset + RECORD_HEADER_LEN, data)?;
+        }
+        let mut header = [0u8; RECORD_HEADER_LEN as usize];
+        header[..4].copy_from_slice(&len.to_le_bytes());
+        header[4..].copy_from_slice(&checksum(len, data).to_le_bytes());
+        self.file.update_region(offset, &header)?;
+        self.end = end;
+        self.records += 1;
+        Ok(offset)
+    }
+
+    /// The bytes of the record at `offset`, as returned by [`append`](Self::append) or the
+    /// iterators.
+    ///
+    /// The view holds the mapping's read lock until it is dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::OutOfBounds` if `offset` is at or past the end of the log.
+    /// Returns `MmapIoError::Misaligned` or `MmapIoError::InvalidFormat` if no record
+    /// starts at `offset`.
+    pub fn read(&self, offset: u64) -> Result<MappedSlice<'_>> {
+        self.check_offset(offset)?;
+        if offset == self.end {
+            return Err(MmapIoError::OutOfBounds {
+                offset,
+                len: RECORD_HEADER_LEN,
+                total: self.end,
+            });
+        }
+        self.record_at(offset, self.end)?
+            .ok_or_else(|| invalid("no valid record at offset"))
+    }
+
+    /// Iterate over all records as `(offset, bytes)` pairs.
+    #[must_use]
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            log: self,
+            pos: HEADER_LEN,
+        }
+    }
+
+    /// Iterate over the records starting with the one at `offset`. An offset equal to
+    /// [`end`](Self::end) yields nothing, so a reader can resume from where it stopped.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::OutOfBounds` if `offset` is past the end of the log.
+    /// Returns `MmapIoError::Misaligned` or `MmapIoError::InvalidFormat` if no record
+    /// starts at `offset`.
+    pub fn iter_from(&self, offset: u64) -> Result<Iter<'_>> {
+        self.check_offset(offset)?;
+        if offset < self.end && self.record_at(offset, self.end)?.is_none() {
+            return Err(invalid("no valid record at offset"));
+        }
+        Ok(Iter {
+            log: self,
+            pos: offset,
+        })
+    }
+
+    /// Flush appended records to disk.
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from `MemoryMappedFile::flush`.
+    pub fn flush(&self) -> Result<()> {
+        self.file.flush()
+    }
+
+    /// Grow the file so that `end` fits, following the growth policy.
+    fn reserve(&self, end: u64) -> Result<()> {
+        let total = self.file.current_len()?;
+        if end <= total {
+            return Ok(());
+        }
+        let new_len = match self.growth {
+            LogGrowth::Double => end.max(total.saturating_mul(2)),
+            LogGrowth::Extent(0) => end,
+            LogGrowth::Extent(step) => total.saturating_add((end - total).div_ceil(step) * step),
+        };
+        self.file.resize(new_len)
+    }
+
+    /// The record at `offset` if a complete record with a matching checksum ends before
+    /// `limit`.
+    fn record_at(&self, offset: u64, limit: u64) -> Result<Option<MappedSlice<'_>>> {
+        if offset + RECORD_HEADER_LEN > limit {
+            return Ok(None);
+        }
+        let mut header = [0u8; RECORD_HEADER_LEN as usize];
+        self.file.read_into(offset, &mut header)?;
+        let len = read_u32(&header, 0);
+        let start = offset + RECORD_HEADER_LEN;
+        if start + u64::from(len) > limit {
+            return Ok(None);
+        }
+        // The checksum covers the length too, so zeroed space never passes as an empty record
+        let data = self.file.as_slice_guarded(start, u64::from(len))?;
+        Ok((checksum(len, &data) == read_u32(&header, 4)).then_some(data))
+    }
+
+    fn check_offset(&self, offset: u64) -> Result<()> {
+        if offset > self.end {
+            return Err(MmapIoError::OutOfBounds {
+                offset,
+                len: 0,
+                total: self.end,
+            });
+        }
+        if offset % RECORD_ALIGN != 0 {
+            return Err(MmapIoError::Misaligned {
+                required: RECORD_ALIGN,
+                offset,
+            });
+        }
+        if offset < HEADER_LEN {
+            return Err(invalid("offset inside the append log header"));
+        }
+        Ok(())
+    }
+
+    /// Zero any non-zero bytes between the end of the log and `total`.
+    fn discard_tail(&self, total: u64) -> Result<()> {
+        let dirty = self
+            .file
+            .as_slice_guarded(self.end, total - self.end)?
+            .iter()
+            .rposition(|&b| b != 0);
+        if let Some(last) = dirty {
+            let len = last as u64 + 1;
+            self.file.as_slice_mut(self.end, len)?.as_mut().fill(0);
+            self.file.flush_range(self.end, len)?;
+        }
+        Ok(())
+    }
+}
+
+/// Iterator over `(offset, bytes)` pairs of an [`AppendLog`].
+pub struct Iter<'a> {
+    log: &'a AppendLog,
+    pos: u64,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = Result<(u64, MappedSlice<'a>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos >= self.log.end {
+            return None;
+        }
+        let offset = self.pos;
+        match self.log.record_at(offset, self.log.end) {
+            Ok(Some(data)) => {
+                self.pos = record_end(offset, data.len() as u64);
+                Some(Ok((offset, data)))
+            }
+            Ok(None) => {
+                self.pos = self.log.end;
+                Some(Err(invalid("corrupt record inside the append log")))
+            }
+            Err(e) => {
+                self.pos = self.log.end;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+fn record_end(offset: u64, len: u64) -> u64 {
+    align_up(offset + RECORD_HEADER_LEN + len, RECORD_ALIGN)
+}
+
+fn checksum(len: u32, data: &[u8]) -> u32 {
+    crc32c(crc32c(0, &len.to_le_bytes()), data)
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().expect("4 bytes"))
+}
+
+fn invalid(msg: &str) -> MmapIoError {
+    MmapIoError::InvalidFormat(msg.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn tmp_path(name: &str) -> PathBuf {
+        let mut p = std::env::temp_dir();
+        p.push(format!(
+            "mmap_io_appendlog_test_{}_{}",
+            name,
+            std::process::id()
+        ));
+        p
+    }
+
+    #[test]
+    fn test_append_iterate_and_reopen() {
+        let path = tmp_path("reopen");
+        let _ = fs::remove_file(&path);
+
+        let mut log = AppendLog::create(&path).expect("create");
+        let records: Vec<Vec<u8>> = (0..500u32)
+            .map(|i| vec![i as u8; (i % 37) as usize])
+            .collect();
+        let offsets: Vec<u64> = records
+            .iter()
+            .map(|r| log.append(r).expect("append"))
+            .collect();
+        assert_eq!(log.len(), 500);
+        assert_eq!(&*log.read(offsets[42]).expect("read"), &records[42][..]);
+        log.flush().expect("flush");
+        let end = log.end();
+        drop(log);
+
+        let log = AppendLog::open(&path).expect("open");
+        assert_eq!(log.len(), 500);
+        assert_eq!(log.end(), end);
+        for (i, record) in log.iter().enumerate() {
+            let (offset, bytes) = record.expect("record");
+            assert_eq!(offset, offsets[i]);
+            assert_eq!(&*bytes, &records[i][..]);
+        }
+
+        fs::remove_file(&path).expect("cleanup");
+    }
+
+    #[test]
+    fn test_iter_from_and_offset_checks() {
+        let path = tmp_path("iter_from");
+        let _ = fs::remove_file(&path);
+
+        let mut log = AppendLog::create(&path).expect("create");
+        let a = log.append(b"alpha").expect("append");
+        let b = log.append(b"beta").expect("append");
+        let c = log.append(b"").expect("append");
+
+        let tail: Vec<(u64, Vec<u8>)> = log
+            .iter_from(b)
+            .expect("iter_from")
+            .map(|r| {
+                r.map(|(offset, bytes)| (offset, bytes.to_vec()))
+                    .expect("record")
+            })
+            .collect();
+        assert_eq!(tail, vec![(b, b"beta".to_vec()), (c, Vec::new())]);
+        assert_eq!(log.iter_from(log.end()).expect("at end").count(), 0);
+
+        assert!(matches!(
+            log.iter_from(a + 1),
+            Err(MmapIoError::Misaligned { .. })
+        ));
+        assert!(matches!(
+            log.read(a + 8),
+            Err(MmapIoError::InvalidFormat(_))
+        ));
+        assert!(matches!(
+            log.read(log.end()),
+            Err(MmapIoError::OutOfBounds { .. })
+        ));
+
+        drop(log);
+        fs::remove_file(&path).expect("cleanup");
+    }
+
+    #[test]
+    fn test_torn_tail_is_discarded() {
+        let path = tmp_path("torn");
+        let _ = fs::remove_file(&path);
+
+        let mut log = AppendLog::create(&path).expect("create");
+        log.append(b"first").expect("append");
+        let second = log.append(b"second record").expect("append");
+        let third = log.append(b"third").expect("append");
+        // Simulate a crash that left a corrupt byte in the second record
+        log.mmap().update_region(second + 9, b"X").expect("corrupt");
+        log.flush().expect("flush");
+        drop(log);
+
+        let mut log = AppendLog::open(&path).expect("open");
+        assert_eq!(log.len(), 1);
+        assert_eq!(log.end(), second);
+        let mut stale = [0xffu8; 32];
+        log.mmap().read_into(second, &mut stale).expect("read tail");
+        assert!(stale.iter().all(|&b| b == 0));
+
+        // A short record over the old tail must not bring the third record back
+        assert_eq!(log.append(b"new").expect("append"), second);
+        log.flush().expect("flush");
+        drop(log);
+        let log = AppendLog::open(&path).expect("reopen");
+        assert_eq!(log.len(), 2);
+        assert!(log.end() < third);
+
+        fs::remove_file(&path).expect("cleanup");
+    }
+
+    #[test]
+    fn test_growth_policies() {
+        let path = tmp_path("growth");
+        let _ = fs::remove_file(&path);
+
+        let mut log = AppendLog::create(&path)
+            .expect("create")
+            .growth(LogGrowth::Extent(10_000));
+        let record = vec![7u8; 1000];
+        while log.end() + 1008 <= INITIAL_FILE_LEN {
+            log.append(&record).expect("append");
+        }
+        log.append(&record).expect("append past capacity");
+        assert_eq!(log.capacity(), INITIAL_FILE_LEN + 10_000);
+
+        let mut log = log.growth(LogGrowth::Double);
+        while log.end() + 1008 <= INITIAL_FILE_LEN + 10_000 {
+            log.append(&record).expect("append");
+        }
+        log.append(&record).expect("append past capacity");
+        assert_eq!(log.capacity(), 2 * (INITIAL_FILE_LEN + 10_000));
+        assert!(log.iter().all(|r| r.expect("record").1.len() == 1000));
+
+        drop(log);
+        fs::remove_file(&path).expect("cleanup");
+    }
+}
@@ -0,0 +1,533 @@
+//! Indexed pack files holding many named blobs, e.g. game assets.
+//!
+//! File layout:
+//!
+//! ```text
+//! header     magic "MMAPPACK" (8) | version (4) | reserved (4) | directory offset (8)
+//!            | slot count (8) | entry count (8) | file length (8)
+//! data       blobs, each starting at its requested alignment
+//! directory  open-addressed hash table of 32-byte slots:
+//!            name hash (8) | name offset (8, 0 = empty) | data offset (8) | data length (8)
+//! names      name length (u32) | UTF-8 name bytes, referenced from the slots
+//! ```
+//!
+//! Integers are little-endian. Names are hashed with 64-bit FNV-1a and probed linearly, so
+//! a lookup touches one or two slots regardless of how many entries the pack holds.
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::errors::{MmapIoError, Result};
+use crate::hash::fnv1a64;
+use crate::mmap::{MemoryMappedFile, MmapMode};
+use crate::segment::Segment;
+use crate::utils::align_up;
+
+const MAGIC: [u8; 8] = *b"MMAPPACK";
+const VERSION: u32 = 1;
+const HEADER_LEN: u64 = 48;
+const SLOT_LEN: u64 = 32;
+const INITIAL_FILE_LEN: u64 = 64 * 1024;
+
+/// Appends named blobs to a new pack file and writes the directory on [`finish`].
+///
+/// [`finish`]: ArchiveWriter::finish
+///
+/// # Examples
+///
+/// ```no_run
+/// use mmap_io::archive::ArchiveWriter;
+///
+/// let mut writer = ArchiveWriter::create("assets.pack")?;
+/// writer.add("shaders/basic.wgsl", b"@vertex fn main() {}")?;
+/// writer.add_aligned("textures/grass.ktx2", &[0u8; 10_000], 4096)?;
+/// let pack = writer.finish()?;
+///
+/// let grass = pack.get("textures/grass.ktx2")?.expect("missing asset");
+/// assert_eq!(grass.offset() % 4096, 0);
+/// # Ok::<(), mmap_io::MmapIoError>(())
+/// ```
+#[derive(Debug)]
+pub struct ArchiveWriter {
+    mmap: MemoryMappedFile,
+    pos: u64,
+    alignment: u64,
+    entries: Vec<(String, u64, u64)>,
+    names: HashSet<String>,
+}
+
+impl ArchiveWriter {
+    /// Start a new pack at `path` (truncating if it exists).
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from `MemoryMappedFile::create_rw`.
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self {
+            mmap: MemoryMappedFile::create_rw(path, INITIAL_FILE_LEN)?,
+            pos: HEADER_LEN,
+            alignment: 1,
+            entries: Vec::new(),
+            names: HashSet::new(),
+        })
+    }
+
+    /// Set the alignment used by [`add`](Self::add) (default 1).
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::Misaligned` if `alignment` is not a power of two.
+    pub fn alignment(mut self, alignment: u64) -> Result<Self> {
+        check_alignment(alignment)?;
+        self.alignment = alignment;
+        Ok(self)
+    }
+
+    /// Append `data` under `name` using the default alignment.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidMode` if `name` was already added.
+    /// Returns errors from `MemoryMappedFile::resize` or `update_region`.
+    pub fn add(&mut self, name: &str, data: &[u8]) -> Result<()> {
+        self.add_aligned(name, data, self.alignment)
+    }
+
+    /// Append `data` under `name`, starting at a multiple of `alignment` in the file. Use
+    /// the page size to let individual entries be `advise`d or `lock`ed.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::Misaligned` if `alignment` is not a power of two.
+    /// Returns `MmapIoError::InvalidMode` if `name` was already added or is 4 GiB or longer.
+    /// Returns errors from `MemoryMappedFile::resize` or `update_region`.
+    pub fn add_aligned(&mut self, name: &str, data: &[u8], alignment: u64) -> Result<()> {
+        check_alignment(alignment)?;
+        if u32::try_from(name.len()).is_err() {
+            return Err(MmapIoError::InvalidMode(
+                "archive entry names must be shorter than 4 GiB",
+            ));
+        }
+        if self.names.contains(name) {
+            return Err(MmapIoError::InvalidMode("duplicate archive entry name"));
+        }
+        self.pos = align_up(self.pos, alignment);
+        let offset = self.pos;
+        self.write(data)?;
+        self.names.insert(name.to_owned());
+        self.entries
+            .push((name.to_owned(), offset, data.len() as u64));
+        Ok(())
+    }
+
+    /// Number of entries added so far.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether no entries have been added.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Write the directory and header, trim the file, flush it and open it for reading.
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from `MemoryMappedFile::resize`, `update_region` or `flush`, and from
+    /// [`ArchiveReader::open`].
+    pub fn finish(mut self) -> Result<ArchiveReader> {
+        let dir_offset = align_up(self.pos, 8);
+        let slot_count = (self.entries.len() as u64 * 2).next_power_of_two().max(8);
+        let mut slots = vec![0u8; (slot_count * SLOT_LEN) as usize];
+        let mut names = Vec::new();
+        let names_offset = dir_offset + slot_count * SLOT_LEN;
+        for (name, offset, len) in &self.entries {
+            let hash = fnv1a64(name.as_bytes());
+            let mut slot = hash & (slot_count - 1);
+            while slots[(slot * SLOT_LEN) as usize + 8..][..8] != [0; 8] {
+                slot = (slot + 1) & (slot_count - 1);
+            }
+            let name_offset = names_offset + names.len() as u64;
+            let record = &mut slots[(slot * SLOT_LEN) as usize..][..SLOT_LEN as usize];
+            record[..8].copy_from_slice(&hash.to_le_bytes());
+            record[8..16].copy_from_slice(&name_offset.to_le_bytes());
+            record[16..24].copy_from_slice(&offset.to_le_bytes());
+            record[24..].copy_from_slice(&len.to_le_bytes());
+            names.extend_from_slice(&(name.len() as u32).to_le_bytes());
+            names.extend_from_slice(name.as_bytes());
+        }
+        self.pos = dir_offset;
+        self.write(&slots)?;
+        self.write(&names)?;
+
+        let mut header = [0u8; HEADER_LEN as usize];
+        header[..8].copy_from_slice(&MAGIC);
+        header[8..12].copy_from_slice(&VERSION.to_le_bytes());
+        header[16..24].copy_from_slice(&dir_offset.to_le_bytes());
+        header[24..32].copy_from_slice(&slot_count.to_le_bytes());
+        header[32..40].copy_from_slice(&(self.entries.len() as u64).to_le_bytes());
+        header[40..].copy_from_slice(&self.pos.to_le_bytes());
+        self.mmap.update_region(0, &header)?;
+        self.mmap.resize(self.pos)?;
+        self.mmap.flush()?;
+        let path = self.mmap.path().to_path_buf();
+        drop(self.mmap);
+        ArchiveReader::open(path)
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        let end = self.pos + bytes.len() as u64;
+        let total = self.mmap.current_len()?;
+        if end > total {
+            self.mmap.resize(end.max(total.saturating_mul(2)))?;
+        }
+        if !bytes.is_empty() {
+            self.mmap.update_region(self.pos, bytes)?;
+        }
+        self.pos = end;
+        Ok(())
+    }
+}
+
+/// Read-only view of a pack file with O(1) lookups by name.
+#[derive(Debug, Clone)]
+pub struct ArchiveReader {
+    mmap: Arc<MemoryMappedFile>,
+    dir_offset: u64,
+    slot_count: u64,
+    entries: u64,
+}
+
+impl ArchiveReader {
+    /// Map the pack at `path` read-only.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidFormat` if the file is not a valid pack.
+    /// Returns errors from `MemoryMappedFile::open_ro`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_mmap(MemoryMappedFile::open_ro(path)?)
+    }
+
+    /// Read a pack from an existing read-only or copy-on-write mapping.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidMode` for read-write mappings.
+    /// Returns `MmapIoError::InvalidFormat` if the header or directory bounds are invalid.
+    pub fn from_mmap(mmap: MemoryMappedFile) -> Result<Self> {
+        if mmap.mode() == MmapMode::ReadWrite {
+            return Err(MmapIoError::InvalidMode(
+                "ArchiveReader requires a ReadOnly or CopyOnWrite mapping",
+            ));
+        }
+        let total = mmap.current_len()?;
+        if total < HEADER_LEN {
+            return Err(invalid("file too small for archive header"));
+        }
+        let header = mmap.as_slice(0, HEADER_LEN)?;
+        if header[..8] != MAGIC || read_u32(header, 8)? != VERSION {
+            return Err(invalid("missing or unsupported archive header"));
+        }
+        let dir_offset = read_u64(header, 16)?;
+        let slot_count = read_u64(header, 24)?;
+        let entries = read_u64(header, 32)?;
+        let file_len = read_u64(header, 40)?;
+        let dir_end = slot_count
+            .checked_mul(SLOT_LEN)
+            .and_then(|len| len.checked_add(dir_offset));
+        if file_len != total
+            || dir_offset < HEADER_LEN
+            || !slot_count.is_power_of_two()
+            || entries >= slot_count
+            || dir_end.map_or(true, |end| end > total)
+        {
+            return Err(invalid("archive header inconsistent with file length"));
+        }
+        Ok(Self {
+            mmap: Arc::new(mmap),
+            dir_offset,
+            slot_count,
+            entries,
+        })
+    }
+
+    /// Number of entries.
+    #[must_use]
+    pub fn len(&self) -> u64 {
+        self.entries
+    }
+
+    /// Whether the pack has no entries.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries == 0
+    }
+
+    /// Underlying mapping.
+    #[must_use]
+    pub fn mmap(&self) -> &Arc<MemoryMappedFile> {
+        &self.mmap
+    }
+
+    /// Data of the entry called `name`, as a segment of the mapping.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidFormat` if the directory is corrupt.
+    pub fn get(&self, name: &str) -> Result<Option<Segment>> {
+        let hash = fnv1a64(name.as_bytes());
+        let mask = self.slot_count - 1;
+        let mut slot = hash & mask;
+        for _ in 0..self.slot_count {
+            let record = self.slot(slot)?;
+            if record.name_offset == 0 {
+                break;
+            }
+            if record.hash == hash && self.name(record.name_offset)? == name {
+                return self.segment(&record).map(Some);
+            }
+            slot = (slot + 1) & mask;
+        }
+        Ok(None)
+    }
+
+    /// Whether an entry called `name` exists.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidFormat` if the directory is corrupt.
+    pub fn contains(&self, name: &str) -> Result<bool> {
+        Ok(self.get(name)?.is_some())
+    }
+
+    /// Iterate over all entries in directory order (not insertion order).
+    pub fn iter(&self) -> impl Iterator<Item = Result<(&str, Segment)>> + '_ {
+        (0..self.slot_count).filter_map(move |slot| {
+            let entry = self.slot(slot).and_then(|record| {
+                if record.name_offset == 0 {
+                    return Ok(None);
+                }
+                Ok(Some((
+                    self.name(record.name_offset)?,
+                    self.segment(&record)?,
+                )))
+            });
+            entry.transpose()
+        })
+    }
+
+    fn slot(&self, slot: u64) -> Result<Slot> {
+        let bytes = self
+            .mmap
+            .as_slice(self.dir_offset + slot * SLOT_LEN, SLOT_LEN)?;
+        Ok(Slot {
+            hash: read_u64(bytes, 0)?,
+            name_offset: read_u64(bytes, 8)?,
+            data_offset: read_u64(bytes, 16)?,
+            data_len: read_u64(bytes, 24)?,
+        })
+    }
+
+    fn name(&self, offset: u64) -> Result<&str> {
+        let total = self.mmap.len();
+        if offset.checked_add(4).map_or(true, |end| end > total) {
+            return Err(invalid("archive entry name out of bounds"));
+        }
+        let len = u64::from(read_u32(self.mmap.as_slice(offset, 4)?, 0)?);
+        if offset + 4 + len > total {
+            return Err(invalid("archive entry name out of bounds"));
+        }
+        std::str::from_utf8(self.mmap.as_slice(offset + 4, len)?)
+            .map_err(|_| invalid("archive entry name is not UTF-8"))
+    }
+
+    fn segment(&self, record: &Slot) -> Result<Segment> {
+        let in_data = record
+            .data_offset
+            .checked_add(record.data_len)
+            .is_some_and(|end| record.data_offset >= HEADER_LEN && end <= self.dir_offset);
+        if !in_data {
+            return Err(invalid("archive entry data out of bounds"));
+        }
+        Segment::new(Arc::clone(&self.mmap), record.data_offset, record.data_len)
+    }
+}
+
+struct Slot {
+    hash: u64,
+    name_offset: u64,
+    data_offset: u64,
+    data_len: u64,
+}
+
+fn check_alignment(alignment: u64) -> Result<()> {
+    if alignment.is_power_of_two() {
+        Ok(())
+    } else {
+        Err(MmapIoError::Misaligned {
+            required: alignment,
+            offset: 0,
+        })
+    }
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Result<u64> {
+    data.get(offset..offset + 8)
+        .map(|b| u64::from_le_bytes(b.try_into().expect("8 bytes")))
+        .ok_or_else(|| invalid("archive data truncated"))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32> {
+    data.get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes(b.try_into().expect("4 bytes")))
+        .ok_or_else(|| invalid("archive data truncated"))
+}
+
+fn invalid(msg: &str) -> MmapIoError {
+    MmapIoError::InvalidFormat(msg.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn tmp_path(name: &str) -> PathBuf {
+        let mut p = std::env::temp_dir();
+        p.push(format!(
+            "mmap_io_archive_test_{}_{}",
+            name,
+            std::process::id()
+        ));
+        p
+    }
+
+    #[test]
+    fn test_write_and_lookup_many_entries() {
+        let path = tmp_path("lookup");
+        let _ = fs::remove_file(&path);
+
+        let mut writer = ArchiveWriter::create(&path).expect("create");
+        for i in 0..500 {
+            writer
+                .add(&format!("assets/{i}.bin"), format!("blob {i}").as_bytes())
+                .expect("add");
+        }
+        writer.add("empty", b"").expect("add empty");
+        assert_eq!(writer.len(), 501);
+        let pack = writer.finish().expect("finish");
+
+        assert_eq!(pack.len(), 501);
+        for i in 0..500 {
+            let seg = pack
+                .get(&format!("assets/{i}.bin"))
+                .expect("get")
+                .expect("present");
+            assert_eq!(
+                seg.as_slice().expect("slice"),
+                format!("blob {i}").as_bytes()
+            );
+        }
+        assert!(pack.get("empty").expect("get").expect("present").is_empty());
+        assert!(pack.get("assets/500.bin").expect("get").is_none());
+        assert!(!pack.contains("missing").expect("contains"));
+
+        let mut names: Vec<_> = pack
+            .iter()
+            .map(|e| e.expect("entry").0.to_owned())
+            .collect();
+        names.sort();
+        assert_eq!(names.len(), 501);
+        assert_eq!(names[0], "assets/0.bin");
+
+        // Reopening sees the same directory
+        drop(pack);
+        let pack = ArchiveReader::open(&path).expect("reopen");
+        assert!(pack.contains("assets/42.bin").expect("contains"));
+
+        fs::remove_file(&path).expect("cleanup");
+    }
+
+    #[test]
+    fn test_per_entry_alignment() {
+        let path = tmp_path("align");
+        let _ = fs::remove_file(&path);
+
+        let page = crate::utils::page_size() as u64;
+        let mut writer = ArchiveWriter::create(&path)
+            .expect("create")
+            .alignment(16)
+            .expect("alignment");
+        assert!(matches!(
+            writer.add_aligned("bad", b"x", 3),
+            Err(MmapIoError::Misaligned { .. })
+        ));
+        writer.add("a", b"abc").expect("add");
+        writer
+            .add_aligned("texture", &[7u8; 5000], page)
+            .expect("add");
+        writer.add("b", b"def").expect("add");
+        assert!(matches!(
+            writer.add("a", b"again"),
+            Err(MmapIoError::InvalidMode(_))
+        ));
+        let pack = writer.finish().expect("finish");
+
+        assert_eq!(pack.get("a").expect("get").expect("a").offset() % 16, 0);
+        assert_eq!(pack.get("b").expect("get").expect("b").offset() % 16, 0);
+        let texture = pack.get("texture").expect("get").expect("texture");
+        assert_eq!(texture.offset() % page, 0);
+        assert!(texture.as_slice().expect("slice").iter().all(|&b| b == 7));
+        #[cfg(feature = "advise")]
+        texture
+            .parent()
+            .advise(texture.offset(), texture.len(), crate::MmapAdvice::WillNeed)
+            .expect("advise aligned entry");
+
+        fs::remove_file(&path).expect("cleanup");
+    }
+
+    #[test]
+    fn test_empty_and_invalid_archives() {
+        let path = tmp_path("invalid");
+        let _ = fs::remove_file(&path);
+
+        let pack = ArchiveWriter::create(&path)
+            .expect("create")
+            .finish()
+            .expect("finish");
+        assert!(pack.is_empty());
+        assert!(pack.get("x").expect("get").is_none());
+        assert_eq!(pack.iter().count(), 0);
+        drop(pack);
+
+        let rw = MemoryMappedFile::open_rw(&path).expect("rw");
+        assert!(matches!(
+            ArchiveReader::from_mmap(rw),
+            Err(MmapIoError::InvalidMode(_))
+        ));
+
+        // Appending bytes makes the recorded file length stale
+        let mut bytes = fs::read(&path).expect("read");
+        bytes.push(0);
+        fs::write(&path, &bytes).expect("write");
+        assert!(matches!(
+            ArchiveReader::open(&path),
+            Err(MmapIoError::InvalidFormat(_))
+        ));
+
+        crate::create_mmap(&path, 100).expect("create");
+        assert!(matches!(
+            ArchiveReader::open(&path),
+            Err(MmapIoError::InvalidFormat(_))
+        ));
+
+        fs::remove_file(&path).expect("cleanup");
+    }
+}
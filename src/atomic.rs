@@ -7,7 +7,9 @@ use std::sync::atomic::{AtomicU32, AtomicU64};
 impl MemoryMappedFile {
     /// Get an atomic view of a u64 value at the specified offset.
     ///
-    /// The offset must be properly aligned for atomic operations (8-byte alignment for u64).
+    /// The value's address must be properly aligned for atomic operations (8-byte alignment
+    /// for u64). That is the offset's alignment unless the mapping is a window starting at a
+    /// file offset that is not a multiple of 8.
     /// This allows lock-free concurrent access to the value.
     ///
     /// # Safety
@@ -18,20 +20,15 @@ impl MemoryMappedFile {
     ///
     /// # Errors
     ///
-    /// Returns `MmapIoError::Misaligned` if the offset is not 8-byte aligned.
+    /// Returns `MmapIoError::Misaligned` if the value's address is not 8-byte aligned.
     /// Returns `MmapIoError::OutOfBounds` if the offset + 8 exceeds file bounds.
     #[cfg(feature = "atomic")]
     pub fn atomic_u64(&self, offset: u64) -> Result<&AtomicU64> {
         const ALIGN: u64 = std::mem::align_of::<AtomicU64>() as u64;
         const SIZE: u64 = std::mem::size_of::<AtomicU64>() as u64;
 
-        // Check alignment
-        if offset % ALIGN != 0 {
-            return Err(MmapIoError::Misaligned {
-                required: ALIGN,
-                offset,
-            });
-        }
+        // Check alignment of the address, not just the offset
+        let ptr = self.atomic_base(offset, ALIGN)?;
 
         // Check bounds
         let total = self.current_len()?;
@@ -43,18 +40,8 @@ impl MemoryMappedFile {
             });
         }
 
-        // Get the base pointer for the mapping
-        let ptr = match &self.inner.map {
-            crate::mmap::MapVariant::Ro(m) => m.as_ptr(),
-            crate::mmap::MapVariant::Rw(lock) => {
-                let guard = lock.read();
-                guard.as_ptr()
-            }
-            crate::mmap::MapVariant::Cow(m) => m.as_ptr(),
-        };
-
         // SAFETY: Multiple invariants are guaranteed:
-        // 1. Alignment: We've verified offset % 8 == 0 (required for AtomicU64)
+        // 1. Alignment: We've verified (base + offset) % 8 == 0 (required for AtomicU64)
         // 2. Bounds: We've verified offset + 8 <= total file size
         // 3. Overflow: try_into() ensures offset fits in usize, preventing ptr arithmetic overflow
         // 4. Lifetime: The returned reference is bound to 'self', ensuring the mapping outlives it
@@ -77,7 +64,9 @@ impl MemoryMappedFile {
 
     /// Get an atomic view of a u32 value at the specified offset.
     ///
-    /// The offset must be properly aligned for atomic operations (4-byte alignment for u32).
+    /// The value's address must be properly aligned for atomic operations (4-byte alignment
+    /// for u32). That is the offset's alignment unless the mapping is a window starting at a
+    /// file offset that is not a multiple of 4.
     /// This allows lock-free concurrent access to the value.
     ///
     /// # Safety
@@ -88,20 +77,15 @@ impl MemoryMappedFile {
     ///
     /// # Errors
     ///
-    /// Returns `MmapIoError::Misaligned` if the offset is not 4-byte aligned.
+    /// Returns `MmapIoError::Misaligned` if the value's address is not 4-byte aligned.
     /// Returns `MmapIoError::OutOfBounds` if the offset + 4 exceeds file bounds.
     #[cfg(feature = "atomic")]
     pub fn atomic_u32(&self, offset: u64) -> Result<&AtomicU32> {
         const ALIGN: u64 = std::mem::align_of::<AtomicU32>() as u64;
         const SIZE: u64 = std::mem::size_of::<AtomicU32>() as u64;
 
-        // Check alignment
-        if offset % ALIGN != 0 {
-            return Err(MmapIoError::Misaligned {
-                required: ALIGN,
-                offset,
-            });
-        }
+        // Check alignment of the address, not just the offset
+        let ptr = self.atomic_base(offset, ALIGN)?;
 
         // Check bounds
         let total = self.current_len()?;
@@ -113,18 +97,8 @@ impl MemoryMappedFile {
             });
         }
 
-        // Get the base pointer for the mapping
-        let ptr = match &self.inner.map {
-            crate::mmap::MapVariant::Ro(m) => m.as_ptr(),
-            crate::mmap::MapVariant::Rw(lock) => {
-                let guard = lock.read();
-                guard.as_ptr()
-            }
-            crate::mmap::MapVariant::Cow(m) => m.as_ptr(),
-        };
-
         // SAFETY: Multiple invariants are guaranteed:
-        // 1. Alignment: We've verified offset % 4 == 0 (required for AtomicU32)
+        // 1. Alignment: We've verified (base + offset) % 4 == 0 (required for AtomicU32)
         // 2. Bounds: We've verified offset + 4 <= total file size
         // 3. Overflow: try_into() ensures offset fits in usize, preventing ptr arithmetic overflow
         // 4. Lifetime: The returned reference is bound to 'self', ensuring the mapping outlives it
@@ -148,24 +122,19 @@ impl MemoryMappedFile {
     /// Get multiple atomic u64 views starting at the specified offset.
     ///
     /// Returns a slice of atomic values. All values must be within bounds
-    /// and the offset must be 8-byte aligned.
+    /// and the first one's address must be 8-byte aligned.
     ///
     /// # Errors
     ///
-    /// Returns `MmapIoError::Misaligned` if the offset is not 8-byte aligned.
+    /// Returns `MmapIoError::Misaligned` if the value's address is not 8-byte aligned.
     /// Returns `MmapIoError::OutOfBounds` if the range exceeds file bounds.
     #[cfg(feature = "atomic")]
     pub fn atomic_u64_slice(&self, offset: u64, count: usize) -> Result<&[AtomicU64]> {
         const ALIGN: u64 = std::mem::align_of::<AtomicU64>() as u64;
         const SIZE: u64 = std::mem::size_of::<AtomicU64>() as u64;
 
-        // Check alignment
-        if offset % ALIGN != 0 {
-            return Err(MmapIoError::Misaligned {
-                required: ALIGN,
-                offset,
-            });
-        }
+        // Check alignment of the address, not just the offset
+        let ptr = self.atomic_base(offset, ALIGN)?;
 
         // Check bounds
         let total_size = SIZE * count as u64;
@@ -178,18 +147,8 @@ impl MemoryMappedFile {
             });
         }
 
-        // Get the base pointer for the mapping
-        let ptr = match &self.inner.map {
-            crate::mmap::MapVariant::Ro(m) => m.as_ptr(),
-            crate::mmap::MapVariant::Rw(lock) => {
-                let guard = lock.read();
-                guard.as_ptr()
-            }
-            crate::mmap::MapVariant::Cow(m) => m.as_ptr(),
-        };
-
         // SAFETY: Multiple invariants are guaranteed:
-        // 1. Alignment: We've verified offset % 8 == 0 (required for AtomicU64 array)
+        // 1. Alignment: We've verified (base + offset) % 8 == 0 (required for AtomicU64 array)
         // 2. Bounds: We've verified offset + (count * 8) <= total file size
         // 3. Overflow: try_into() ensures offset fits in usize, preventing ptr arithmetic overflow
         // 4. Lifetime: The returned slice is bound to 'self', ensuring the mapping outlives it
@@ -216,24 +175,19 @@ impl MemoryMappedFile {
     /// Get multiple atomic u32 views starting at the specified offset.
     ///
     /// Returns a slice of atomic values. All values must be within bounds
-    /// and the offset must be 4-byte aligned.
+    /// and the first one's address must be 4-byte aligned.
     ///
     /// # Errors
     ///
-    /// Returns `MmapIoError::Misaligned` if the offset is not 4-byte aligned.
+    /// Returns `MmapIoError::Misaligned` if the value's address is not 4-byte aligned.
     /// Returns `MmapIoError::OutOfBounds` if the range exceeds file bounds.
     #[cfg(feature = "atomic")]
     pub fn atomic_u32_slice(&self, offset: u64, count: usize) -> Result<&[AtomicU32]> {
         const ALIGN: u64 = std::mem::align_of::<AtomicU32>() as u64;
         const SIZE: u64 = std::mem::size_of::<AtomicU32>() as u64;
 
-        // Check alignment
-        if offset % ALIGN != 0 {
-            return Err(MmapIoError::Misaligned {
-                required: ALIGN,
-                offset,
-            });
-        }
+        // Check alignment of the address, not just the offset
+        let ptr = self.atomic_base(offset, ALIGN)?;
 
         // Check bounds
         let total_size = SIZE * count as u64;
@@ -246,18 +200,8 @@ impl MemoryMappedFile {
             });
         }
 
-        // Get the base pointer for the mapping
-        let ptr = match &self.inner.map {
-            crate::mmap::MapVariant::Ro(m) => m.as_ptr(),
-            crate::mmap::MapVariant::Rw(lock) => {
-                let guard = lock.read();
-                guard.as_ptr()
-            }
-            crate::mmap::MapVariant::Cow(m) => m.as_ptr(),
-        };
-
         // SAFETY: Multiple invariants are guaranteed:
-        // 1. Alignment: We've verified offset % 4 == 0 (required for AtomicU32 array)
+        // 1. Alignment: We've verified (base + offset) % 4 == 0 (required for AtomicU32 array)
         // 2. Bounds: We've verified offset + (count * 4) <= total file size
         // 3. Overflow: try_into() ensures offset fits in usize, preventing ptr arithmetic overflow
         // 4. Lifetime: The returned slice is bound to 'self', ensuring the mapping outlives it
@@ -280,6 +224,28 @@ impl MemoryMappedFile {
             Ok(std::slice::from_raw_parts(atomic_ptr, count))
         }
     }
+    // Base pointer of the mapping, after checking that `offset` lands on an `align`-byte
+    // boundary in memory and that a read-write mapping was not patched after a SIGBUS. A window starts wherever its file offset falls within a page, so
+    // the offset alone does not decide alignment.
+    #[cfg(feature = "atomic")]
+    fn atomic_base(&self, offset: u64, align: u64) -> Result<*const u8> {
+        self.check_truncated(offset, align)?;
+        let ptr = match &self.inner.map {
+            crate::mmap::MapVariant::Ro(m) => m.as_ptr(),
+            crate::mmap::MapVariant::Rw(lock) => {
+                let guard = lock.read();
+                guard.as_ptr()
+            }
+            crate::mmap::MapVariant::Cow(m) => m.as_ptr(),
+        };
+        if (ptr as u64).wrapping_add(offset) % align != 0 {
+            return Err(MmapIoError::Misaligned {
+                required: align,
+                offset,
+            });
+        }
+        Ok(ptr)
+    }
 }
 
 #[cfg(test)]
@@ -487,4 +453,43 @@ mod tests {
 
         fs::remove_file(&path).expect("cleanup");
     }
+
+    #[test]
+    #[cfg(feature = "atomic")]
+    fn test_atomic_alignment_in_windowed_mapping() {
+        let path = tmp_path("windowed");
+        let _ = fs::remove_file(&path);
+        create_mmap(&path, 4096).expect("create");
+
+        // The window starts 3 bytes into a page, so offset 5 is the first aligned u64
+        let mmap = MemoryMappedFile::builder(&path)
+            .mode(crate::MmapMode::ReadWrite)
+            .offset(3)
+            .len(64)
+            .open()
+            .expect("open window");
+        assert!(matches!(
+            mmap.atomic_u64(0),
+            Err(MmapIoError::Misaligned {
+                required: 8,
+                offset: 0
+            })
+        ));
+        assert!(mmap.atomic_u64_slice(8, 2).is_err());
+        assert!(mmap.atomic_u32(0).is_err());
+        assert!(mmap.atomic_u32_slice(4, 2).is_err());
+
+        let atomic = mmap.atomic_u64(5).expect("aligned address");
+        atomic.store(0x0102_0304_0506_0708, Ordering::SeqCst);
+        mmap.atomic_u32_slice(1, 2).expect("aligned u32 slice");
+        drop(mmap);
+        let whole = MemoryMappedFile::open_ro(&path).expect("open");
+        assert_eq!(
+            whole.as_slice(8, 8).expect("read"),
+            0x0102_0304_0506_0708u64.to_ne_bytes()
+        );
+
+        drop(whole);
+        fs::remove_file(&path).expect("cleanup");
+    }
 }
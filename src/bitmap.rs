@@ -0,0 +1,345 @@
+//! Persistent bitmap over a region of a memory-mapped file.
+//!
+//! Bits are stored little-endian within 64-bit words (bit `i` is bit `i % 64` of word
+//! `i / 64`) and accessed through [`MemoryMappedFile::atomic_u64_slice`], so single-bit
+//! updates are lock-free and safe to race from multiple threads or processes.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::errors::{MmapIoError, Result};
+use crate::mmap::{MemoryMappedFile, MmapMode};
+
+/// Atomic bitmap view of `len` bits starting at an 8-byte aligned offset.
+///
+/// The view borrows the mapping; do not resize the mapping while a bitmap is alive.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mmap_io::bitmap::MmapBitmap;
+/// use mmap_io::MemoryMappedFile;
+///
+/// let mmap = MemoryMappedFile::create_rw("alloc.bits", 4096)?;
+/// let bits = MmapBitmap::new(&mmap, 0, 4096 * 8)?;
+///
+/// let slot = bits.claim_first_zero()?.expect("bitmap full");
+/// assert!(bits.test(slot)?);
+/// bits.clear(slot)?;
+/// assert_eq!(bits.count_ones(), 0);
+/// # Ok::<(), mmap_io::MmapIoError>(())
+/// ```
+#[derive(Debug)]
+pub struct MmapBitmap<'a> {
+    words: &'a [AtomicU64],
+    len: u64,
+    writable: bool,
+}
+
+impl<'a> MmapBitmap<'a> {
+    /// Number of bytes needed to store `bits` bits (rounded up to whole words).
+    #[must_use]
+    pub const fn bytes_for(bits: u64) -> u64 {
+        bits.div_ceil(64) * 8
+    }
+
+    /// View `len` bits starting at `offset`. Read-only and copy-on-write mappings give a
+    /// bitmap that can be tested and scanned but not modified.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::Misaligned` if the words are not 8-byte aligned in memory, i.e.
+    /// `offset` plus the window offset of `mmap` is not a multiple of 8.
+    /// Returns `MmapIoError::OutOfBounds` if the words do not fit in the mapping.
+    pub fn new(mmap: &'a MemoryMappedFile, offset: u64, len: u64) -> Result<Self> {
+        let count = usize::try_from(len.div_ceil(64)).map_err(|_| MmapIoError::OutOfBounds {
+            offset,
+            len: Self::bytes_for(len),
+            total: mmap.len(),
+        })?;
+        Ok(Self {
+            words: mmap.atomic_u64_slice(offset, count)?,
+            len,
+            writable: mmap.mode() == MmapMode::ReadWrite,
+        })
+    }
+
+    /// Number of bits.
+    #[must_use]
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Whether the bitmap has no bits.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Whether bit `index` is set.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::OutOfBounds` if `index >= len()`.
+    pub fn test(&self, index: u64) -> Result<bool> {
+        let (word, mask) = self.locate(index)?;
+        Ok(word.load(Ordering::Acquire) & mask != 0)
+    }
+
+    /// Set bit `index`, returning its previous value.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::OutOfBounds` if `index >= len()`.
+    /// Returns `MmapIoError::InvalidMode` if the mapping is not read-write.
+    pub fn set(&self, index: u64) -> Result<bool> {
+        self.check_writable()?;
+        let (word, mask) = self.locate(index)?;
+        Ok(word.fetch_or(mask, Ordering::AcqRel) & mask != 0)
+    }
+
+    /// Clear bit `index`, returning its previous value.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::OutOfBounds` if `index >= len()`.
+    /// Returns `MmapIoError::InvalidMode` if the mapping is not read-write.
+    pub fn clear(&self, index: u64) -> Result<bool> {
+        self.check_writable()?;
+        let (word, mask) = self.locate(index)?;
+        Ok(word.fetch_and(!mask, Ordering::AcqRel) & mask != 0)
+    }
+
+    /// Atomically find a zero bit, set it and return its index. Concurrent callers never
+    /// receive the same index. Returns `None` if every bit is set.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidMode` if the mapping is not read-write.
+    pub fn claim_first_zero(&self) -> Result<Option<u64>> {
+        self.check_writable()?;
+        for (i, word) in self.words.iter().enumerate() {
+            let valid = self.valid_mask(i);
+            let mut current = word.load(Ordering::Acquire);
+            while !current & valid != 0 {
+                let bit = (!current & valid).trailing_zeros();
+                match word.compare_exchange_weak(
+                    current,
+                    current | (1 << bit),
+                    Ordering::AcqRel,
+                    Ordering::Acquire,
+                ) {
+                    Ok(_) => return Ok(Some(i as u64 * 64 + u64::from(bit))),
+                    Err(actual) => current = actual,
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Number of set bits.
+    #[must_use]
+    pub fn count_ones(&self) -> u64 {
+        self.words
+            .iter()
+            .enumerate()
+            .map(|(i, w)| u64::from((w.load(Ordering::Relaxed) & self.valid_mask(i)).count_ones()))
+            .sum()
+    }
+
+    /// Index of the first zero bit, if any.
+    #[must_use]
+    pub fn find_first_zero(&self) -> Option<u64> {
+        self.find_next_zero(0)
+    }
+
+    /// Index of the first zero bit at or after `from`, if any.
+    #[must_use]
+    pub fn find_next_zero(&self, from: u64) -> Option<u64> {
+        self.scan(from, |w| !w)
+    }
+
+    /// Index of the first set bit, if any.
+    #[must_use]
+    pub fn find_first_set(&self) -> Option<u64> {
+        self.find_next_set(0)
+    }
+
+    /// Index of the first set bit at or after `from`, if any.
+    #[must_use]
+    pub fn find_next_set(&self, from: u64) -> Option<u64> {
+        self.scan(from, |w| w)
+    }
+
+    /// Iterate over the indices of set bits in ascending order.
+    pub fn iter_ones(&self) -> impl Iterator<Item = u64> + '_ {
+        let mut next = 0;
+        std::iter::from_fn(move || {
+            let found = self.find_next_set(next)?;
+            next = found + 1;
+            Some(found)
+        })
+    }
+
+    /// Word-level scan: `select` maps each word to the bits of interest.
+    fn scan(&self, from: u64, select: impl Fn(u64) -> u64) -> Option<u64> {
+        if from >= self.len {
+            return None;
+        }
+        let first = (from / 64) as usize;
+        let mut skip = !0u64 << (from % 64);
+        for (i, word) in self.words.iter().enumerate().skip(first) {
+            let bits = select(word.load(Ordering::Acquire)) & self.valid_mask(i) & skip;
+            if bits != 0 {
+                return Some(i as u64 * 64 + u64::from(bits.trailing_zeros()));
+            }
+            skip = !0;
+        }
+        None
+    }
+
+    /// Mask of the bits of word `i` that lie inside the bitmap.
+    fn valid_mask(&self, i: usize) -> u64 {
+        let remaining = self.len - i as u64 * 64;
+        if remaining >= 64 {
+            !0
+        } else {
+            (1 << remaining) - 1
+        }
+    }
+
+    fn locate(&self, index: u64) -> Result<(&AtomicU64, u64)> {
+        if index >= self.len {
+            return Err(MmapIoError::OutOfBounds {
+                offset: index,
+                len: 1,
+                total: self.len,
+            });
+        }
+        Ok((&self.words[(index / 64) as usize], 1 << (index % 64)))
+    }
+
+    fn check_writable(&self) -> Result<()> {
+        if self.writable {
+            Ok(())
+        } else {
+            Err(MmapIoError::InvalidMode(
+                "MmapBitmap updates require ReadWrite mode",
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn tmp_path(name: &str) -> PathBuf {
+        let mut p = std::env::temp_dir();
+        p.push(format!(
+            "mmap_io_bitmap_test_{}_{}",
+            name,
+            std::process::id()
+        ));
+        p
+    }
+
+    #[test]
+    fn test_set_clear_test_count() {
+        let mmap = MemoryMappedFile::anonymous(4096).expect("anon");
+        let bits = MmapBitmap::new(&mmap, 64, 200).expect("bitmap");
+        assert_eq!(bits.len(), 200);
+        assert!(!bits.set(0).expect("set"));
+        assert!(bits.set(0).expect("set again"));
+        bits.set(63).expect("set");
+        bits.set(64).expect("set");
+        bits.set(199).expect("set");
+        assert_eq!(bits.count_ones(), 4);
+        assert!(bits.test(199).expect("test"));
+        assert!(bits.clear(63).expect("clear"));
+        assert!(!bits.clear(63).expect("clear again"));
+        assert_eq!(bits.count_ones(), 3);
+        assert!(matches!(
+            bits.set(200),
+            Err(MmapIoError::OutOfBounds { .. })
+        ));
+        assert!(MmapBitmap::new(&mmap, 4, 8).is_err());
+        assert!(MmapBitmap::new(&mmap, 4032, 1000).is_err());
+    }
+
+    #[test]
+    fn test_scans_respect_word_and_length_boundaries() {
+        let mmap = MemoryMappedFile::anonymous(4096).expect("anon");
+        let bits = MmapBitmap::new(&mmap, 0, 130).expect("bitmap");
+        assert_eq!(bits.find_first_set(), None);
+        assert_eq!(bits.find_first_zero(), Some(0));
+
+        for i in 0..130 {
+            bits.set(i).expect("set");
+        }
+        // Bits past the end of the bitmap are ignored even though the last word has room
+        assert_eq!(bits.find_first_zero(), None);
+        assert_eq!(bits.claim_first_zero().expect("claim"), None);
+
+        bits.clear(70).expect("clear");
+        bits.clear(129).expect("clear");
+        assert_eq!(bits.find_first_zero(), Some(70));
+        assert_eq!(bits.find_next_zero(71), Some(129));
+        assert_eq!(bits.find_next_set(129), None);
+        assert_eq!(bits.find_next_set(70), Some(71));
+        assert_eq!(bits.find_next_set(500), None);
+
+        bits.clear(0).expect("clear");
+        let ones: Vec<_> = bits.iter_ones().take(3).collect();
+        assert_eq!(ones, [1, 2, 3]);
+        assert_eq!(bits.iter_ones().count(), 127);
+    }
+
+    #[test]
+    fn test_concurrent_claims_are_unique() {
+        let mmap = MemoryMappedFile::anonymous(4096).expect("anon");
+        let bits = MmapBitmap::new(&mmap, 0, 1000).expect("bitmap");
+        let mut claimed: Vec<u64> = std::thread::scope(|s| {
+            let handles: Vec<_> = (0..4)
+                .map(|_| {
+                    s.spawn(|| {
+                        let mut mine = Vec::new();
+                        while let Some(i) = bits.claim_first_zero().expect("claim") {
+                            mine.push(i);
+                        }
+                        mine
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|h| h.join().expect("join"))
+                .collect()
+        });
+        claimed.sort_unstable();
+        assert_eq!(claimed, (0..1000).collect::<Vec<_>>());
+        assert_eq!(bits.count_ones(), 1000);
+    }
+
+    #[test]
+    fn test_persisted_and_read_only() {
+        let path = tmp_path("persist");
+        let _ = fs::remove_file(&path);
+
+        let mmap = MemoryMappedFile::create_rw(&path, 64).expect("create");
+        let bits = MmapBitmap::new(&mmap, 8, 100).expect("bitmap");
+        bits.set(5).expect("set");
+        bits.set(99).expect("set");
+        mmap.flush().expect("flush");
+        drop(mmap);
+
+        let ro = MemoryMappedFile::open_ro(&path).expect("ro");
+        let bits = MmapBitmap::new(&ro, 8, 100).expect("bitmap");
+        assert_eq!(bits.iter_ones().collect::<Vec<_>>(), [5, 99]);
+        assert!(matches!(bits.set(6), Err(MmapIoError::InvalidMode(_))));
+        assert!(bits.claim_first_zero().is_err());
+
+        fs::remove_file(&path).expect("cleanup");
+    }
+}
@@ -0,0 +1,539 @@
+//! Content-addressed blob store with deduplication.
+//!
+//! A store is two files: the data file at `path` and an [`MmapHashMap`] index at
+//! `path` + `.idx` mapping each content hash to the blob's `(offset, len)`.
+//!
+//! Data file layout:
+//!
+//! ```text
+//! header   magic "MMAPBLOB" (8) | version (4) | reserved (4) | data end (8) | reserved (8)
+//! records  content hash (16) | length (8) | bytes, each record 8-byte aligned
+//! ```
+//!
+//! Appends write and flush the record and the data end before the index entry is inserted,
+//! so after a crash the index never points at unwritten data. Records appended but not yet
+//! indexed are orphans that [`BlobStore::compact`] drops. Records are self-describing, so a
+//! missing index is rebuilt from the data file on open.
+
+use std::ffi::OsString;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::errors::{MmapIoError, Result};
+use crate::hash::fnv1a128;
+use crate::hashmap::MmapHashMap;
+use crate::mmap::MemoryMappedFile;
+use crate::segment::Segment;
+use crate::utils::align_up;
+
+const MAGIC: [u8; 8] = *b"MMAPBLOB";
+const VERSION: u32 = 1;
+const HEADER_LEN: u64 = 32;
+const DATA_END_OFFSET: u64 = 16;
+const RECORD_HEADER_LEN: u64 = 24;
+const INITIAL_FILE_LEN: u64 = 64 * 1024;
+
+/// 128-bit content hash identifying a blob (FNV-1a; not collision resistant against
+/// adversarial input).
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BlobHash([u8; 16]);
+
+impl BlobHash {
+    /// Hash `data`.
+    #[must_use]
+    pub fn of(data: &[u8]) -> Self {
+        Self(fnv1a128(data).to_le_bytes())
+    }
+
+    /// Wrap raw hash bytes, e.g. read back from another index.
+    #[must_use]
+    pub const fn from_bytes(bytes: [u8; 16]) -> Self {
+        Self(bytes)
+    }
+
+    /// Raw hash bytes.
+    #[must_use]
+    pub const fn as_bytes(&self) -> &[u8; 16] {
+        &self.0
+    }
+}
+
+impl fmt::Display for BlobHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|b| write!(f, "{b:02x}"))
+    }
+}
+
+impl fmt::Debug for BlobHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "BlobHash({self})")
+    }
+}
+
+/// Append-only, deduplicating blob store over a growable mapped data file.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mmap_io::blobstore::BlobStore;
+///
+/// let mut store = BlobStore::create("assets.blobs")?;
+/// let a = store.put(b"texture bytes")?;
+/// let b = store.put(b"texture bytes")?; // stored once
+/// assert_eq!(a, b);
+///
+/// let blob = store.get(&a)?.expect("missing blob");
+/// assert_eq!(blob.as_slice()?, b"texture bytes");
+/// # Ok::<(), mmap_io::MmapIoError>(())
+/// ```
+#[derive(Debug)]
+pub struct BlobStore {
+    path: PathBuf,
+    data: MemoryMappedFile,
+    // Read-only view of the same file, so returned segments can be read without locking.
+    // Replaced whenever the data file grows.
+    reader: Arc<MemoryMappedFile>,
+    index: MmapHashMap,
+    data_end: u64,
+}
+
+impl BlobStore {
+    /// Create a new, empty store at `path` (truncating the data and index files).
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from `MemoryMappedFile::create_rw` or `MmapHashMap::create`.
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let data = MemoryMappedFile::create_rw(&path, INITIAL_FILE_LEN)?;
+        let mut header = [0u8; HEADER_LEN as usize];
+        header[..8].copy_from_slice(&MAGIC);
+        header[8..12].copy_from_slice(&VERSION.to_le_bytes());
+        header[16..24].copy_from_slice(&HEADER_LEN.to_le_bytes());
+        data.update_region(0, &header)?;
+        data.flush()?;
+        let index = MmapHashMap::create(index_path(&path))?;
+        Ok(Self {
+            reader: Arc::new(MemoryMappedFile::open_ro(&path)?),
+            path,
+            data,
+            index,
+            data_end: HEADER_LEN,
+        })
+    }
+
+    /// Open an existing store, rebuilding the index from the data file if it is missing.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidFormat` if the data file or a record is invalid.
+    /// Returns errors from `MemoryMappedFile::open_rw` or `MmapHashMap::open`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let data = MemoryMappedFile::open_rw(&path)?;
+        let total = data.current_len()?;
+        let mut header = [0u8; HEADER_LEN as usize];
+        if total < HEADER_LEN {
+            return Err(invalid("file too small for blob store header"));
+        }
+        data.read_into(0, &mut header)?;
+        if header[..8] != MAGIC || read_u32(&header, 8) != VERSION {
+            return Err(invalid("missing or unsupported blob store header"));
+        }
+        let data_end = read_u64(&header, DATA_END_OFFSET as usize);
+        if data_end < HEADER_LEN || data_end > total {
+            return Err(invalid("blob store data end outside the file"));
+        }
+        let idx = index_path(&path);
+        let rebuild = !idx.exists();
+        let index = if rebuild {
+            MmapHashMap::create(&idx)?
+        } else {
+            MmapHashMap::open(&idx)?
+        };
+        let mut store = Self {
+            reader: Arc::new(MemoryMappedFile::open_ro(&path)?),
+            path,
+            data,
+            index,
+            data_end,
+        };
+        if rebuild {
+            store.rebuild_index()?;
+        }
+        Ok(store)
+    }
+
+    /// Number of distinct blobs in the index.
+    #[must_use]
+    pub fn len(&self) -> u64 {
+        self.index.len()
+    }
+
+    /// Whether the store holds no blobs.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.index.len() == 0
+    }
+
+    /// Bytes used in the data file, including removed and orphaned blobs.
+    #[must_use]
+    pub fn data_end(&self) -> u64 {
+        self.data_end
+    }
+
+    /// Path of the data file.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Store `data` and return its hash. Content that is already present is not written
+    /// again.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidFormat` if different content with the same hash is
+    /// already stored.
+    /// Returns errors from `MemoryMappedFile::resize`, `update_region` or `flush_range`, and
+    /// from `MmapHashMap::insert`.
+    pub fn put(&mut self, data: &[u8]) -> Result<BlobHash> {
+        let hash = BlobHash::of(data);
+        if let Some(existing) = self.get(&hash)? {
+            if existing.as_slice()? != data {
+                return Err(invalid("content hash collision"));
+            }
+            return Ok(hash);
+        }
+        let start = self.data_end;
+        let offset = self.write_record(&hash, data)?;
+        self.commit_data(start)?;
+        self.index
+            .insert(hash.as_bytes(), &index_value(offset, data.len() as u64))?;
+        self.index.flush()?;
+        Ok(hash)
+    }
+
+    /// The blob with `hash`, as a segment of the data file.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidFormat` if the index entry does not match a data record.
+    pub fn get(&self, hash: &BlobHash) -> Result<Option<Segment>> {
+        let Some(value) = self.index.get(hash.as_bytes())? else {
+            return Ok(None);
+        };
+        if value.len() != 16 {
+            return Err(invalid("blob index entry has wrong length"));
+        }
+        let (offset, len) = (read_u64(&value, 0), read_u64(&value, 8));
+        drop(value);
+        // Check the record header so a stale index can never return the wrong bytes
+        let in_data = offset
+            .checked_add(len)
+            .is_some_and(|end| offset >= HEADER_LEN + RECORD_HEADER_LEN && end <= self.data_end);
+        if !in_data {
+            return Err(invalid("blob index entry outside the data file"));
+        }
+        let record = self
+            .reader
+            .as_slice(offset - RECORD_HEADER_LEN, RECORD_HEADER_LEN)?;
+        if record[..16] != hash.0 || read_u64(record, 16) != len {
+            return Err(invalid("blob index entry does not match its data record"));
+        }
+        Segment::new(Arc::clone(&self.reader), offset, len).map(Some)
+    }
+
+    /// Whether a blob with `hash` is stored.
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from `MmapHashMap::contains_key`.
+    pub fn contains(&self, hash: &BlobHash) -> Result<bool> {
+        self.index.contains_key(hash.as_bytes())
+    }
+
+    /// Drop the blob with `hash` from the index. Its bytes stay in the data file until the
+    /// next [`compact`](Self::compact).
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from `MmapHashMap::remove` or `flush`.
+    pub fn remove(&mut self, hash: &BlobHash) -> Result<bool> {
+        let removed = self.index.remove(hash.as_bytes())?;
+        if removed {
+            self.index.flush()?;
+        }
+        Ok(removed)
+    }
+
+    /// Copy live blobs into a fresh data file and index, then replace the old files.
+    ///
+    /// Segments returned earlier keep the old mapping alive and stay valid on Unix. On
+    /// Windows, drop them first or the replacement fails. If the process dies after the old
+    /// index is removed, the next [`open`](Self::open) rebuilds the index from whichever
+    /// data file is in place, which may bring back blobs removed before the compaction.
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from creating the new files, copying blobs, or renaming the files.
+    pub fn compact(self) -> Result<Self> {
+        let tmp = with_suffix(&self.path, ".compact");
+        let mut fresh = Self::create(&tmp)?;
+        let mut live = Vec::with_capacity(self.index.len() as usize);
+        for entry in self.index.entries()?.iter() {
+            let (key, _) = entry?;
+            let hash = BlobHash::from_bytes(
+                key.try_into()
+                    .map_err(|_| invalid("blob index key has wrong length"))?,
+            );
+            let blob = self
+                .get(&hash)?
+                .ok_or_else(|| invalid("blob index entry vanished"))?;
+            let offset = fresh.write_record(&hash, blob.as_slice()?)?;
+            live.push((hash, offset, blob.len()));
+        }
+        fresh.commit_data(HEADER_LEN)?;
+        for (hash, offset, len) in &live {
+            fresh
+                .index
+                .insert(hash.as_bytes(), &index_value(*offset, *len))?;
+        }
+        fresh.index.flush()?;
+
+        let path = self.path.clone();
+        drop(self);
+        drop(fresh);
+        // Remove the old index first so a crash never pairs it with the new data file
+        fs::remove_file(index_path(&path))?;
+        fs::rename(&tmp, &path)?;
+        fs::rename(index_path(&tmp), index_path(&path))?;
+        Self::open(path)
+    }
+
+    /// Flush the data file and index.
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from `MemoryMappedFile::flush` or `MmapHashMap::flush`.
+    pub fn flush(&self) -> Result<()> {
+        self.data.flush()?;
+        self.index.flush()
+    }
+
+    /// Write a record at the data end without persisting the new end. Returns the offset
+    /// of the blob bytes.
+    fn write_record(&mut self, hash: &BlobHash, data: &[u8]) -> Result<u64> {
+        let record = self.data_end;
+        let offset = record + RECORD_HEADER_LEN;
+        let end = align_up(offset + data.len() as u64, 8);
+        let total = self.data.current_len()?;
+        if end > total {
+            self.data.resize(end.max(total.saturating_mul(2)))?;
+            self.reader = Arc::new(MemoryMappedFile::open_ro(&self.path)?);
+        }
+        let mut header = [0u8; RECORD_HEADER_LEN as usize];
+        header[..16].copy_from_slice(&hash.0);
+        header[16..].copy_from_slice(&(data.len() as u64).to_le_bytes());
+        self.data.update_region(record, &header)?;
+        if !data.is_empty() {
+            self.data.update_region(offset, data)?;
+        }
+        self.data_end = end;
+        Ok(offset)
+    }
+
+    /// Flush records written since `start`, then persist the new data end.
+    fn commit_data(&mut self, start: u64) -> Result<()> {
+        if self.data_end > start {
+            self.data.flush_range(start, self.data_end - start)?;
+        }
+        self.data
+            .update_region(DATA_END_OFFSET, &self.data_end.to_le_bytes())?;
+        self.data.flush_range(0, HEADER_LEN)
+    }
+
+    fn rebuild_index(&mut self) -> Result<()> {
+        let mut record = HEADER_LEN;
+        while record < self.data_end {
+            if record + RECORD_HEADER_LEN > self.data_end {
+                return Err(invalid("truncated blob record"));
+            }
+            let header = self.reader.as_slice(record, RECORD_HEADER_LEN)?;
+            let hash = BlobHash::from_bytes(header[..16].try_into().expect("16 bytes"));
+            let len = read_u64(header, 16);
+            let offset = record + RECORD_HEADER_LEN;
+            let end = offset
+                .checked_add(len)
+                .filter(|&end| end <= self.data_end)
+                .ok_or_else(|| invalid("blob record extends past the data end"))?;
+            if !self.index.contains_key(hash.as_bytes())? {
+                self.index
+                    .insert(hash.as_bytes(), &index_value(offset, len))?;
+            }
+            record = align_up(end, 8);
+        }
+        self.index.flush()
+    }
+}
+
+fn index_path(path: &Path) -> PathBuf {
+    with_suffix(path, ".idx")
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(suffix);
+    name.into()
+}
+
+fn index_value(offset: u64, len: u64) -> [u8; 16] {
+    let mut value = [0u8; 16];
+    value[..8].copy_from_slice(&offset.to_le_bytes());
+    value[8..].copy_from_slice(&len.to_le_bytes());
+    value
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().expect("8 bytes"))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().expect("4 bytes"))
+}
+
+fn invalid(msg: &str) -> MmapIoError {
+    MmapIoError::InvalidFormat(msg.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tmp_path(name: &str) -> PathBuf {
+        let mut p = std::env::temp_dir();
+        p.push(format!(
+            "mmap_io_blobstore_test_{}_{}",
+            name,
+            std::process::id()
+        ));
+        p
+    }
+
+    fn cleanup(path: &Path) {
+        let _ = fs::remove_file(path);
+        let _ = fs::remove_file(index_path(path));
+        let _ = fs::remove_file(with_suffix(path, ".compact"));
+        let _ = fs::remove_file(index_path(&with_suffix(path, ".compact")));
+    }
+
+    #[test]
+    fn test_put_get_dedup_and_reopen() {
+        let path = tmp_path("dedup");
+        cleanup(&path);
+
+        let mut store = BlobStore::create(&path).expect("create");
+        let a = store.put(b"hello").expect("put");
+        let end = store.data_end();
+        assert_eq!(store.put(b"hello").expect("put again"), a);
+        assert_eq!(
+            store.data_end(),
+            end,
+            "duplicate content is not stored twice"
+        );
+        let b = store.put(&vec![9u8; 200_000]).expect("put large");
+        let empty = store.put(b"").expect("put empty");
+        assert_eq!(store.len(), 3);
+        assert_eq!(
+            store
+                .get(&a)
+                .expect("get")
+                .expect("a")
+                .as_slice()
+                .expect("slice"),
+            b"hello"
+        );
+        assert!(store.get(&empty).expect("get").expect("empty").is_empty());
+        assert!(store.get(&BlobHash::of(b"nope")).expect("get").is_none());
+        assert_eq!(a.to_string().len(), 32);
+        drop(store);
+
+        let store = BlobStore::open(&path).expect("open");
+        let blob = store.get(&b).expect("get").expect("b");
+        assert_eq!(blob.len(), 200_000);
+        assert!(blob.as_slice().expect("slice").iter().all(|&x| x == 9));
+
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_segments_survive_growth() {
+        let path = tmp_path("growth");
+        cleanup(&path);
+
+        let mut store = BlobStore::create(&path).expect("create");
+        let first = store.put(b"first").expect("put");
+        let seg = store.get(&first).expect("get").expect("first");
+        for i in 0..100u32 {
+            store.put(&i.to_le_bytes().repeat(1000)).expect("put");
+        }
+        assert_eq!(seg.as_slice().expect("slice"), b"first");
+        assert_eq!(store.len(), 101);
+
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_remove_and_compact() {
+        let path = tmp_path("compact");
+        cleanup(&path);
+
+        let mut store = BlobStore::create(&path).expect("create");
+        let keep = store.put(b"keep me").expect("put");
+        let drop_me = store.put(&[1u8; 10_000]).expect("put");
+        assert!(store.remove(&drop_me).expect("remove"));
+        assert!(!store.remove(&drop_me).expect("remove again"));
+        assert!(!store.contains(&drop_me).expect("contains"));
+        let before = store.data_end();
+
+        let store = store.compact().expect("compact");
+        assert!(store.data_end() < before);
+        assert_eq!(store.len(), 1);
+        assert_eq!(
+            store
+                .get(&keep)
+                .expect("get")
+                .expect("kept")
+                .as_slice()
+                .expect("slice"),
+            b"keep me"
+        );
+        assert!(!with_suffix(&path, ".compact").exists());
+
+        cleanup(&path);
+    }
+
+    #[test]
+    fn test_missing_index_is_rebuilt() {
+        let path = tmp_path("rebuild");
+        cleanup(&path);
+
+        let mut store = BlobStore::create(&path).expect("create");
+        let hashes: Vec<_> = (0..20u8)
+            .map(|i| store.put(&[i; 33]).expect("put"))
+            .collect();
+        drop(store);
+        fs::remove_file(index_path(&path)).expect("remove index");
+
+        let store = BlobStore::open(&path).expect("open");
+        assert_eq!(store.len(), 20);
+        for (i, hash) in hashes.iter().enumerate() {
+            let blob = store.get(hash).expect("get").expect("present");
+            assert_eq!(blob.as_slice().expect("slice"), [i as u8; 33]);
+        }
+
+        cleanup(&path);
+    }
+}
@@ -0,0 +1,738 @@
+//! Persistent LRU/TTL cache stored in a single mapped file.
+//!
+//! File layout:
+//!
+//! ```text
+//! header  magic "MMAPLRUC" (8) | version (4) | reserved (4) | slot count (8) | max entries (8)
+//!         | capacity (8) | len (8) | arena end (8) | live bytes (8) | clock (8) | reserved
+//! slots   open-addressed table of 40-byte slots, linear probing with backward-shift deletion:
+//!         key hash (8, 0 = empty) | arena offset (8) | key len (4) | value len (4)
+//!         | expires at, Unix ms (8, 0 = never) | last access tick (8)
+//! arena   `capacity` bytes of key and value data
+//! ```
+//!
+//! Integers are little-endian. The clock and last-access ticks are updated atomically, so
+//! lookups take `&self` and can run from several threads at once. Nothing is flushed
+//! automatically; call [`MmapCache::flush`] to make the contents durable.
+
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::errors::{MmapIoError, Result};
+use crate::hash::fnv1a64;
+use crate::mmap::{MappedSlice, MemoryMappedFile};
+
+const MAGIC: [u8; 8] = *b"MMAPLRUC";
+const VERSION: u32 = 1;
+const HEADER_LEN: u64 = 128;
+const CLOCK_OFFSET: u64 = 64;
+const SLOT_LEN: u64 = 40;
+const LAST_ACCESS_OFFSET: u64 = 32;
+const MIN_ENTRIES: u64 = 16;
+/// Arena bytes per entry assumed by [`MmapCache::create`] when sizing the slot table.
+const BYTES_PER_ENTRY: u64 = 128;
+
+#[derive(Debug, Clone, Copy)]
+struct Header {
+    slot_count: u64,
+    max_entries: u64,
+    capacity: u64,
+    len: u64,
+    arena_end: u64,
+    live_bytes: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Slot {
+    hash: u64,
+    offset: u64,
+    key_len: u32,
+    value_len: u32,
+    expires_at: u64,
+}
+
+impl Slot {
+    fn size(&self) -> u64 {
+        u64::from(self.key_len) + u64::from(self.value_len)
+    }
+
+    fn is_expired(&self, now: u64) -> bool {
+        self.expires_at != 0 && self.expires_at <= now
+    }
+}
+
+/// Fixed-capacity key/value cache that evicts expired and least-recently-used entries.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::time::Duration;
+/// use mmap_io::cache::MmapCache;
+///
+/// let mut cache = MmapCache::create("results.cache", 16 << 20)?;
+/// cache.insert(b"fib(90)", b"2880067194370816120", None)?;
+/// cache.insert(b"weather", b"sunny", Some(Duration::from_secs(600)))?;
+///
+/// if let Some(value) = cache.get(b"fib(90)")? {
+///     assert_eq!(&*value, b"2880067194370816120");
+/// }
+/// cache.flush()?;
+/// # Ok::<(), mmap_io::MmapIoError>(())
+/// ```
+#[derive(Debug)]
+pub struct MmapCache {
+    mmap: MemoryMappedFile,
+    header: Header,
+}
+
+impl MmapCache {
+    /// Create a cache at `path` holding up to `capacity` bytes of keys and values (truncating
+    /// if it exists). The entry limit is `capacity / 128`, at least 16.
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from `MemoryMappedFile::create_rw`.
+    pub fn create<P: AsRef<Path>>(path: P, capacity: u64) -> Result<Self> {
+        Self::create_with_entries(path, capacity, capacity / BYTES_PER_ENTRY)
+    }
+
+    /// Create a cache holding up to `capacity` bytes and `max_entries` entries.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::ResizeFailed` if the slot table would be too large.
+    /// Returns errors from `MemoryMappedFile::create_rw`.
+    pub fn create_with_entries<P: AsRef<Path>>(
+        path: P,
+        capacity: u64,
+        max_entries: u64,
+    ) -> Result<Self> {
+        let max_entries = max_entries.max(MIN_ENTRIES);
+        let slot_count = max_entries
+            .checked_mul(2)
+            .and_then(u64::checked_next_power_of_two)
+            .ok_or_else(|| MmapIoError::ResizeFailed("too many cache entries".into()))?;
+        let header = Header {
+            slot_count,
+            max_entries,
+            capacity,
+            len: 0,
+            arena_end: 0,
+            live_bytes: 0,
+        };
+        let mmap = MemoryMappedFile::create_rw(path, arena_start(slot_count) + capacity)?;
+        let mut bytes = [0u8; 16];
+        bytes[..8].copy_from_slice(&MAGIC);
+        bytes[8..12].copy_from_slice(&VERSION.to_le_bytes());
+        mmap.update_region(0, &bytes)?;
+        let cache = Self { mmap, header };
+        cache.write_header()?;
+        Ok(cache)
+    }
+
+    /// Open an existing cache file with its contents intact.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidFormat` if the file is not a valid cache.
+    /// Returns errors from `MemoryMappedFile::open_rw`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mmap = MemoryMappedFile::open_rw(path)?;
+        let total = mmap.current_len()?;
+        let mut bytes = [0u8; CLOCK_OFFSET as usize];
+        if total < HEADER_LEN {
+            return Err(invalid("file too small for MmapCache header"));
+        }
+        mmap.read_into(0, &mut bytes)?;
+        if bytes[..8] != MAGIC || read_u32(&bytes, 8) != VERSION {
+            return Err(invalid("missing or unsupported MmapCache header"));
+        }
+        let header = Header {
+            slot_count: read_u64(&bytes, 16),
+            max_entries: read_u64(&bytes, 24),
+            capacity: read_u64(&bytes, 32),
+            len: read_u64(&bytes, 40),
+            arena_end: read_u64(&bytes, 48),
+            live_bytes: read_u64(&bytes, 56),
+        };
+        let end = header
+            .slot_count
+            .checked_mul(SLOT_LEN)
+            .and_then(|n| n.checked_add(HEADER_LEN))
+            .and_then(|n| n.checked_add(header.capacity));
+        if !header.slot_count.is_power_of_two()
+            || end != Some(total)
+            || header.len > header.max_entries
+            || header.max_entries >= header.slot_count
+            || header.live_bytes > header.arena_end
+            || header.arena_end > header.capacity
+        {
+            return Err(MmapIoError::InvalidFormat(format!(
+                "MmapCache header inconsistent with file length {total}: {header:?}"
+            )));
+        }
+        Ok(Self { mmap, header })
+    }
+
+    /// Number of entries, including expired entries not yet evicted.
+    #[must_use]
+    pub fn len(&self) -> u64 {
+        self.header.len
+    }
+
+    /// Whether the cache has no entries.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.header.len == 0
+    }
+
+    /// Maximum bytes of keys and values.
+    #[must_use]
+    pub fn capacity(&self) -> u64 {
+        self.header.capacity
+    }
+
+    /// Maximum number of entries.
+    #[must_use]
+    pub fn max_entries(&self) -> u64 {
+        self.header.max_entries
+    }
+
+    /// Bytes of keys and values currently stored.
+    #[must_use]
+    pub fn used_bytes(&self) -> u64 {
+        self.header.live_bytes
+    }
+
+    /// Underlying mapping.
+    #[must_use]
+    pub fn mmap(&self) -> &MemoryMappedFile {
+        &self.mmap
+    }
+
+    /// Value for `key` if present and not expired. Marks the entry as most recently used.
+    ///
+    /// The returned view holds the mapping's read lock until dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidFormat` if the slot table is corrupt.
+    pub fn get(&self, key: &[u8]) -> Result<Option<MappedSlice<'_>>> {
+        let Some((index, slot)) = self.find(key)? else {
+            return Ok(None);
+        };
+        if slot.is_expired(now_millis()) {
+            return Ok(None);
+        }
+        let tick = self.tick()?;
+        self.last_access(index)?.fetch_max(tick, Ordering::Relaxed);
+        let value = arena_start(self.header.slot_count) + slot.offset + u64::from(slot.key_len);
+        self.mmap
+            .as_slice_guarded(value, u64::from(slot.value_len))
+            .map(Some)
+    }
+
+    /// Whether `key` is present and not expired, without touching its recency.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidFormat` if the slot table is corrupt.
+    pub fn contains_key(&self, key: &[u8]) -> Result<bool> {
+        let now = now_millis();
+        Ok(self
+            .find(key)?
+            .is_some_and(|(_, slot)| !slot.is_expired(now)))
+    }
+
+    /// Insert or replace `key`, expiring after `ttl` if given. Expired entries and then
+    /// least-recently-used entries are evicted until the new entry fits.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidMode` if the entry is larger than the capacity or the key
+    /// or value is 4 GiB or larger.
+    /// Returns errors from `MemoryMappedFile::update_region`.
+    pub fn insert(&mut self, key: &[u8], value: &[u8], ttl: Option<Duration>) -> Result<()> {
+        let (Ok(key_len), Ok(value_len)) = (u32::try_from(key.len()), u32::try_from(value.len()))
+        else {
+            return Err(MmapIoError::InvalidMode(
+                "MmapCache keys and values must be shorter than 4 GiB",
+            ));
+        };
+        let need = u64::from(key_len) + u64::from(value_len);
+        if need > self.header.capacity {
+            return Err(MmapIoError::InvalidMode(
+                "MmapCache entry is larger than the cache capacity",
+            ));
+        }
+        if let Some((index, _)) = self.find(key)? {
+            self.remove_at(index)?;
+        }
+        self.make_room(need)?;
+
+        let offset = self.header.arena_end;
+        let start = arena_start(self.header.slot_count) + offset;
+        self.mmap.update_region(start, key)?;
+        if !value.is_empty() {
+            self.mmap.update_region(start + u64::from(key_len), value)?;
+        }
+        let expires_at = ttl.map_or(0, |ttl| {
+            let ttl = u64::try_from(ttl.as_millis()).unwrap_or(u64::MAX);
+            now_millis().saturating_add(ttl).max(1)
+        });
+        let slot = Slot {
+            hash: hash_key(key),
+            offset,
+            key_len,
+            value_len,
+            expires_at,
+        };
+        let mask = self.header.slot_count - 1;
+        let mut index = slot.hash & mask;
+        while self.read_slot(index)?.hash != 0 {
+            index = (index + 1) & mask;
+        }
+        self.write_slot(index, &slot)?;
+        let tick = self.tick()?;
+        self.last_access(index)?.store(tick, Ordering::Relaxed);
+
+        self.header.arena_end += need;
+        self.header.live_bytes += need;
+        self.header.len += 1;
+        self.write_header()
+    }
+
+    /// Remove `key`. Returns `true` if it was present (even if expired).
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from `MemoryMappedFile::update_region`.
+    pub fn remove(&mut self, key: &[u8]) -> Result<bool> {
+        match self.find(key)? {
+            Some((index, _)) => {
+                self.remove_at(index)?;
+                self.write_header()?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Evict every expired entry and return how many were removed.
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from `MemoryMappedFile::update_region`.
+    pub fn purge_expired(&mut self) -> Result<u64> {
+        let now = now_millis();
+        let mut removed = 0;
+        let mut index = 0;
+        while index < self.header.slot_count {
+            let slot = self.read_slot(index)?;
+            // Backward-shift deletion may move an unvisited entry into this slot, so only
+            // advance when nothing was removed
+            if slot.hash != 0 && slot.is_expired(now) {
+                self.remove_at(index)?;
+                removed += 1;
+            } else {
+                index += 1;
+            }
+        }
+        self.write_header()?;
+        Ok(removed)
+    }
+
+    /// Remove every entry.
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from `MemoryMappedFile::update_region`.
+    pub fn clear(&mut self) -> Result<()> {
+        let table = vec![0u8; (self.header.slot_count * SLOT_LEN) as usize];
+        self.mmap.update_region(HEADER_LEN, &table)?;
+        self.header.len = 0;
+        self.header.arena_end = 0;
+        self.header.live_bytes = 0;
+        self.write_header()
+    }
+
+    /// Change the byte capacity, evicting least-recently-used entries if the cache shrinks
+    /// below its contents.
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from `MemoryMappedFile::resize` or `update_region`.
+    pub fn set_capacity(&mut self, capacity: u64) -> Result<()> {
+        if self.header.live_bytes > capacity {
+            self.purge_expired()?;
+        }
+        while self.header.live_bytes > capacity {
+            self.evict_lru()?;
+        }
+        self.compact()?;
+        self.mmap
+            .resize(arena_start(self.header.slot_count) + capacity)?;
+        self.header.capacity = capacity;
+        self.write_header()
+    }
+
+    /// Flush the cache file to disk.
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from `MemoryMappedFile::flush`.
+    pub fn flush(&self) -> Result<()> {
+        self.mmap.flush()
+    }
+
+    fn find(&self, key: &[u8]) -> Result<Option<(u64, Slot)>> {
+        let hash = hash_key(key);
+        let mask = self.header.slot_count - 1;
+        let mut index = hash & mask;
+        for _ in 0..self.header.slot_count {
+            let slot = self.read_slot(index)?;
+            if slot.hash == 0 {
+                return Ok(None);
+            }
+            if slot.hash == hash && slot.key_len as usize == key.len() {
+                let start = arena_start(self.header.slot_count) + slot.offset;
+                if *self.mmap.as_slice_guarded(start, u64::from(slot.key_len))? == *key {
+                    return Ok(Some((index, slot)));
+                }
+            }
+            index = (index + 1) & mask;
+        }
+        Ok(None)
+    }
+
+    fn make_room(&mut self, need: u64) -> Result<()> {
+        let full = |h: &Header| h.len >= h.max_entries || h.live_bytes + need > h.capacity;
+        if full(&self.header) {
+            self.purge_expired()?;
+        }
+        while full(&self.header) {
+            self.evict_lru()?;
+        }
+        if self.header.arena_end + need > self.header.capacity {
+            self.compact()?;
+        }
+        Ok(())
+    }
+
+    fn evict_lru(&mut self) -> Result<()> {
+        let mut victim = None;
+        for index in 0..self.header.slot_count {
+            if self.read_slot(index)?.hash == 0 {
+                continue;
+            }
+            let tick = self.last_access(index)?.load(Ordering::Relaxed);
+            if victim.map_or(true, |(_, oldest)| tick < oldest) {
+                victim = Some((index, tick));
+            }
+        }
+        let (index, _) = victim.ok_or_else(|| invalid("MmapCache is full but has no entries"))?;
+        self.remove_at(index)
+    }
+
+    /// Remove the entry in slot `index` (header is updated in memory only).
+    fn remove_at(&mut self, mut index: u64) -> Result<()> {
+        let removed = self.read_slot(index)?;
+        self.header.len -= 1;
+        self.header.live_bytes -= removed.size();
+        let mask = self.header.slot_count - 1;
+        let mut next = index;
+        loop {
+            next = (next + 1) & mask;
+            let slot = self.read_slot(next)?;
+            if slot.hash == 0 {
+                break;
+            }
+            // Leave entries whose home slot lies cyclically in (index, next]
+            let home = slot.hash & mask;
+            let stays = if index <= next {
+                index < home && home <= next
+            } else {
+                index < home || home <= next
+            };
+            if !stays {
+                let tick = self.last_access(next)?.load(Ordering::Relaxed);
+                self.write_slot(index, &slot)?;
+                self.last_access(index)?.store(tick, Ordering::Relaxed);
+                index = next;
+            }
+        }
+        self.write_slot(index, &Slot::default())?;
+        self.last_access(index)?.store(0, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Move live entries to the front of the arena.
+    fn compact(&mut self) -> Result<()> {
+        let mut live = Vec::with_capacity(self.header.len as usize);
+        for index in 0..self.header.slot_count {
+            let slot = self.read_slot(index)?;
+            if slot.hash != 0 {
+                live.push((index, slot));
+            }
+        }
+        live.sort_unstable_by_key(|(_, slot)| slot.offset);
+        let mut arena = self
+            .mmap
+            .as_slice_mut(arena_start(self.header.slot_count), self.header.capacity)?;
+        let mut end = 0;
+        let mut moved = Vec::new();
+        for (index, mut slot) in live {
+            let (from, len) = (slot.offset as usize, slot.size() as usize);
+            arena.as_mut().copy_within(from..from + len, end as usize);
+            if slot.offset != end {
+                slot.offset = end;
+                moved.push((index, slot));
+            }
+            end += slot.size();
+        }
+        drop(arena);
+        for (index, slot) in moved {
+            self.write_slot(index, &slot)?;
+        }
+        self.header.arena_end = end;
+        Ok(())
+    }
+
+    fn read_slot(&self, index: u64) -> Result<Slot> {
+        // The last-access tick is only touched atomically
+        let mut bytes = [0u8; LAST_ACCESS_OFFSET as usize];
+        self.mmap.read_into(slot_pos(index), &mut bytes)?;
+        let slot = Slot {
+            hash: read_u64(&bytes, 0),
+            offset: read_u64(&bytes, 8),
+            key_len: read_u32(&bytes, 16),
+            value_len: read_u32(&bytes, 20),
+            expires_at: read_u64(&bytes, 24),
+        };
+        if slot.hash != 0
+            && slot
+                .offset
+                .checked_add(slot.size())
+                .map_or(true, |end| end > self.header.arena_end)
+        {
+            return Err(invalid("MmapCache slot points outside the arena"));
+        }
+        Ok(slot)
+    }
+
+    fn write_slot(&self, index: u64, slot: &Slot) -> Result<()> {
+        let mut bytes = [0u8; LAST_ACCESS_OFFSET as usize];
+        bytes[..8].copy_from_slice(&slot.hash.to_le_bytes());
+        bytes[8..16].copy_from_slice(&slot.offset.to_le_bytes());
+        bytes[16..20].copy_from_slice(&slot.key_len.to_le_bytes());
+        bytes[20..24].copy_from_slice(&slot.value_len.to_le_bytes());
+        bytes[24..].copy_from_slice(&slot.expires_at.to_le_bytes());
+        self.mmap.update_region(slot_pos(index), &bytes)
+    }
+
+    fn last_access(&self, index: u64) -> Result<&AtomicU64> {
+        self.mmap.atomic_u64(slot_pos(index) + LAST_ACCESS_OFFSET)
+    }
+
+    fn tick(&self) -> Result<u64> {
+        Ok(self
+            .mmap
+            .atomic_u64(CLOCK_OFFSET)?
+            .fetch_add(1, Ordering::Relaxed)
+            + 1)
+    }
+
+    fn write_header(&self) -> Result<()> {
+        let h = &self.header;
+        let mut bytes = [0u8; 48];
+        for (i, field) in [
+            h.slot_count,
+            h.max_entries,
+            h.capacity,
+            h.len,
+            h.arena_end,
+            h.live_bytes,
+        ]
+        .iter()
+        .enumerate()
+        {
+            bytes[i * 8..i * 8 + 8].copy_from_slice(&field.to_le_bytes());
+        }
+        self.mmap.update_region(16, &bytes)
+    }
+}
+
+fn arena_start(slot_count: u64) -> u64 {
+    HEADER_LEN + slot_count * SLOT_LEN
+}
+
+fn slot_pos(index: u64) -> u64 {
+    HEADER_LEN + index * SLOT_LEN
+}
+
+fn hash_key(key: &[u8]) -> u64 {
+    fnv1a64(key).max(1)
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| u64::try_from(d.as_millis()).unwrap_or(u64::MAX))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().expect("8 bytes"))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().expect("4 bytes"))
+}
+
+fn invalid(msg: &str) -> MmapIoError {
+    MmapIoError::InvalidFormat(msg.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn tmp_path(name: &str) -> PathBuf {
+        let mut p = std::env::temp_dir();
+        p.push(format!(
+            "mmap_io_cache_test_{}_{}",
+            name,
+            std::process::id()
+        ));
+        p
+    }
+
+    fn value(cache: &MmapCache, key: &[u8]) -> Option<Vec<u8>> {
+        cache.get(key).expect("get").map(|v| v.to_vec())
+    }
+
+    #[test]
+    fn test_insert_get_replace_and_reopen() {
+        let path = tmp_path("reopen");
+        let _ = fs::remove_file(&path);
+
+        let mut cache = MmapCache::create(&path, 4096).expect("create");
+        for i in 0..20u32 {
+            cache
+                .insert(format!("k{i}").as_bytes(), &i.to_le_bytes(), None)
+                .expect("insert");
+        }
+        cache.insert(b"k3", b"replaced", None).expect("replace");
+        assert_eq!(cache.len(), 20);
+        assert!(cache.remove(b"k4").expect("remove"));
+        assert!(!cache.remove(b"k4").expect("remove again"));
+        cache.flush().expect("flush");
+        drop(cache);
+
+        let cache = MmapCache::open(&path).expect("open");
+        assert_eq!(cache.len(), 19);
+        assert_eq!(value(&cache, b"k3").as_deref(), Some(&b"replaced"[..]));
+        assert_eq!(value(&cache, b"k4"), None);
+        for i in (5..20u32).chain(0..3) {
+            assert_eq!(
+                value(&cache, format!("k{i}").as_bytes()),
+                Some(i.to_le_bytes().to_vec())
+            );
+        }
+
+        fs::remove_file(&path).expect("cleanup");
+    }
+
+    #[test]
+    fn test_lru_eviction_by_bytes_and_entries() {
+        let path = tmp_path("lru");
+        let _ = fs::remove_file(&path);
+
+        // Room for four 100-byte entries
+        let mut cache = MmapCache::create(&path, 400).expect("create");
+        for key in [b"a", b"b", b"c", b"d"] {
+            cache.insert(key, &[0u8; 99], None).expect("insert");
+        }
+        // Touch "a" so "b" becomes the least recently used
+        assert!(cache.get(b"a").expect("get").is_some());
+        cache.insert(b"e", &[1u8; 99], None).expect("insert");
+        assert!(!cache.contains_key(b"b").expect("contains"));
+        for key in [b"a", b"c", b"d", b"e"] {
+            assert!(cache.contains_key(key).expect("contains"));
+        }
+        assert_eq!(cache.used_bytes(), 400);
+        assert!(cache.insert(b"huge", &[0u8; 400], None).is_err());
+
+        // Entry-count limit evicts even when bytes remain
+        let mut small = MmapCache::create_with_entries(&path, 1 << 20, 16).expect("create");
+        for i in 0..40u32 {
+            small.insert(&i.to_le_bytes(), b"v", None).expect("insert");
+        }
+        assert_eq!(small.len(), 16);
+        assert!(small.contains_key(&39u32.to_le_bytes()).expect("contains"));
+        assert!(!small.contains_key(&0u32.to_le_bytes()).expect("contains"));
+
+        fs::remove_file(&path).expect("cleanup");
+    }
+
+    #[test]
+    fn test_ttl_expiry() {
+        let path = tmp_path("ttl");
+        let _ = fs::remove_file(&path);
+
+        let mut cache = MmapCache::create(&path, 300).expect("create");
+        cache
+            .insert(b"short", &[0u8; 95], Some(Duration::from_millis(1)))
+            .expect("insert");
+        cache.insert(b"old", &[0u8; 97], None).expect("insert");
+        cache
+            .insert(b"long", &[0u8; 96], Some(Duration::from_secs(3600)))
+            .expect("insert");
+        std::thread::sleep(Duration::from_millis(20));
+
+        assert!(cache.get(b"short").expect("get").is_none());
+        assert!(cache.get(b"long").expect("get").is_some());
+        // The expired entry is evicted before the least recently used one
+        cache.insert(b"new", &[0u8; 97], None).expect("insert");
+        assert!(cache.contains_key(b"old").expect("contains"));
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.purge_expired().expect("purge"), 0);
+
+        fs::remove_file(&path).expect("cleanup");
+    }
+
+    #[test]
+    fn test_fragmented_arena_and_set_capacity() {
+        let path = tmp_path("capacity");
+        let _ = fs::remove_file(&path);
+
+        let mut cache = MmapCache::create(&path, 1000).expect("create");
+        for i in 0..10u8 {
+            cache.insert(&[i], &[i; 99], None).expect("insert");
+        }
+        for i in (0..10u8).step_by(2) {
+            cache.remove(&[i]).expect("remove");
+        }
+        // Needs compaction: 500 bytes free, but none at the end of the arena
+        cache.insert(b"big", &[7u8; 400], None).expect("insert");
+        assert_eq!(cache.len(), 6);
+        for i in (1..10u8).step_by(2) {
+            assert_eq!(value(&cache, &[i]), Some(vec![i; 99]));
+        }
+
+        // Make "big" the most recently used, then shrink
+        assert!(cache.get(b"big").expect("get").is_some());
+        cache.set_capacity(500).expect("shrink");
+        assert_eq!(cache.capacity(), 500);
+        assert!(cache.used_bytes() <= 500);
+        assert_eq!(value(&cache, b"big"), Some(vec![7u8; 400]));
+        cache.set_capacity(2000).expect("grow");
+        cache.insert(b"more", &[1u8; 1000], None).expect("insert");
+        drop(cache);
+        assert_eq!(MmapCache::open(&path).expect("open").capacity(), 2000);
+
+        fs::remove_file(&path).expect("cleanup");
+    }
+}
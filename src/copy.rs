@@ -0,0 +1,242 @@
+//! Point-in-time snapshots of a mapping and copies between mappings.
+//!
+//! Snapshots of file-backed mappings are made by the kernel where it can: a `FICLONE`
+//! reflink shares the blocks on copy-on-write filesystems, and `copy_file_range` copies
+//! without passing the data through user space. Otherwise the mapped bytes are written out
+//! directly. Copies between mappings go from one mapping to the other with no buffer between,
+//! except where two mappings of one file overlap.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::errors::{MmapIoError, Result};
+use crate::mmap::{copy_guarded, MapVariant, MemoryMappedFile, MmapMode};
+use crate::utils::ensure_in_bounds;
+
+// Bytes copied between progress callbacks, and between releases of the mapping locks.
+const COPY_CHUNK: u64 = 8 * 1024 * 1024;
+
+impl MemoryMappedFile {
+    /// Write a point-in-time copy of the mapped bytes to a new file at `path`.
+    ///
+    /// The mapping is flushed first, and writers to it are held off until the copy is done.
+    /// On Linux a mapping of a whole file is cloned with `FICLONE` where the filesystem
+    /// supports reflinks (Btrfs, XFS), which is near-instant and shares blocks until either
+    /// file changes. Otherwise the file is copied in the kernel with `copy_file_range`, or,
+    /// where that is not available, from the mapping. Windowed mappings copy only the
+    /// window, and copy-on-write mappings copy what they show, including private changes.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidMode` if `path` is the mapped file itself.
+    /// Returns `MmapIoError::Io` if the destination cannot be created or written.
+    /// Returns errors from [`flush`](Self::flush).
+    pub fn snapshot_to<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let mapped = fs::canonicalize(&self.inner.path).ok();
+        if path.exists() && mapped == Some(fs::canonicalize(path)?) {
+            return Err(MmapIoError::InvalidMode(
+                "snapshot destination is the mapped file",
+            ));
+        }
+        self.flush()?;
+        let len = self.current_len()?;
+        // The view holds the read lock of a ReadWrite mapping for the whole copy
+        let view = self.as_slice_guarded(0, len)?;
+        let mut dst = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        let copied = match &self.inner.file {
+            Some(file) if self.inner.mode != MmapMode::CopyOnWrite => {
+                let base = self.inner.window_offset.unwrap_or(0);
+                copy_in_kernel(file, base, len, &dst)?
+            }
+            _ => false,
+        };
+        if !copied {
+            dst.write_all(&view)?;
+        }
+        Ok(())
+    }
+}
+
+/// Copy `len` bytes from `src` to `dst` chunk by chunk, calling `progress` after each one.
+pub(crate) fn copy_range(
+    src: &MemoryMappedFile,
+    src_offset: u64,
+    dst: &MemoryMappedFile,
+    dst_offset: u64,
+    len: u64,
+    progress: &mut dyn FnMut(u64, u64),
+) -> Result<()> {
+    if dst.inner.mode != MmapMode::ReadWrite {
+        return Err(MmapIoError::InvalidMode(
+            "Copy range requires a ReadWrite destination.",
+        ));
+    }
+    ensure_in_bounds(src_offset, len, src.current_len()?)?;
+    dst.check_truncated(dst_offset, len)?;
+    dst.grow_for_write(dst_offset, len)?;
+    ensure_in_bounds(dst_offset, len, dst.current_len()?)?;
+    // Positions in the file, so that two mappings of one file are compared correctly
+    let src_at = src.inner.window_offset.unwrap_or(0) + src_offset;
+    let dst_at = dst.inner.window_offset.unwrap_or(0) + dst_offset;
+    let overlapping = src_at < dst_at + len && dst_at < src_at + len && same_bytes(src, dst);
+    // Copying forwards would overwrite source bytes that later chunks still need
+    let backwards = overlapping && dst_at > src_at;
+    let mut done = 0;
+    while done < len {
+        let n = (len - done).min(COPY_CHUNK);
+        let at = if backwards { len - done - n } else { done };
+        copy_chunk(src, src_offset + at, dst, dst_offset + at, n, overlapping)?;
+        done += n;
+        progress(done, len);
+    }
+    dst.mark_used((dst_offset + len) as usize);
+    dst.apply_flush_policy(len)
+}
+
+// Whether `src` and `dst` show the same bytes: one mapping, or two mappings of one file.
+fn same_bytes(src: &MemoryMappedFile, dst: &MemoryMappedFile) -> bool {
+    Arc::ptr_eq(&src.inner, &dst.inner) || same_file(&src.inner.file, &dst.inner.file)
+}
+
+#[cfg(unix)]
+fn same_file(a: &Option<File>, b: &Option<File>) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    let (Some(a), Some(b)) = (a, b) else {
+        return false;
+    };
+    match (a.metadata(), b.metadata()) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+// Without inode numbers two mappings of one file cannot be told apart here
+#[cfg(not(unix))]
+fn same_file(_a: &Option<File>, _b: &Option<File>) -> bool {
+    false
+}
+
+// Copy one in-bounds chunk straight from the source mapping into the destination mapping.
+// `overlapping` chunks of two mappings of one file go through a buffer, since the bytes
+// alias behind the two mappings' backs.
+fn copy_chunk(
+    src: &MemoryMappedFile,
+    src_offset: u64,
+    dst: &MemoryMappedFile,
+    dst_offset: u64,
+    len: u64,
+    overlapping: bool,
+) -> Result<()> {
+    let (start, end) = (src_offset as usize, (src_offset + len) as usize);
+    let to = dst_offset as usize..(dst_offset + len) as usize;
+    let MapVariant::Rw(dst_lock) = &dst.inner.map else {
+        return Err(MmapIoError::InvalidMode(
+            "Copy range requires a ReadWrite destination.",
+        ));
+    };
+    if Arc::ptr_eq(&src.inner, &dst.inner) {
+        dst_lock.write().copy_within(start..end, to.start);
+        return Ok(());
+    }
+    if overlapping {
+        let mut buf = vec![0u8; len as usize];
+        match &src.inner.map {
+            MapVariant::Ro(m) | MapVariant::Cow(m) => {
+                copy_guarded(&src.inner, src_offset, &m[start..end], &mut buf)?;
+            }
+            MapVariant::Rw(src_lock) => {
+                copy_guarded(
+                    &src.inner,
+                    src_offset,
+                    &src_lock.read()[start..end],
+                    &mut buf,
+                )?;
+            }
+        }
+        dst_lock.write()[to].copy_from_slice(&buf);
+        return Ok(());
+    }
+    match &src.inner.map {
+        MapVariant::Ro(m) | MapVariant::Cow(m) => copy_guarded(
+            &src.inner,
+            src_offset,
+            &m[start..end],
+            &mut dst_lock.write()[to],
+        ),
+        MapVariant::Rw(src_lock) => {
+            // Lock in address order so that copies in opposite directions cannot deadlock
+            if Arc::as_ptr(&src.inner) < Arc::as_ptr(&dst.inner) {
+                let from = src_lock.read();
+                copy_guarded(
+                    &src.inner,
+                    src_offset,
+                    &from[start..end],
+                    &mut dst_lock.write()[to],
+                )
+            } else {
+                let mut into = dst_lock.write();
+                copy_guarded(
+                    &src.inner,
+                    src_offset,
+                    &src_lock.read()[start..end],
+                    &mut into[to],
+                )
+            }
+        }
+    }
+}
+
+// Copy `[offset, offset + len)` of `src` to the start of the empty file `dst` without
+// going through user space. Returns `false` if the kernel cannot, before anything is written.
+#[cfg(target_os = "linux")]
+fn copy_in_kernel(src: &File, offset: u64, len: u64, dst: &File) -> Result<bool> {
+    use std::os::unix::io::AsRawFd;
+    let (src_fd, dst_fd) = (src.as_raw_fd(), dst.as_raw_fd());
+    if offset == 0 && len == src.metadata()?.len() {
+        // SAFETY: FICLONE takes the source descriptor as its argument; both are open.
+        if unsafe { libc::ioctl(dst_fd, libc::FICLONE, src_fd) } == 0 {
+            return Ok(true);
+        }
+    }
+    let mut off_in = offset as libc::loff_t;
+    let mut off_out: libc::loff_t = 0;
+    let mut left = len;
+    while left > 0 {
+        let want = left.min(1 << 30) as usize;
+        // SAFETY: both descriptors are open and the offsets are valid for writes.
+        let n =
+            unsafe { libc::copy_file_range(src_fd, &mut off_in, dst_fd, &mut off_out, want, 0) };
+        if n < 0 {
+            let err = std::io::Error::last_os_error();
+            let unsupported = matches!(
+                err.raw_os_error(),
+                Some(libc::ENOSYS | libc::EXDEV | libc::EOPNOTSUPP | libc::EINVAL)
+            );
+            if unsupported && off_out == 0 {
+                return Ok(false);
+            }
+            return Err(MmapIoError::Io(err));
+        }
+        if n == 0 {
+            // The file is shorter than the mapping
+            return Err(MmapIoError::Truncated {
+                offset: off_out as u64,
+                len: left,
+            });
+        }
+        left -= n as u64;
+    }
+    Ok(true)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn copy_in_kernel(_src: &File, _offset: u64, _len: u64, _dst: &File) -> Result<bool> {
+    Ok(false)
+}
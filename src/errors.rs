@@ -7,7 +7,10 @@ use thiserror::Error;
 pub type Result<T> = std::result::Result<T, MmapIoError>;
 
 /// Error type covering filesystem, mapping, bounds, and concurrency issues.
+///
+/// New variants may be added in minor releases, so matches need a wildcard arm.
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum MmapIoError {
     /// Wrapper for `std::io::Error`.
     #[error("I/O error: {0}")]
@@ -60,4 +63,18 @@ pub enum MmapIoError {
     /// Error when starting or running a watcher fails.
     #[error("watch failed: {0}")]
     WatchFailed(String),
+
+    /// Error when mapped data does not match the expected on-disk format.
+    #[error("invalid format: {0}")]
+    InvalidFormat(String),
+
+    /// Error when a read touched pages that were cut from the file by a truncation while it
+    /// was mapped. Reported only while the SIGBUS handler is installed.
+    #[error("mapped file was truncated: offset={offset}, len={len} is no longer backed")]
+    Truncated {
+        /// Offset of the access.
+        offset: u64,
+        /// Length of the access.
+        len: u64,
+    },
 }
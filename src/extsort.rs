@@ -0,0 +1,578 @@
+//! External merge sort for fixed-size records in memory-mapped files.
+//!
+//! Sorting runs in two phases:
+//!
+//! 1. The input is split into runs that fit the memory budget, and each run is sorted in place
+//!    with [`MemoryMappedFile::chunks_mut`].
+//! 2. Runs are k-way merged through small read buffers. If there are more runs than the
+//!    budget allows buffers for, groups of runs are merged into temporary mapped files first,
+//!    and the passes repeat until one merge can write the output.
+//!
+//! The input is advised `Sequential` before the first phase. Merge buffers advise
+//! `DontNeed` on every window they have copied out of a file, and on every window written to
+//! the output, so the page cache does not fill up with data that will not be read again.
+
+use std::cmp::Ordering;
+use std::convert::Infallible;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::advise::MmapAdvice;
+use crate::errors::{MmapIoError, Result};
+use crate::mmap::{MemoryMappedFile, MmapMode};
+use crate::pod::{pod_from_bytes, Pod};
+
+/// Target size of one merge read buffer; the merge fan-in is the budget divided by this.
+const MERGE_BUFFER: u64 = 1 << 20;
+const MAX_FAN_IN: u64 = 1024;
+
+/// Counters describing a finished sort.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SortStats {
+    /// Number of records sorted.
+    pub records: u64,
+    /// Number of sorted runs produced by the first phase.
+    pub runs: u64,
+    /// Number of merge passes, including the final one into the output.
+    pub merge_passes: u32,
+}
+
+/// External merge sorter with a memory budget.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mmap_io::extsort::ExternalSorter;
+/// use mmap_io::MemoryMappedFile;
+///
+/// let input = MemoryMappedFile::open_rw("unsorted.u64")?;
+/// let output = MemoryMappedFile::create_rw("sorted.u64", input.len())?;
+///
+/// let stats = ExternalSorter::new(256 << 20).sort::<u64>(&input, &output)?;
+/// println!("sorted {} records in {} runs", stats.records, stats.runs);
+/// output.flush()?;
+/// # Ok::<(), mmap_io::MmapIoError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct ExternalSorter {
+    memory_budget: u64,
+    temp_dir: PathBuf,
+}
+
+impl ExternalSorter {
+    /// Sorter that keeps run buffers and merge buffers within about `memory_budget` bytes.
+    /// Spill files go to the system temporary directory.
+    #[must_use]
+    pub fn new(memory_budget: u64) -> Self {
+        Self {
+            memory_budget,
+            temp_dir: std::env::temp_dir(),
+        }
+    }
+
+    /// Create spill files in `dir` instead of the system temporary directory.
+    #[must_use]
+    pub fn temp_dir<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.temp_dir = dir.as_ref().to_path_buf();
+        self
+    }
+
+    /// Memory budget in bytes.
+    #[must_use]
+    pub fn memory_budget(&self) -> u64 {
+        self.memory_budget
+    }
+
+    /// Sort `T` records from `input` into `output` in ascending order.
+    ///
+    /// `input` must be a read-write mapping whose length is a multiple of `size_of::<T>()`;
+    /// it is left holding sorted runs. `output` must be a different mapping at least as long
+    /// as the input. Nothing is flushed.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidMode` if `input` is not read-write or both arguments are
+    /// the same mapping.
+    /// Returns `MmapIoError::InvalidFormat` if the input length is not a multiple of the
+    /// record size.
+    /// Returns `MmapIoError::OutOfBounds` if `output` is shorter than `input`.
+    /// Returns errors from creating or writing spill files.
+    pub fn sort<T: Pod + Ord>(
+        &self,
+        input: &MemoryMappedFile,
+        output: &MemoryMappedFile,
+    ) -> Result<SortStats> {
+        let record_len = std::mem::size_of::<T>();
+        let compare = |a: &[u8], b: &[u8]| pod_from_bytes::<T>(a).cmp(&pod_from_bytes::<T>(b));
+        let mut scratch = RunScratch::default();
+        let mut sort_run = |run: &mut [u8]| {
+            // SAFETY: T is Pod, so any properly aligned bytes are valid values.
+            let (prefix, records, suffix) = unsafe { run.align_to_mut::<T>() };
+            if prefix.is_empty() && suffix.is_empty() {
+                records.sort_unstable();
+            } else {
+                scratch.sort(run, record_len, &compare);
+            }
+        };
+        // Runs are sorted in place, so the whole budget can go to one run
+        self.run(
+            input,
+            output,
+            record_len,
+            self.memory_budget,
+            &compare,
+            &mut sort_run,
+        )
+    }
+
+    /// Sort byte records of `record_len` bytes by the key `key` extracts from each record.
+    /// The sort is stable.
+    ///
+    /// Requirements on `input` and `output` are the same as for [`sort`](Self::sort).
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidFormat` if `record_len` is zero or does not divide the
+    /// input length.
+    /// Otherwise the same as [`sort`](Self::sort).
+    pub fn sort_by_key<K, F>(
+        &self,
+        input: &MemoryMappedFile,
+        output: &MemoryMappedFile,
+        record_len: usize,
+        key: F,
+    ) -> Result<SortStats>
+    where
+        K: Ord,
+        F: Fn(&[u8]) -> K,
+    {
+        let compare = |a: &[u8], b: &[u8]| key(a).cmp(&key(b));
+        let mut scratch = RunScratch::default();
+        let mut sort_run = |run: &mut [u8]| scratch.sort(run, record_len, &compare);
+        // Sorting a run needs a copy of it, so runs get half the budget
+        self.run(
+            input,
+            output,
+            record_len,
+            self.memory_budget / 2,
+            &compare,
+            &mut sort_run,
+        )
+    }
+
+    fn run(
+        &self,
+        input: &MemoryMappedFile,
+        output: &MemoryMappedFile,
+        record_len: usize,
+        run_budget: u64,
+        compare: &dyn Fn(&[u8], &[u8]) -> Ordering,
+        sort_run: &mut dyn FnMut(&mut [u8]),
+    ) -> Result<SortStats> {
+        if Arc::ptr_eq(&input.inner, &output.inner) {
+            return Err(MmapIoError::InvalidMode(
+                "external sort output must be a different mapping than the input",
+            ));
+        }
+        if input.mode() != MmapMode::ReadWrite {
+            return Err(MmapIoError::InvalidMode(
+                "external sort sorts runs in place and needs a ReadWrite input",
+            ));
+        }
+        let total = input.current_len()?;
+        let record = record_len as u64;
+        if record == 0 || total % record != 0 {
+            return Err(MmapIoError::InvalidFormat(format!(
+                "input length {total} is not a multiple of the record size {record_len}"
+            )));
+        }
+        let output_len = output.current_len()?;
+        if output_len < total {
+            return Err(MmapIoError::OutOfBounds {
+                offset: 0,
+                len: total,
+                total: output_len,
+            });
+        }
+        let mut stats = SortStats {
+            records: total / record,
+            ..SortStats::default()
+        };
+        if total == 0 {
+            return Ok(stats);
+        }
+
+        // Phase 1: sort runs in place
+        let run_len = (run_budget / record).max(1) * record;
+        // Advice is only a hint; failures are ignored here and below.
+        let _ = input.advise(0, total, MmapAdvice::Sequential);
+        let sorted = input
+            .chunks_mut(usize::try_from(run_len).unwrap_or(usize::MAX))
+            .for_each_mut(|_, run| {
+                sort_run(run);
+                Ok::<(), Infallible>(())
+            })?;
+        if let Err(never) = sorted {
+            match never {}
+        }
+        let runs: Vec<Run<'_>> = (0..total)
+            .step_by(run_len as usize)
+            .map(|offset| Run {
+                mmap: input,
+                offset,
+                len: run_len.min(total - offset),
+            })
+            .collect();
+        stats.runs = runs.len() as u64;
+
+        // Phase 2: merge, spilling intermediate passes to temporary files
+        let fan_in = (self.memory_budget / MERGE_BUFFER)
+            .saturating_sub(1)
+            .clamp(2, MAX_FAN_IN) as usize;
+        let buffer_len = ((self.memory_budget / (fan_in as u64 + 1)) / record).max(1) * record;
+        let mut spilled: Vec<MemoryMappedFile> = Vec::new();
+        loop {
+            stats.merge_passes += 1;
+            let sources: Vec<Run<'_>> = if spilled.is_empty() {
+                runs.clone()
+            } else {
+                spilled
+                    .iter()
+                    .map(|mmap| Run {
+                        mmap,
+                        offset: 0,
+                        len: mmap.len(),
+                    })
+                    .collect()
+            };
+            if sources.len() <= fan_in {
+                merge(&sources, output, record_len, buffer_len, compare)?;
+                return Ok(stats);
+            }
+            let mut next = Vec::with_capacity(sources.len().div_ceil(fan_in));
+            for group in sources.chunks(fan_in) {
+                let len = group.iter().map(|run| run.len).sum();
+                let spill = MemoryMappedFile::temp_in(&self.temp_dir, len)?;
+                merge(group, &spill, record_len, buffer_len, compare)?;
+                next.push(spill);
+            }
+            spilled = next;
+        }
+    }
+}
+
+/// A sorted run of records inside a mapping.
+#[derive(Clone, Copy)]
+struct Run<'a> {
+    mmap: &'a MemoryMappedFile,
+    offset: u64,
+    len: u64,
+}
+
+/// Reusable buffers for sorting byte records: an order vector and a copy of the run.
+#[derive(Default)]
+struct RunScratch {
+    order: Vec<usize>,
+    copy: Vec<u8>,
+}
+
+impl RunScratch {
+    fn sort(
+        &mut self,
+        run: &mut [u8],
+        record_len: usize,
+        compare: &dyn Fn(&[u8], &[u8]) -> Ordering,
+    ) {
+        let count = run.len() / record_len;
+        self.copy.clear();
+        self.copy.extend_from_slice(run);
+        let copy = &self.copy;
+        let record = |i: usize| &copy[i * record_len..(i + 1) * record_len];
+        self.order.clear();
+        self.order.extend(0..count);
+        self.order.sort_by(|&a, &b| compare(record(a), record(b)));
+        for (dst, &src) in run.chunks_exact_mut(record_len).zip(&self.order) {
+            dst.copy_from_slice(record(src));
+        }
+    }
+}
+
+/// Buffered reader over one run.
+struct Cursor<'a> {
+    run: Run<'a>,
+    read: u64,
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(run: Run<'a>) -> Self {
+        Self {
+            run,
+            read: 0,
+            buf: Vec::new(),
+            pos: 0,
+        }
+    }
+
+    fn current(&self, record_len: usize) -> &[u8] {
+        &self.buf[self.pos..self.pos + record_len]
+    }
+
+    /// Move to the next record. Returns `false` once the run is exhausted.
+    fn advance(&mut self, record_len: usize, buffer_len: u64) -> Result<bool> {
+        self.pos += record_len;
+        if self.pos < self.buf.len() {
+            return Ok(true);
+        }
+        self.fill(buffer_len)
+    }
+
+    /// Copy the next window of the run into the buffer. Returns `false` at the end.
+    fn fill(&mut self, buffer_len: u64) -> Result<bool> {
+        if self.read == self.run.len {
+            return Ok(false);
+        }
+        let len = buffer_len.min(self.run.len - self.read);
+        let offset = self.run.offset + self.read;
+        self.buf.resize(len as usize, 0);
+        self.run.mmap.read_into(offset, &mut self.buf)?;
+        release(self.run.mmap, offset, len);
+        self.read += len;
+        self.pos = 0;
+        Ok(true)
+    }
+}
+
+/// K-way merge of sorted `runs` into `output` starting at offset 0.
+fn merge(
+    runs: &[Run<'_>],
+    output: &MemoryMappedFile,
+    record_len: usize,
+    buffer_len: u64,
+    compare: &dyn Fn(&[u8], &[u8]) -> Ordering,
+) -> Result<()> {
+    let mut cursors = Vec::with_capacity(runs.len());
+    for &run in runs {
+        let mut cursor = Cursor::new(run);
+        if cursor.fill(buffer_len)? {
+            cursors.push(cursor);
+        }
+    }
+    let total: u64 = runs.iter().map(|run| run.len).sum();
+    let _ = output.advise(0, total, MmapAdvice::Sequential);
+
+    // Binary min-heap of cursor indices; ties go to the earlier run to keep merges stable
+    let less = |cursors: &[Cursor<'_>], a: usize, b: usize| match compare(
+        cursors[a].current(record_len),
+        cursors[b].current(record_len),
+    ) {
+        Ordering::Less => true,
+        Ordering::Equal => a < b,
+        Ordering::Greater => false,
+    };
+    let mut heap: Vec<usize> = (0..cursors.len()).collect();
+    for i in (0..heap.len() / 2).rev() {
+        sift_down(&mut heap, i, |a, b| less(&cursors, a, b));
+    }
+
+    let mut out = Vec::with_capacity(buffer_len as usize);
+    let mut written = 0;
+    while let Some(&top) = heap.first() {
+        out.extend_from_slice(cursors[top].current(record_len));
+        if out.len() as u64 >= buffer_len {
+            write_out(output, &mut written, &mut out)?;
+        }
+        if !cursors[top].advance(record_len, buffer_len)? {
+            heap.swap_remove(0);
+        }
+        sift_down(&mut heap, 0, |a, b| less(&cursors, a, b));
+    }
+    write_out(output, &mut written, &mut out)
+}
+
+fn sift_down(heap: &mut [usize], mut i: usize, less: impl Fn(usize, usize) -> bool) {
+    loop {
+        let (left, right) = (2 * i + 1, 2 * i + 2);
+        let mut smallest = i;
+        if left < heap.len() && less(heap[left], heap[smallest]) {
+            smallest = left;
+        }
+        if right < heap.len() && less(heap[right], heap[smallest]) {
+            smallest = right;
+        }
+        if smallest == i {
+            return;
+        }
+        heap.swap(i, smallest);
+        i = smallest;
+    }
+}
+
+fn write_out(output: &MemoryMappedFile, written: &mut u64, out: &mut Vec<u8>) -> Result<()> {
+    if out.is_empty() {
+        return Ok(());
+    }
+    output.update_region(*written, out)?;
+    release(output, *written, out.len() as u64);
+    *written += out.len() as u64;
+    out.clear();
+    Ok(())
+}
+
+/// Drop pages of a window that will not be touched again. `DontNeed` discards private
+/// pages, so this is limited to shared, file-backed mappings.
+fn release(mmap: &MemoryMappedFile, offset: u64, len: u64) {
+    if mmap.inner.file.is_some() && mmap.mode() != MmapMode::CopyOnWrite {
+        let _ = mmap.advise(offset, len, MmapAdvice::DontNeed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn tmp_path(name: &str) -> PathBuf {
+        let mut p = std::env::temp_dir();
+        p.push(format!(
+            "mmap_io_extsort_test_{}_{}",
+            name,
+            std::process::id()
+        ));
+        p
+    }
+
+    /// Deterministic pseudo-random values (xorshift).
+    fn values(n: usize) -> Vec<u64> {
+        let mut x = 0x9e37_79b9_7f4a_7c15u64;
+        (0..n)
+            .map(|_| {
+                x ^= x << 13;
+                x ^= x >> 7;
+                x ^= x << 17;
+                x % 1000
+            })
+            .collect()
+    }
+
+    fn write_u64s(mmap: &MemoryMappedFile, values: &[u64]) {
+        let bytes: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+        mmap.update_region(0, &bytes).expect("write");
+    }
+
+    fn read_u64s(mmap: &MemoryMappedFile, count: usize) -> Vec<u64> {
+        let mut bytes = vec![0u8; count * 8];
+        mmap.read_into(0, &mut bytes).expect("read");
+        bytes
+            .chunks_exact(8)
+            .map(|b| u64::from_le_bytes(b.try_into().expect("8 bytes")))
+            .collect()
+    }
+
+    #[test]
+    fn test_sort_pod_with_multiple_merge_passes() {
+        let input_path = tmp_path("pod_in");
+        let output_path = tmp_path("pod_out");
+        let data = values(20_000);
+        let input = MemoryMappedFile::create_rw(&input_path, 160_000).expect("input");
+        let output = MemoryMappedFile::create_rw(&output_path, 160_000).expect("output");
+        write_u64s(&input, &data);
+
+        // 4 KiB runs give 40 runs; a fan-in of 2 needs several spill passes
+        let stats = ExternalSorter::new(4096)
+            .sort::<u64>(&input, &output)
+            .expect("sort");
+        assert_eq!(stats.records, 20_000);
+        assert_eq!(stats.runs, 40);
+        assert_eq!(stats.merge_passes, 6);
+
+        let mut expected = data;
+        expected.sort_unstable();
+        assert_eq!(read_u64s(&output, 20_000), expected);
+
+        // A budget that fits everything sorts in a single run and pass
+        let output2 = MemoryMappedFile::anonymous(160_000).expect("anon");
+        let stats = ExternalSorter::new(1 << 20)
+            .sort::<u64>(&input, &output2)
+            .expect("sort");
+        assert_eq!((stats.runs, stats.merge_passes), (1, 1));
+        assert_eq!(read_u64s(&output2, 20_000), expected);
+
+        drop((input, output));
+        fs::remove_file(&input_path).expect("cleanup");
+        fs::remove_file(&output_path).expect("cleanup");
+    }
+
+    #[test]
+    fn test_sort_by_key_is_stable() {
+        // 12-byte records: 4-byte key followed by an 8-byte sequence number
+        let count = 5_000u64;
+        let input = MemoryMappedFile::anonymous(count * 12).expect("input");
+        let output = MemoryMappedFile::anonymous(count * 12).expect("output");
+        let keys = values(count as usize);
+        let bytes: Vec<u8> = keys
+            .iter()
+            .zip(0u64..)
+            .flat_map(|(&k, seq)| {
+                let mut record = (k as u32 % 50).to_be_bytes().to_vec();
+                record.extend_from_slice(&seq.to_le_bytes());
+                record
+            })
+            .collect();
+        input.update_region(0, &bytes).expect("write");
+
+        let stats = ExternalSorter::new(3000)
+            .sort_by_key(&input, &output, 12, |r| r[..4].to_vec())
+            .expect("sort");
+        assert!(stats.runs > 2);
+
+        let mut sorted = vec![0u8; bytes.len()];
+        output.read_into(0, &mut sorted).expect("read");
+        let records: Vec<(u32, u64)> = sorted
+            .chunks_exact(12)
+            .map(|r| {
+                (
+                    u32::from_be_bytes(r[..4].try_into().expect("key")),
+                    u64::from_le_bytes(r[4..].try_into().expect("seq")),
+                )
+            })
+            .collect();
+        // Sorted by key, and by original position within equal keys
+        assert!(records.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn test_sort_rejects_bad_arguments() {
+        let input = MemoryMappedFile::anonymous(100).expect("input");
+        let output = MemoryMappedFile::anonymous(100).expect("output");
+        let sorter = ExternalSorter::new(1024);
+        assert!(matches!(
+            sorter.sort::<u64>(&input, &output),
+            Err(MmapIoError::InvalidFormat(_))
+        ));
+        assert!(matches!(
+            sorter.sort_by_key(&input, &output, 0, |r| r.to_vec()),
+            Err(MmapIoError::InvalidFormat(_))
+        ));
+        assert!(matches!(
+            sorter.sort::<u32>(&input, &input),
+            Err(MmapIoError::InvalidMode(_))
+        ));
+        let short = MemoryMappedFile::anonymous(96).expect("short");
+        assert!(matches!(
+            sorter.sort::<u32>(&input, &short),
+            Err(MmapIoError::OutOfBounds { .. })
+        ));
+
+        let path = tmp_path("ro");
+        crate::create_mmap(&path, 64).expect("create");
+        let ro = MemoryMappedFile::open_ro(&path).expect("ro");
+        assert!(matches!(
+            sorter.sort::<u64>(&ro, &output),
+            Err(MmapIoError::InvalidMode(_))
+        ));
+        drop(ro);
+        fs::remove_file(&path).expect("cleanup");
+    }
+}
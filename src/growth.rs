@@ -0,0 +1,130 @@
+//! Growth policies for read-write mappings that extend themselves on writes past the end.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use parking_lot::Mutex;
+
+use crate::errors::{MmapIoError, Result};
+use crate::mmap::MemoryMappedFile;
+
+/// How a mapping grows when a write ends past the current file length.
+///
+/// Set with `MemoryMappedFileBuilder::growth_policy`. The new length is never smaller than
+/// the write needs and never larger than the builder's `max_size`.
+#[derive(Debug, Clone, Copy)]
+pub enum GrowthPolicy {
+    /// Grow in whole steps of this many bytes. `Fixed(0)` grows exactly to fit.
+    Fixed(u64),
+    /// Double the file length, or grow to fit the write if that is larger.
+    Double,
+    /// Compute the new length from `(current_len, required_len)`.
+    Custom(fn(u64, u64) -> u64),
+}
+
+impl GrowthPolicy {
+    /// The length to grow to from `current` so that at least `required` bytes fit.
+    #[must_use]
+    pub fn next_len(&self, current: u64, required: u64) -> u64 {
+        let len = match *self {
+            GrowthPolicy::Fixed(0) => required,
+            GrowthPolicy::Fixed(step) => {
+                let missing = required.saturating_sub(current);
+                current.saturating_add(missing.div_ceil(step).saturating_mul(step))
+            }
+            GrowthPolicy::Double => current.saturating_mul(2),
+            GrowthPolicy::Custom(f) => f(current, required),
+        };
+        len.max(required)
+    }
+}
+
+/// Growth state of a mapping built with a growth policy.
+pub(crate) struct Growth {
+    policy: GrowthPolicy,
+    max_size: u64,
+    // Highest offset written through the growing write paths (or the file length on open).
+    used: AtomicU64,
+    // Serializes growth so two writers never resize from the same stale length.
+    grow: Mutex<()>,
+    // Trim the file to `used` when the mapping is dropped.
+    shrink_on_drop: bool,
+}
+
+impl Growth {
+    pub(crate) fn new(
+        policy: GrowthPolicy,
+        max_size: u64,
+        used: u64,
+        shrink_on_drop: bool,
+    ) -> Self {
+        Self {
+            policy,
+            max_size,
+            used: AtomicU64::new(used),
+            grow: Mutex::new(()),
+            shrink_on_drop,
+        }
+    }
+
+    pub(crate) fn max_size(&self) -> u64 {
+        self.max_size
+    }
+
+    pub(crate) fn shrink_on_drop(&self) -> bool {
+        self.shrink_on_drop
+    }
+
+    pub(crate) fn used(&self) -> u64 {
+        self.used.load(Ordering::Acquire)
+    }
+
+    /// Record that bytes up to `end` are in use.
+    pub(crate) fn mark_used(&self, end: u64) {
+        self.used.fetch_max(end, Ordering::AcqRel);
+    }
+
+    /// Forget use past `len` after the file was shrunk to it.
+    pub(crate) fn truncate_used(&self, len: u64) {
+        self.used.fetch_min(len, Ordering::AcqRel);
+    }
+
+    /// Grow `mmap` so that `[offset, offset + len)` fits, following the policy.
+    pub(crate) fn ensure(&self, mmap: &MemoryMappedFile, offset: u64, len: u64) -> Result<()> {
+        let out_of_bounds = || MmapIoError::OutOfBounds {
+            offset,
+            len,
+            total: self.max_size,
+        };
+        let end = offset.checked_add(len).ok_or_else(out_of_bounds)?;
+        if end <= mmap.current_len()? {
+            return Ok(());
+        }
+        if end > self.max_size {
+            return Err(out_of_bounds());
+        }
+        let _grow = self.grow.lock();
+        let current = mmap.current_len()?;
+        if end > current {
+            let new_len = self.policy.next_len(current, end).min(self.max_size);
+            mmap.resize(new_len)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_len() {
+        assert_eq!(GrowthPolicy::Fixed(4096).next_len(4096, 4097), 8192);
+        assert_eq!(GrowthPolicy::Fixed(4096).next_len(4096, 20_000), 20_480);
+        assert_eq!(GrowthPolicy::Fixed(0).next_len(4096, 5000), 5000);
+        assert_eq!(GrowthPolicy::Double.next_len(4096, 5000), 8192);
+        assert_eq!(GrowthPolicy::Double.next_len(4096, 100_000), 100_000);
+        let quarter: fn(u64, u64) -> u64 = |current, _| current + current / 4;
+        assert_eq!(GrowthPolicy::Custom(quarter).next_len(4096, 4097), 5120);
+        assert_eq!(GrowthPolicy::Custom(quarter).next_len(4096, 9000), 9000);
+    }
+}
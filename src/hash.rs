@@ -0,0 +1,91 @@
+//! Small, dependency-free hash functions and checksums used by on-disk formats.
+//!
+//! These are part of the file formats that use them, so they must never change.
+
+// Only used by feature-gated containers.
+#![cfg_attr(
+    not(any(feature = "hashmap", feature = "archive", feature = "cache")),
+    allow(dead_code)
+)]
+
+const FNV64_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV64_PRIME: u64 = 0x0000_0100_0000_01b3;
+const FNV128_OFFSET: u128 = 0x6c62_272e_07bb_0142_62b8_2175_6295_c58d;
+const FNV128_PRIME: u128 = 0x0000_0000_0100_0000_0000_0000_0000_013b;
+const CRC32C_POLY: u32 = 0x82f6_3b78;
+const CRC32C_TABLE: [u32; 256] = crc32c_table();
+
+/// 64-bit FNV-1a.
+pub(crate) fn fnv1a64(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(FNV64_OFFSET, |h, &b| {
+        (h ^ u64::from(b)).wrapping_mul(FNV64_PRIME)
+    })
+}
+
+/// 128-bit FNV-1a. Not collision resistant against adversarial input.
+#[cfg_attr(not(feature = "blobstore"), allow(dead_code))]
+pub(crate) fn fnv1a128(bytes: &[u8]) -> u128 {
+    bytes.iter().fold(FNV128_OFFSET, |h, &b| {
+        (h ^ u128::from(b)).wrapping_mul(FNV128_PRIME)
+    })
+}
+
+/// CRC-32C (Castagnoli) of `bytes`, continuing from a previous checksum `crc` (0 to start).
+///
+/// `crc32c(crc32c(0, a), b)` equals the checksum of `a` followed by `b`.
+#[cfg_attr(not(feature = "appendlog"), allow(dead_code))]
+pub(crate) fn crc32c(crc: u32, bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!crc, |c, &b| {
+        CRC32C_TABLE[usize::from(c as u8 ^ b)] ^ (c >> 8)
+    })
+}
+
+const fn crc32c_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            c = if c & 1 == 1 {
+                (c >> 1) ^ CRC32C_POLY
+            } else {
+                c >> 1
+            };
+            bit += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fnv1a64_reference_values() {
+        assert_eq!(fnv1a64(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a64(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(fnv1a64(b"foobar"), 0x8594_4171_f739_67e8);
+    }
+
+    #[test]
+    fn test_fnv1a128_reference_values() {
+        assert_eq!(fnv1a128(b""), 0x6c62_272e_07bb_0142_62b8_2175_6295_c58d);
+        assert_eq!(fnv1a128(b"a"), 0xd228_cb69_6f1a_8caf_7891_2b70_4e4a_8964);
+        assert_eq!(
+            fnv1a128(b"foobar"),
+            0x343e_1662_793c_64bf_6f0d_3597_ba44_6f18
+        );
+    }
+
+    #[test]
+    fn test_crc32c_reference_values() {
+        assert_eq!(crc32c(0, b""), 0);
+        assert_eq!(crc32c(0, b"a"), 0xc1d0_4330);
+        assert_eq!(crc32c(0, b"123456789"), 0xe306_9283);
+        assert_eq!(crc32c(crc32c(0, b"1234"), b"56789"), 0xe306_9283);
+    }
+}
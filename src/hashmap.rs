@@ -0,0 +1,825 @@
+//! Persistent hash map from byte keys to byte values stored in a single memory-mapped file.
+//!
+//! Layout: a 64-byte header, an open-addressing bucket array (16 bytes per bucket: key hash
+//! and entry offset) and a heap of `key_len | value_len | key | value` entries. Lookups read
+//! the file in place, so opening is O(1) and nothing is deserialized. When the table gets too
+//! full, a larger bucket array is appended to the file (growing it with
+//! [`MemoryMappedFile::resize`]) and the header is switched over to it.
+
+use std::ops::Range;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::errors::{MmapIoError, Result};
+use crate::hash::fnv1a64;
+use crate::mmap::{MappedSlice, MemoryMappedFile, MmapMode};
+
+const MAGIC: [u8; 8] = *b"MMAPHMAP";
+const VERSION: u32 = 1;
+// Header layout: magic (8) | version (4) | reserved (4) | bucket offset (8) | bucket count (8)
+//                | len (8) | tombstones (8) | heap end (8) | reserved
+const HEADER_LEN: u64 = 64;
+const BUCKETS_OFFSET: u64 = 16;
+const COUNTS_OFFSET: u64 = 32;
+const HEAP_END_OFFSET: u64 = 48;
+const BUCKET_LEN: u64 = 16;
+const ENTRY_HEADER_LEN: u64 = 8;
+// Bucket hash values with special meaning; real hashes are remapped away from them.
+const EMPTY: u64 = 0;
+const TOMBSTONE: u64 = 1;
+const MIN_BUCKETS: u64 = 16;
+const INITIAL_HEAP: u64 = 4096;
+
+/// Persistent hash map with byte-slice keys and values.
+///
+/// A single writer opens the file read-write; any number of readers may open it read-only,
+/// in the same or another process. The writer appends an entry before publishing the bucket
+/// that points at it, storing each bucket word atomically (on 8-byte-aligned mappings, i.e.
+/// all but unaligned windows), so a concurrent reader sees a key either with an old or a new
+/// value, never a torn one. Readers keep the bucket array and heap extent they opened with:
+/// entries appended past that extent read as absent, and once the writer grows the bucket
+/// array readers stop seeing its updates. Reopen to observe everything.
+///
+/// Overwritten and removed entries leave garbage in the heap until the file is rebuilt.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mmap_io::hashmap::MmapHashMap;
+///
+/// let mut map = MmapHashMap::create("index.map")?;
+/// map.insert(b"alpha", b"1")?;
+/// map.insert(b"beta", b"2")?;
+/// map.flush()?;
+///
+/// let reader = MmapHashMap::open_ro("index.map")?;
+/// assert_eq!(reader.get(b"alpha")?.as_deref(), Some(&b"1"[..]));
+/// # Ok::<(), mmap_io::MmapIoError>(())
+/// ```
+pub struct MmapHashMap {
+    mmap: MemoryMappedFile,
+    header: Header,
+}
+
+impl std::fmt::Debug for MmapHashMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MmapHashMap")
+            .field("path", &self.mmap.path())
+            .field("len", &self.header.len)
+            .field("buckets", &self.header.bucket_count)
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Header {
+    bucket_offset: u64,
+    bucket_count: u64,
+    len: u64,
+    tombstones: u64,
+    heap_end: u64,
+}
+
+enum Probe {
+    Occupied { index: u64, entry: u64 },
+    Vacant { index: u64, tombstone: bool },
+}
+
+impl MmapHashMap {
+    /// Create a new, empty map file (truncating if it exists).
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from `MemoryMappedFile::create_rw`.
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::with_capacity(path, 0)
+    }
+
+    /// Create a new map file with buckets for at least `capacity` entries.
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from `MemoryMappedFile::create_rw`.
+    pub fn with_capacity<P: AsRef<Path>>(path: P, capacity: u64) -> Result<Self> {
+        let bucket_count = buckets_for(capacity)?;
+        let heap_start = HEADER_LEN + bucket_count * BUCKET_LEN;
+        let mmap = MemoryMappedFile::create_rw(path, heap_start + INITIAL_HEAP)?;
+        let header = Header {
+            bucket_offset: HEADER_LEN,
+            bucket_count,
+            len: 0,
+            tombstones: 0,
+            heap_end: heap_start,
+        };
+        let mut bytes = [0u8; HEADER_LEN as usize];
+        bytes[..8].copy_from_slice(&MAGIC);
+        bytes[8..12].copy_from_slice(&VERSION.to_le_bytes());
+        mmap.update_region(0, &bytes)?;
+        let map = Self { mmap, header };
+        map.write_header()?;
+        Ok(map)
+    }
+
+    /// Open an existing map file read-write.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidFormat` if the file is not a valid map.
+    /// Returns errors from `MemoryMappedFile::open_rw`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_mmap(MemoryMappedFile::open_rw(path)?)
+    }
+
+    /// Open an existing map file read-only.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidFormat` if the file is not a valid map.
+    /// Returns errors from `MemoryMappedFile::open_ro`.
+    pub fn open_ro<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_mmap(MemoryMappedFile::open_ro(path)?)
+    }
+
+    /// Open a map previously stored in `mmap`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidFormat` if the header is missing or inconsistent with the
+    /// mapping's length.
+    pub fn from_mmap(mmap: MemoryMappedFile) -> Result<Self> {
+        let total = mmap.current_len()?;
+        let data = mmap.as_slice_guarded(0, total.min(HEADER_LEN))?;
+        if data.len() < HEADER_LEN as usize || data[..8] != MAGIC || read_u32(&data, 8)? != VERSION
+        {
+            return Err(MmapIoError::InvalidFormat(
+                "missing or unsupported MmapHashMap header".into(),
+            ));
+        }
+        let header = Header {
+            bucket_offset: read_u64(&data, BUCKETS_OFFSET)?,
+            bucket_count: read_u64(&data, BUCKETS_OFFSET + 8)?,
+            len: read_u64(&data, COUNTS_OFFSET)?,
+            tombstones: read_u64(&data, COUNTS_OFFSET + 8)?,
+            heap_end: read_u64(&data, HEAP_END_OFFSET)?,
+        };
+        drop(data);
+        let buckets_end = header
+            .bucket_count
+            .checked_mul(BUCKET_LEN)
+            .and_then(|n| n.checked_add(header.bucket_offset));
+        if !header.bucket_count.is_power_of_two()
+            || header.bucket_offset < HEADER_LEN
+            || buckets_end.map_or(true, |end| end > total)
+            || header.heap_end > total
+            || header.len.saturating_add(header.tombstones) > header.bucket_count
+        {
+            return Err(MmapIoError::InvalidFormat(format!(
+                "MmapHashMap header inconsistent with file length {total}: {header:?}"
+            )));
+        }
+        Ok(Self { mmap, header })
+    }
+
+    /// Number of entries.
+    #[must_use]
+    pub fn len(&self) -> u64 {
+        self.header.len
+    }
+
+    /// Whether the map has no entries.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.header.len == 0
+    }
+
+    /// Underlying mapping.
+    #[must_use]
+    pub fn mmap(&self) -> &MemoryMappedFile {
+        &self.mmap
+    }
+
+    /// Zero-copy view of the value stored for `key`. For read-write mappings the view holds
+    /// the mapping's read lock until dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidFormat` if the table references data outside the file.
+    pub fn get(&self, key: &[u8]) -> Result<Option<MappedSlice<'_>>> {
+        let range = {
+            let data = self.data()?;
+            match self.probe(&data, key)? {
+                Probe::Occupied { entry, .. } => entry_at(&data, entry)?.1,
+                Probe::Vacant { .. } => return Ok(None),
+            }
+        };
+        self.mmap
+            .as_slice_guarded(range.start as u64, (range.end - range.start) as u64)
+            .map(Some)
+    }
+
+    /// Whether `key` is present.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidFormat` if the table references data outside the file.
+    pub fn contains_key(&self, key: &[u8]) -> Result<bool> {
+        let data = self.data()?;
+        Ok(matches!(self.probe(&data, key)?, Probe::Occupied { .. }))
+    }
+
+    /// Insert or replace the value for `key`. Returns `true` if the key was not present.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidMode` for read-only maps or keys/values over 4 GiB.
+    /// Returns errors from `MemoryMappedFile::resize` or `update_region`.
+    pub fn insert(&mut self, key: &[u8], value: &[u8]) -> Result<bool> {
+        self.check_writable()?;
+        let (Ok(key_len), Ok(value_len)) = (u32::try_from(key.len()), u32::try_from(value.len()))
+        else {
+            return Err(MmapIoError::InvalidMode(
+                "MmapHashMap keys and values must be shorter than 4 GiB",
+            ));
+        };
+        if (self.header.len + self.header.tombstones + 1) * 4 > self.header.bucket_count * 3 {
+            self.rehash()?;
+        }
+
+        let probe = {
+            let data = self.data()?;
+            self.probe(&data, key)?
+        };
+
+        // Append the entry and publish the new heap end before any bucket points at it.
+        let entry_offset = align8(self.header.heap_end);
+        let entry_len = ENTRY_HEADER_LEN + key.len() as u64 + value.len() as u64;
+        self.ensure_capacity(entry_offset + entry_len)?;
+        let mut entry = Vec::with_capacity(entry_len as usize);
+        entry.extend_from_slice(&key_len.to_le_bytes());
+        entry.extend_from_slice(&value_len.to_le_bytes());
+        entry.extend_from_slice(key);
+        entry.extend_from_slice(value);
+        self.mmap.update_region(entry_offset, &entry)?;
+        self.header.heap_end = entry_offset + entry_len;
+        self.mmap
+            .update_region(HEAP_END_OFFSET, &self.header.heap_end.to_le_bytes())?;
+
+        let (index, inserted) = match probe {
+            Probe::Occupied { index, .. } => (index, false),
+            Probe::Vacant { index, tombstone } => {
+                if tombstone {
+                    self.header.tombstones -= 1;
+                }
+                self.header.len += 1;
+                (index, true)
+            }
+        };
+        // Point the bucket at the entry before giving it the key's hash, so readers that
+        // match the hash always find a complete entry.
+        let bucket = self.header.bucket_offset + index * BUCKET_LEN;
+        store_word(&self.mmap, bucket + 8, entry_offset)?;
+        if inserted {
+            store_word(&self.mmap, bucket, bucket_hash(key))?;
+        }
+        self.write_counts()?;
+        Ok(inserted)
+    }
+
+    /// Remove `key`. Returns `true` if it was present.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidMode` for read-only maps.
+    /// Returns errors from `MemoryMappedFile::update_region`.
+    pub fn remove(&mut self, key: &[u8]) -> Result<bool> {
+        self.check_writable()?;
+        let probe = {
+            let data = self.data()?;
+            self.probe(&data, key)?
+        };
+        let Probe::Occupied { index, .. } = probe else {
+            return Ok(false);
+        };
+        store_word(
+            &self.mmap,
+            self.header.bucket_offset + index * BUCKET_LEN,
+            TOMBSTONE,
+        )?;
+        self.header.len -= 1;
+        self.header.tombstones += 1;
+        self.write_counts()?;
+        Ok(true)
+    }
+
+    /// Zero-copy snapshot of all entries. For read-write mappings the snapshot holds the
+    /// mapping's read lock until dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from `MemoryMappedFile::as_slice_guarded`.
+    pub fn entries(&self) -> Result<Entries<'_>> {
+        Ok(Entries {
+            data: self.data()?,
+            header: self.header,
+        })
+    }
+
+    /// Flush the map to disk.
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from `MemoryMappedFile::flush`.
+    pub fn flush(&self) -> Result<()> {
+        self.mmap.flush()
+    }
+
+    fn data(&self) -> Result<MappedSlice<'_>> {
+        self.mmap.as_slice_guarded(0, self.mmap.current_len()?)
+    }
+
+    fn probe(&self, data: &[u8], key: &[u8]) -> Result<Probe> {
+        let hash = bucket_hash(key);
+        let mask = self.header.bucket_count - 1;
+        let mut index = hash & mask;
+        let mut first_tombstone = None;
+        for _ in 0..self.header.bucket_count {
+            let bucket = self.header.bucket_offset + index * BUCKET_LEN;
+            match load_word(data, bucket)? {
+                EMPTY => {
+                    return Ok(match first_tombstone {
+                        Some(index) => Probe::Vacant {
+                            index,
+                            tombstone: true,
+                        },
+                        None => Probe::Vacant {
+                            index,
+                            tombstone: false,
+                        },
+                    })
+                }
+                TOMBSTONE => {
+                    first_tombstone.get_or_insert(index);
+                }
+                h if h == hash => {
+                    // Entries past our heap end were appended after this view was opened and
+                    // may lie beyond the end of its mapping.
+                    let entry = load_word(data, bucket + 8)?;
+                    if entry < self.header.heap_end && data[entry_at(data, entry)?.0] == *key {
+                        return Ok(Probe::Occupied { index, entry });
+                    }
+                }
+                _ => {}
+            }
+            index = (index + 1) & mask;
+        }
+        // The load factor keeps at least one empty bucket, but a corrupt file may not.
+        match first_tombstone {
+            Some(index) => Ok(Probe::Vacant {
+                index,
+                tombstone: true,
+            }),
+            None => Err(MmapIoError::InvalidFormat(
+                "MmapHashMap bucket array has no free slot".into(),
+            )),
+        }
+    }
+
+    /// Append a new bucket array after the heap, rehash live buckets into it using the
+    /// stored hashes, then switch the header over to it.
+    fn rehash(&mut self) -> Result<()> {
+        // Keep the size when removals left mostly tombstones; the rehashed table is then at
+        // most half full, so rehashes stay rare.
+        let new_count = if (self.header.len + 1) * 2 > self.header.bucket_count {
+            self.header.bucket_count * 2
+        } else {
+            self.header.bucket_count
+        };
+        let mask = new_count - 1;
+        let mut buckets = vec![0u8; (new_count * BUCKET_LEN) as usize];
+        {
+            let data = self.data()?;
+            for index in 0..self.header.bucket_count {
+                let bucket = self.header.bucket_offset + index * BUCKET_LEN;
+                let hash = read_u64(&data, bucket)?;
+                if hash == EMPTY || hash == TOMBSTONE {
+                    continue;
+                }
+                let mut slot = hash & mask;
+                while read_u64(&buckets, slot * BUCKET_LEN)? != EMPTY {
+                    slot = (slot + 1) & mask;
+                }
+                let at = (slot * BUCKET_LEN) as usize;
+                buckets[at..at + BUCKET_LEN as usize]
+                    .copy_from_slice(&data[bucket as usize..(bucket + BUCKET_LEN) as usize]);
+            }
+        }
+
+        let new_offset = align8(self.header.heap_end);
+        let heap_end = new_offset + buckets.len() as u64;
+        self.ensure_capacity(heap_end + INITIAL_HEAP)?;
+        self.mmap.update_region(new_offset, &buckets)?;
+        self.header = Header {
+            bucket_offset: new_offset,
+            bucket_count: new_count,
+            len: self.header.len,
+            tombstones: 0,
+            heap_end,
+        };
+        self.write_header()
+    }
+
+    fn ensure_capacity(&mut self, required: u64) -> Result<()> {
+        let total = self.mmap.current_len()?;
+        if required > total {
+            self.mmap.resize(required.max(total.saturating_mul(2)))?;
+        }
+        Ok(())
+    }
+
+    fn check_writable(&self) -> Result<()> {
+        if self.mmap.mode() == MmapMode::ReadWrite {
+            Ok(())
+        } else {
+            Err(MmapIoError::InvalidMode(
+                "MmapHashMap writes require ReadWrite mode",
+            ))
+        }
+    }
+
+    fn write_counts(&self) -> Result<()> {
+        let mut counts = [0u8; 16];
+        counts[..8].copy_from_slice(&self.header.len.to_le_bytes());
+        counts[8..].copy_from_slice(&self.header.tombstones.to_le_bytes());
+        self.mmap.update_region(COUNTS_OFFSET, &counts)
+    }
+
+    fn write_header(&self) -> Result<()> {
+        let h = self.header;
+        let mut fields = [0u8; 40];
+        for (i, v) in [
+            h.bucket_offset,
+            h.bucket_count,
+            h.len,
+            h.tombstones,
+            h.heap_end,
+        ]
+        .iter()
+        .enumerate()
+        {
+            fields[i * 8..i * 8 + 8].copy_from_slice(&v.to_le_bytes());
+        }
+        self.mmap.update_region(BUCKETS_OFFSET, &fields)
+    }
+}
+
+/// Zero-copy snapshot of the entries of an [`MmapHashMap`], see [`MmapHashMap::entries`].
+pub struct Entries<'a> {
+    data: MappedSlice<'a>,
+    header: Header,
+}
+
+impl Entries<'_> {
+    /// Iterate over `(key, value)` pairs in bucket order.
+    pub fn iter(&self) -> EntriesIter<'_> {
+        EntriesIter {
+            data: &self.data,
+            header: self.header,
+            index: 0,
+        }
+    }
+}
+
+impl<'e> IntoIterator for &'e Entries<'_> {
+    type Item = Result<(&'e [u8], &'e [u8])>;
+    type IntoIter = EntriesIter<'e>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over the entries of an [`MmapHashMap`]. Yields an error if the table
+/// references data outside the file.
+pub struct EntriesIter<'e> {
+    data: &'e [u8],
+    header: Header,
+    index: u64,
+}
+
+impl<'e> Iterator for EntriesIter<'e> {
+    type Item = Result<(&'e [u8], &'e [u8])>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.index < self.header.bucket_count {
+            let bucket = self.header.bucket_offset + self.index * BUCKET_LEN;
+            self.index += 1;
+            let entry = match load_word(self.data, bucket) {
+                Ok(EMPTY | TOMBSTONE) => continue,
+                Ok(_) => match load_word(self.data, bucket + 8) {
+                    Ok(off) if off >= self.header.heap_end => continue,
+                    Ok(off) => entry_at(self.data, off),
+                    Err(e) => Err(e),
+                },
+                Err(e) => Err(e),
+            };
+            return Some(entry.map(|(k, v)| (&self.data[k], &self.data[v])));
+        }
+        None
+    }
+}
+
+fn buckets_for(capacity: u64) -> Result<u64> {
+    capacity
+        .checked_mul(4)
+        .map(|n| (n / 3 + 1).max(MIN_BUCKETS))
+        .and_then(u64::checked_next_power_of_two)
+        .ok_or_else(|| MmapIoError::ResizeFailed("MmapHashMap capacity overflow".into()))
+}
+
+fn bucket_hash(key: &[u8]) -> u64 {
+    match fnv1a64(key) {
+        h @ (EMPTY | TOMBSTONE) => h + 2,
+        h => h,
+    }
+}
+
+fn align8(offset: u64) -> u64 {
+    (offset + 7) & !7
+}
+
+/// Byte ranges of the key and value of the entry at `offset`.
+fn entry_at(data: &[u8], offset: u64) -> Result<(Range<usize>, Range<usize>)> {
+    let key_len = u64::from(read_u32(data, offset)?);
+    let value_len = u64::from(read_u32(data, offset + 4)?);
+    let key_start = offset + ENTRY_HEADER_LEN;
+    let value_start = key_start + key_len;
+    let value_end = value_start + value_len;
+    if value_end > data.len() as u64 {
+        return Err(truncated(offset));
+    }
+    Ok((
+        key_start as usize..value_start as usize,
+        value_start as usize..value_end as usize,
+    ))
+}
+
+fn read_u64(data: &[u8], offset: u64) -> Result<u64> {
+    let bytes = usize::try_from(offset)
+        .ok()
+        .and_then(|o| data.get(o..o.checked_add(8)?))
+        .ok_or_else(|| truncated(offset))?;
+    Ok(u64::from_le_bytes(bytes.try_into().expect("8 bytes")))
+}
+
+/// Read a bucket word with acquire ordering, pairing with [`store_word`].
+fn load_word(data: &[u8], offset: u64) -> Result<u64> {
+    let bytes = usize::try_from(offset)
+        .ok()
+        .and_then(|o| data.get(o..o.checked_add(8)?))
+        .ok_or_else(|| truncated(offset))?;
+    if bytes.as_ptr() as usize % 8 != 0 {
+        return Ok(u64::from_le_bytes(bytes.try_into().expect("8 bytes")));
+    }
+    // SAFETY: the pointer is valid for 8 bytes and 8-byte aligned; the writer only ever
+    // changes these bytes through `store_word`, and atomic loads do not write.
+    let word = unsafe { AtomicU64::from_ptr(bytes.as_ptr().cast_mut().cast()) };
+    Ok(u64::from_le(word.load(Ordering::Acquire)))
+}
+
+/// Store a bucket word so that concurrent readers see either the old or the new value.
+fn store_word(mmap: &MemoryMappedFile, offset: u64, value: u64) -> Result<()> {
+    let mut slice = mmap.as_slice_mut(offset, 8)?;
+    let bytes = slice.as_mut();
+    if bytes.as_ptr() as usize % 8 != 0 {
+        bytes.copy_from_slice(&value.to_le_bytes());
+        return Ok(());
+    }
+    // SAFETY: the pointer is valid for 8 bytes, 8-byte aligned and borrowed exclusively
+    // within this process for the duration of the store.
+    let word = unsafe { AtomicU64::from_ptr(bytes.as_mut_ptr().cast()) };
+    word.store(value.to_le(), Ordering::Release);
+    Ok(())
+}
+
+fn read_u32(data: &[u8], offset: u64) -> Result<u32> {
+    let bytes = usize::try_from(offset)
+        .ok()
+        .and_then(|o| data.get(o..o.checked_add(4)?))
+        .ok_or_else(|| truncated(offset))?;
+    Ok(u32::from_le_bytes(bytes.try_into().expect("4 bytes")))
+}
+
+fn truncated(offset: u64) -> MmapIoError {
+    MmapIoError::InvalidFormat(format!(
+        "MmapHashMap references data beyond the end of the file at offset {offset}"
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn tmp_path(name: &str) -> PathBuf {
+        let mut p = std::env::temp_dir();
+        p.push(format!(
+            "mmap_io_hashmap_test_{}_{}",
+            name,
+            std::process::id()
+        ));
+        p
+    }
+
+    #[test]
+    fn test_insert_get_overwrite_remove() {
+        let path = tmp_path("basic");
+        let _ = fs::remove_file(&path);
+
+        let mut map = MmapHashMap::create(&path).expect("create");
+        assert!(map.insert(b"alpha", b"one").expect("insert"));
+        assert!(map.insert(b"beta", b"").expect("insert"));
+        assert!(map.insert(b"", b"empty key").expect("insert"));
+        assert!(!map.insert(b"alpha", b"uno").expect("overwrite"));
+        assert_eq!(map.len(), 3);
+
+        assert_eq!(
+            map.get(b"alpha").expect("get").as_deref(),
+            Some(&b"uno"[..])
+        );
+        assert_eq!(map.get(b"beta").expect("get").as_deref(), Some(&b""[..]));
+        assert_eq!(
+            map.get(b"").expect("get").as_deref(),
+            Some(&b"empty key"[..])
+        );
+        assert!(map.get(b"gamma").expect("get").is_none());
+
+        assert!(map.remove(b"alpha").expect("remove"));
+        assert!(!map.remove(b"alpha").expect("remove again"));
+        assert!(!map.contains_key(b"alpha").expect("contains"));
+        assert_eq!(map.len(), 2);
+
+        // Tombstoned slot is reused
+        assert!(map.insert(b"alpha", b"again").expect("reinsert"));
+        assert_eq!(map.len(), 3);
+
+        fs::remove_file(&path).expect("cleanup");
+    }
+
+    #[test]
+    fn test_growth_reopen_and_iteration() {
+        let path = tmp_path("growth");
+        let _ = fs::remove_file(&path);
+
+        let mut expected = HashMap::new();
+        let mut map = MmapHashMap::create(&path).expect("create");
+        for i in 0..2000u32 {
+            let key = format!("key-{i}").into_bytes();
+            let value = i.to_le_bytes().repeat((i % 7) as usize);
+            map.insert(&key, &value).expect("insert");
+            expected.insert(key, value);
+        }
+        for i in (0..2000u32).step_by(3) {
+            let key = format!("key-{i}").into_bytes();
+            assert!(map.remove(&key).expect("remove"));
+            expected.remove(&key);
+        }
+        assert!(map.header.bucket_count > MIN_BUCKETS);
+        map.flush().expect("flush");
+        drop(map);
+
+        let map = MmapHashMap::open(&path).expect("reopen");
+        assert_eq!(map.len(), expected.len() as u64);
+        for (key, value) in &expected {
+            assert_eq!(
+                map.get(key).expect("get").as_deref(),
+                Some(value.as_slice())
+            );
+        }
+        let entries = map.entries().expect("entries");
+        let mut seen = 0;
+        for entry in &entries {
+            let (key, value) = entry.expect("entry");
+            assert_eq!(expected.get(key).map(Vec::as_slice), Some(value));
+            seen += 1;
+        }
+        assert_eq!(seen, expected.len());
+
+        fs::remove_file(&path).expect("cleanup");
+    }
+
+    #[test]
+    fn test_read_only_reader() {
+        let path = tmp_path("reader");
+        let _ = fs::remove_file(&path);
+
+        let mut writer = MmapHashMap::with_capacity(&path, 100).expect("create");
+        writer.insert(b"shared", b"value").expect("insert");
+        writer.flush().expect("flush");
+
+        let mut reader = MmapHashMap::open_ro(&path).expect("open_ro");
+        assert_eq!(
+            reader.get(b"shared").expect("get").as_deref(),
+            Some(&b"value"[..])
+        );
+        assert!(matches!(
+            reader.insert(b"nope", b"x"),
+            Err(MmapIoError::InvalidMode(_))
+        ));
+
+        fs::remove_file(&path).expect("cleanup");
+    }
+
+    #[test]
+    fn test_reader_ignores_entries_past_its_mapping() {
+        let path = tmp_path("past_mapping");
+        let _ = fs::remove_file(&path);
+
+        let mut writer = MmapHashMap::with_capacity(&path, 100).expect("create");
+        writer.insert(b"old", b"1").expect("insert");
+        let reader = MmapHashMap::open_ro(&path).expect("open_ro");
+        let reader_len = reader.mmap().len();
+
+        // The new entry lands beyond the reader's mapping, in a bucket it can see
+        writer.insert(b"new", &vec![7u8; 8192]).expect("insert");
+        assert!(writer.mmap().current_len().expect("len") > reader_len);
+        assert!(reader.get(b"new").expect("get").is_none());
+        assert!(!reader.contains_key(b"new").expect("contains"));
+        let entries = reader.entries().expect("entries");
+        let keys: Vec<_> = entries.iter().map(|e| e.expect("entry").0).collect();
+        assert_eq!(keys, vec![&b"old"[..]]);
+
+        fs::remove_file(&path).expect("cleanup");
+    }
+
+    #[test]
+    fn test_concurrent_writer_and_reader() {
+        let path = tmp_path("concurrent");
+        let _ = fs::remove_file(&path);
+
+        let mut writer = MmapHashMap::with_capacity(&path, 64).expect("create");
+        for i in 0..32u32 {
+            writer
+                .insert(&i.to_le_bytes(), &0u32.to_le_bytes())
+                .expect("insert");
+        }
+        let reader = MmapHashMap::open_ro(&path).expect("open_ro");
+
+        let done = std::sync::atomic::AtomicBool::new(false);
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                // Overwrite in place, remove and re-add, and finally outgrow the table
+                for round in 1..200u32 {
+                    for i in 0..32u32 {
+                        writer
+                            .insert(&i.to_le_bytes(), &round.to_le_bytes())
+                            .expect("overwrite");
+                    }
+                    writer.remove(&(round % 32).to_le_bytes()).expect("remove");
+                }
+                for i in 32..200u32 {
+                    writer.insert(&i.to_le_bytes(), b"late").expect("insert");
+                }
+                done.store(true, Ordering::Release);
+            });
+            while !done.load(Ordering::Acquire) {
+                for i in 0..64u32 {
+                    let value = reader.get(&i.to_le_bytes()).expect("reader get");
+                    if let Some(value) = value {
+                        assert!(i < 32, "key {i} is past the reader's view");
+                        assert_eq!(value.len(), 4);
+                    }
+                }
+            }
+        });
+        assert_eq!(writer.len(), 199);
+
+        fs::remove_file(&path).expect("cleanup");
+    }
+
+    #[test]
+    fn test_open_rejects_invalid_files() {
+        let path = tmp_path("invalid");
+        let _ = fs::remove_file(&path);
+
+        crate::create_mmap(&path, 256).expect("create");
+        assert!(matches!(
+            MmapHashMap::open(&path),
+            Err(MmapIoError::InvalidFormat(_))
+        ));
+
+        let map = MmapHashMap::create(&path).expect("create map");
+        // Point the bucket array past the end of the file
+        map.mmap()
+            .update_region(BUCKETS_OFFSET, &u64::MAX.to_le_bytes())
+            .expect("corrupt");
+        drop(map);
+        assert!(matches!(
+            MmapHashMap::open(&path),
+            Err(MmapIoError::InvalidFormat(_))
+        ));
+
+        fs::remove_file(&path).expect("cleanup");
+    }
+}
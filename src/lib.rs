@@ -34,20 +34,47 @@
 //! - [`mmap`]: Core `MemoryMappedFile` implementation
 //! - [`segment`]: Segmented views for working with file regions
 //! - [`manager`]: High-level convenience functions
+//! - [`pod`]: Plain-old-data marker for values stored directly in mappings
+//! - [`growth`]: Growth policies for read-write mappings written past the end
 //!
 //! ## Feature Flags
 //!
 //! - `async`: Enables Tokio-based async file operations
+//! - `shm`: Named shared memory (`shm_open`) and `memfd`-backed mappings (Unix)
+//! - `vec`: Growable persistent vector (`MmapVec<T: Pod>`)
+//! - `hashmap`: Persistent byte-keyed hash map (`MmapHashMap`)
+//! - `allocator`: Offset-based slab allocator inside a mapped file (`MmapAllocator`)
+//! - `relptr`: Position-independent pointers for mapped structures (`OffsetPtr`, `RelPtr`)
+//! - `pager`: Fixed-size page manager with a persisted free-page list (`Pager`)
+//! - `bitmap`: Persistent atomic bitmap with word-level scans (`MmapBitmap`, implies `atomic`)
+//! - `sstable`: Sorted string table writer and zero-copy reader (`SsTableBuilder`, `SsTable`)
+//! - `archive`: Indexed multi-blob pack files with O(1) lookup by name (`ArchiveWriter`, `ArchiveReader`)
+//! - `blobstore`: Content-addressed, deduplicating blob store (`BlobStore`, implies `hashmap`)
+//! - `cache`: Persistent fixed-capacity LRU/TTL cache file (`MmapCache`, implies `atomic`)
+//! - `extsort`: External merge sort for fixed-size records (`ExternalSorter`, implies `advise` and `iterator`)
+//! - `appendlog`: Append-only checksummed record log with torn-tail recovery (`AppendLog`)
+//! - `appender`: Lock-free concurrent append reservation with a commit watermark (`ConcurrentAppender`)
+//! - `windowed`: Sliding-window access to files larger than the address space (`WindowedFile`)
 
 #![cfg_attr(not(test), deny(clippy::unwrap_used))]
 #![deny(missing_docs)]
 #![doc(html_root_url = "https://docs.rs/mmap-io")]
 
+mod copy;
 pub mod errors;
+pub mod growth;
+mod hash;
 pub mod manager;
 /// Memory-mapped file support.
 pub mod mmap;
+pub mod pod;
+mod prealloc;
+#[cfg(unix)]
+mod reserve;
 pub mod segment;
+#[cfg(unix)]
+pub mod sigbus;
+mod sparse;
 pub mod utils;
 
 /// Provides functions for flushing memory-mapped file changes to disk.
@@ -68,11 +95,59 @@ pub mod atomic;
 #[cfg(feature = "watch")]
 pub mod watch;
 
+#[cfg(all(unix, feature = "shm"))]
+pub mod shm;
+
+#[cfg(feature = "vec")]
+pub mod vec;
+
+#[cfg(feature = "hashmap")]
+pub mod hashmap;
+
+#[cfg(feature = "allocator")]
+pub mod allocator;
+
+#[cfg(feature = "relptr")]
+pub mod relptr;
+
+#[cfg(feature = "pager")]
+pub mod pager;
+
+#[cfg(feature = "bitmap")]
+pub mod bitmap;
+
+#[cfg(feature = "sstable")]
+pub mod sstable;
+
+#[cfg(feature = "archive")]
+pub mod archive;
+
+#[cfg(feature = "blobstore")]
+pub mod blobstore;
+
+#[cfg(feature = "cache")]
+pub mod cache;
+
+#[cfg(feature = "extsort")]
+pub mod extsort;
+
+#[cfg(feature = "appendlog")]
+pub mod appendlog;
+
+#[cfg(feature = "appender")]
+pub mod appender;
+
+#[cfg(feature = "windowed")]
+pub mod windowed;
+
 pub use errors::MmapIoError;
+pub use growth::GrowthPolicy;
 pub use manager::{
-    copy_mmap, create_mmap, delete_mmap, flush, load_mmap, update_region, write_mmap,
+    copy_mmap, copy_range, copy_range_with_progress, create_mmap, delete_mmap, flush, load_mmap,
+    update_region, write_mmap,
 };
-pub use mmap::{MemoryMappedFile, MmapMode, TouchHint};
+pub use mmap::{LengthChange, MemoryMappedFile, MmapMode, TouchHint};
+pub use pod::Pod;
 
 #[cfg(feature = "advise")]
 pub use advise::MmapAdvice;
@@ -81,4 +156,49 @@ pub use advise::MmapAdvice;
 pub use iterator::{ChunkIterator, PageIterator};
 
 #[cfg(feature = "watch")]
-pub use watch::{ChangeEvent, ChangeKind, WatchHandle};
+pub use watch::{ChangeEvent, ChangeKind, Follower, WatchHandle};
+
+#[cfg(all(unix, feature = "shm"))]
+pub use shm::{recv_mapping, send_mapping, MemfdSeals};
+
+#[cfg(feature = "vec")]
+pub use vec::MmapVec;
+
+#[cfg(feature = "hashmap")]
+pub use hashmap::MmapHashMap;
+
+#[cfg(feature = "allocator")]
+pub use allocator::MmapAllocator;
+
+#[cfg(feature = "relptr")]
+pub use relptr::{OffsetPtr, RelPtr};
+
+#[cfg(feature = "pager")]
+pub use pager::{PageId, Pager};
+
+#[cfg(feature = "bitmap")]
+pub use bitmap::MmapBitmap;
+
+#[cfg(feature = "sstable")]
+pub use sstable::{SsTable, SsTableBuilder};
+
+#[cfg(feature = "archive")]
+pub use archive::{ArchiveReader, ArchiveWriter};
+
+#[cfg(feature = "blobstore")]
+pub use blobstore::{BlobHash, BlobStore};
+
+#[cfg(feature = "cache")]
+pub use cache::MmapCache;
+
+#[cfg(feature = "extsort")]
+pub use extsort::{ExternalSorter, SortStats};
+
+#[cfg(feature = "appendlog")]
+pub use appendlog::{AppendLog, LogGrowth};
+
+#[cfg(feature = "appender")]
+pub use appender::{AppendSlot, ConcurrentAppender};
+
+#[cfg(feature = "windowed")]
+pub use windowed::WindowedFile;
@@ -67,13 +67,53 @@ pub fn flush(mmap: &MemoryMappedFile) -> Result<()> {
 
 /// Copy a mapped file to a new destination using the filesystem.
 /// This does not copy the mapping identity, only the underlying file contents.
+/// Holes in a sparse source are skipped and stay holes in the copy.
 ///
 /// # Errors
 ///
 /// Returns `MmapIoError::Io` if the copy operation fails.
 pub fn copy_mmap<P: AsRef<Path>>(src: P, dst: P) -> Result<()> {
-    fs::copy(src, dst)?;
-    Ok(())
+    crate::sparse::copy_sparse(src.as_ref(), dst.as_ref())
+}
+
+/// Copy `len` bytes from `src` at `src_offset` to `dst` at `dst_offset`. `dst` must be
+/// ReadWrite; with a growth policy it grows to fit. Overlapping ranges are handled like
+/// `copy_within`.
+///
+/// The bytes go straight from one mapping into the other, except when `src` and `dst` are
+/// two separate mappings of the same file and the ranges overlap. Those copies go through a
+/// buffer. Only Unix detects that case; elsewhere it is not supported.
+///
+/// # Errors
+///
+/// Returns `MmapIoError::InvalidMode` if `dst` is not in `ReadWrite` mode.
+/// Returns `MmapIoError::OutOfBounds` if either range exceeds its mapping.
+pub fn copy_range(
+    src: &MemoryMappedFile,
+    src_offset: u64,
+    dst: &MemoryMappedFile,
+    dst_offset: u64,
+    len: u64,
+) -> Result<()> {
+    copy_range_with_progress(src, src_offset, dst, dst_offset, len, |_, _| {})
+}
+
+/// [`copy_range`] that calls `progress(copied, len)` after every chunk of a few MiB. The
+/// mapping locks are released between chunks, so other threads are not blocked for the
+/// whole of a large copy.
+///
+/// # Errors
+///
+/// Returns the same errors as [`copy_range`].
+pub fn copy_range_with_progress<F: FnMut(u64, u64)>(
+    src: &MemoryMappedFile,
+    src_offset: u64,
+    dst: &MemoryMappedFile,
+    dst_offset: u64,
+    len: u64,
+    mut progress: F,
+) -> Result<()> {
+    crate::copy::copy_range(src, src_offset, dst, dst_offset, len, &mut progress)
 }
 
 /// Delete the file backing a mapping path. The mapping itself should be dropped by users before invoking this.
@@ -94,7 +134,7 @@ pub mod r#async {
 
     use tokio::fs as tfs;
 
-    use crate::errors::Result;
+    use crate::errors::{MmapIoError, Result};
     use crate::mmap::MemoryMappedFile;
 
     /// Create a new file with the specified size asynchronously, then map it RW.
@@ -117,14 +157,16 @@ pub mod r#async {
         MemoryMappedFile::open_rw(path_ref)
     }
 
-    /// Copy a file asynchronously.
+    /// Copy a file asynchronously, skipping holes like [`copy_mmap`](super::copy_mmap).
     ///
     /// # Errors
     ///
     /// Returns `MmapIoError::Io` if the async copy operation fails.
     pub async fn copy_mmap_async<P: AsRef<Path>>(src: P, dst: P) -> Result<()> {
-        tfs::copy(src, dst).await?;
-        Ok(())
+        let (src, dst) = (src.as_ref().to_path_buf(), dst.as_ref().to_path_buf());
+        tokio::task::spawn_blocking(move || super::copy_mmap(src, dst))
+            .await
+            .map_err(|e| MmapIoError::Io(std::io::Error::other(format!("join error: {e}"))))?
     }
 
     /// Delete a file asynchronously.
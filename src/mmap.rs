@@ -16,15 +16,14 @@ pub enum TouchHint {
 use std::{
     fs::{File, OpenOptions},
     path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
     sync::Arc,
 };
 
-use memmap2::{Mmap, MmapMut};
+use memmap2::{Mmap, MmapMut, MmapOptions};
 
 use crate::flush::FlushPolicy;
-
-#[cfg(feature = "cow")]
-use memmap2::MmapOptions;
+use crate::growth::{Growth, GrowthPolicy};
 
 use parking_lot::RwLock;
 
@@ -34,6 +33,7 @@ use crate::utils::{ensure_in_bounds, slice_range};
 // Error message constants
 const ERR_ZERO_SIZE: &str = "Size must be greater than zero";
 const ERR_ZERO_LENGTH_FILE: &str = "Cannot map zero-length file";
+const ERR_ZERO_LENGTH_WINDOW: &str = "Cannot map zero-length window";
 
 // Maximum safe mmap size: 128TB (reasonable limit for most systems)
 // This prevents accidental exhaustion of address space or disk
@@ -56,10 +56,61 @@ pub enum MmapMode {
     CopyOnWrite,
 }
 
+/// How the file length changed since the mapping last looked, as reported by
+/// [`MemoryMappedFile::refresh`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LengthChange {
+    /// The file length is the one already mapped.
+    Unchanged,
+    /// The file grew and the mapping now covers the new bytes.
+    Grew {
+        /// Previous length.
+        from: u64,
+        /// New length.
+        to: u64,
+    },
+    /// The file shrank and the mapping now ends at the new length.
+    Shrank {
+        /// Previous length.
+        from: u64,
+        /// New length.
+        to: u64,
+    },
+}
+
+/// Kind of object backing a mapping.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Backing {
+    /// Regular file on a filesystem.
+    File,
+    /// Named POSIX shared memory object (`shm_open`).
+    #[cfg(all(unix, feature = "shm"))]
+    Shm(String),
+    /// Anonymous in-memory file (`memfd_create`) with the seals requested at creation.
+    #[cfg(all(any(target_os = "linux", target_os = "android"), feature = "shm"))]
+    Memfd {
+        name: String,
+        seals: crate::shm::MemfdSeals,
+    },
+    /// Private anonymous memory (`MAP_ANONYMOUS`); no descriptor at all.
+    Anonymous,
+    /// Unnamed or already-unlinked temporary file that disappears when the mapping is dropped.
+    Temp,
+}
+
+impl Backing {
+    /// Whether flushing has any effect for this backing (only regular files are persisted).
+    pub(crate) fn is_persistent(&self) -> bool {
+        matches!(self, Backing::File)
+    }
+}
+
 #[doc(hidden)]
 pub struct Inner {
     pub(crate) path: PathBuf,
-    pub(crate) file: File,
+    // Backing file descriptor; `None` for anonymous mappings.
+    pub(crate) file: Option<File>,
+    pub(crate) backing: Backing,
     pub(crate) mode: MmapMode,
     // Cached length to avoid repeated metadata queries
     pub(crate) cached_len: RwLock<u64>,
@@ -71,16 +122,115 @@ pub struct Inner {
     // Huge pages preference (builder-set), effective on supported platforms
     #[cfg(feature = "hugepages")]
     pub(crate) huge_pages: bool,
+    // Reserved address space for in-place growth (builder-set, RW file mappings only)
+    #[cfg(unix)]
+    pub(crate) reservation: Option<crate::reserve::Reservation>,
+    // Growth policy and used-length watermark (builder-set, RW file mappings only)
+    pub(crate) growth: Option<Growth>,
+    // File offset of the mapped window; `None` when the whole file is mapped
+    pub(crate) window_offset: Option<u64>,
+    // Set when the SIGBUS handler patched a private zero page into the mapping. Writes to a
+    // read-write mapping would then land in that page and never reach the file, so they fail
+    // with `Truncated` until `refresh` remaps it.
+    pub(crate) truncated: AtomicBool,
+}
+
+impl Inner {
+    /// Backing file descriptor, if the mapping has one.
+    #[cfg_attr(not(feature = "shm"), allow(dead_code))]
+    pub(crate) fn file(&self) -> Result<&File> {
+        self.file.as_ref().ok_or(MmapIoError::InvalidMode(
+            "anonymous mapping has no backing file",
+        ))
+    }
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        // Builder `shrink_on_drop`: trim spare growth capacity once the last handle is gone.
+        // The mapping is unmapped right after this, so only the file length needs to change.
+        let (Some(growth), Some(file)) = (&self.growth, &self.file) else {
+            return;
+        };
+        if !growth.shrink_on_drop() || self.window_offset.is_some() {
+            return;
+        }
+        let len = *self.cached_len.read();
+        let used = growth.used().min(len).max(1);
+        if used < len {
+            let _ = file.set_len(used);
+        }
+    }
 }
 
 #[doc(hidden)]
 pub enum MapVariant {
     Ro(Mmap),
-    Rw(RwLock<MmapMut>),
+    Rw(RwLock<RwMap>),
     /// Private, per-process copy-on-write mapping. Underlying file is not modified by writes.
     Cow(Mmap),
 }
 
+/// The mapping of a ReadWrite `MemoryMappedFile`.
+///
+/// Derefs to the whole mapped range. For a reserved mapping that `mremap` extended in place
+/// this is longer than the `MmapMut`, which keeps the length it was created with.
+#[doc(hidden)]
+pub struct RwMap {
+    mmap: MmapMut,
+    // Length of the mapped range, kept up to date by the reservation that owns the rest
+    extent: Option<Arc<AtomicUsize>>,
+}
+
+impl RwMap {
+    pub(crate) fn with_extent(mmap: MmapMut, extent: Arc<AtomicUsize>) -> Self {
+        Self {
+            mmap,
+            extent: Some(extent),
+        }
+    }
+
+    fn flush_range(&self, offset: usize, len: usize) -> std::io::Result<()> {
+        self.mmap.flush_range(offset, len)
+    }
+}
+
+impl From<MmapMut> for RwMap {
+    fn from(mmap: MmapMut) -> Self {
+        Self { mmap, extent: None }
+    }
+}
+
+impl std::ops::Deref for RwMap {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match &self.extent {
+            // SAFETY: the reservation maps `extent` bytes from the base and only unmaps them
+            // when it is dropped, after the map.
+            Some(extent) => unsafe {
+                std::slice::from_raw_parts(self.mmap.as_ptr(), extent.load(Ordering::Acquire))
+            },
+            None => &self.mmap,
+        }
+    }
+}
+
+impl std::ops::DerefMut for RwMap {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        match &self.extent {
+            // SAFETY: as in `deref`; `&mut self` makes the view exclusive.
+            Some(extent) => unsafe {
+                std::slice::from_raw_parts_mut(
+                    self.mmap.as_mut_ptr(),
+                    extent.load(Ordering::Acquire),
+                )
+            },
+            None => &mut self.mmap,
+        }
+    }
+}
+
 /// Memory-mapped file with safe, zero-copy region access.
 ///
 /// This is the core type for memory-mapped file operations. It provides:
@@ -143,15 +293,7 @@ impl MemoryMappedFile {
     /// //     .create().unwrap();
     /// ```
     pub fn builder<P: AsRef<Path>>(path: P) -> MemoryMappedFileBuilder {
-        MemoryMappedFileBuilder {
-            path: path.as_ref().to_path_buf(),
-            size: None,
-            mode: None,
-            flush_policy: FlushPolicy::default(),
-            touch_hint: TouchHint::default(),
-            #[cfg(feature = "hugepages")]
-            huge_pages: false,
-        }
+        MemoryMappedFileBuilder::with_backing(path.as_ref().to_path_buf(), Backing::File)
     }
 
     /// Create a new file (truncating if exists) and memory-map it in read-write mode with the given size.
@@ -189,14 +331,20 @@ impl MemoryMappedFile {
         let mmap = unsafe { MmapMut::map_mut(&file)? };
         let inner = Inner {
             path: path_ref.to_path_buf(),
-            file,
+            file: Some(file),
+            backing: Backing::File,
             mode: MmapMode::ReadWrite,
             cached_len: RwLock::new(size),
-            map: MapVariant::Rw(RwLock::new(mmap)),
+            map: MapVariant::Rw(RwLock::new(mmap.into())),
             flush_policy: FlushPolicy::default(),
             written_since_last_flush: RwLock::new(0),
             #[cfg(feature = "hugepages")]
             huge_pages: false,
+            #[cfg(unix)]
+            reservation: None,
+            growth: None,
+            window_offset: None,
+            truncated: AtomicBool::new(false),
         };
         Ok(Self {
             inner: Arc::new(inner),
@@ -216,7 +364,8 @@ impl MemoryMappedFile {
         let mmap = unsafe { Mmap::map(&file)? };
         let inner = Inner {
             path: path_ref.to_path_buf(),
-            file,
+            file: Some(file),
+            backing: Backing::File,
             mode: MmapMode::ReadOnly,
             cached_len: RwLock::new(len),
             map: MapVariant::Ro(mmap),
@@ -224,6 +373,11 @@ impl MemoryMappedFile {
             written_since_last_flush: RwLock::new(0),
             #[cfg(feature = "hugepages")]
             huge_pages: false,
+            #[cfg(unix)]
+            reservation: None,
+            growth: None,
+            window_offset: None,
+            truncated: AtomicBool::new(false),
         };
         Ok(Self {
             inner: Arc::new(inner),
@@ -249,14 +403,179 @@ impl MemoryMappedFile {
         let mmap = unsafe { MmapMut::map_mut(&file)? };
         let inner = Inner {
             path: path_ref.to_path_buf(),
-            file,
+            file: Some(file),
+            backing: Backing::File,
             mode: MmapMode::ReadWrite,
             cached_len: RwLock::new(len),
-            map: MapVariant::Rw(RwLock::new(mmap)),
+            map: MapVariant::Rw(RwLock::new(mmap.into())),
             flush_policy: FlushPolicy::default(),
             written_since_last_flush: RwLock::new(0),
             #[cfg(feature = "hugepages")]
             huge_pages: false,
+            #[cfg(unix)]
+            reservation: None,
+            growth: None,
+            window_offset: None,
+            truncated: AtomicBool::new(false),
+        };
+        Ok(Self {
+            inner: Arc::new(inner),
+        })
+    }
+
+    /// Create an anonymous read-write mapping of `size` bytes (`MAP_ANONYMOUS`).
+    ///
+    /// Nothing is written to disk: the memory is private to this process and released on drop.
+    /// Segments, iterators, atomics and `resize` work as for file-backed mappings; `flush` is a no-op.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use mmap_io::MemoryMappedFile;
+    ///
+    /// let scratch = MemoryMappedFile::anonymous(4096)?;
+    /// scratch.update_region(0, b"scratch")?;
+    /// scratch.resize(8192)?;
+    ///
+    /// let mut buf = [0u8; 7];
+    /// scratch.read_into(0, &mut buf)?;
+    /// assert_eq!(&buf, b"scratch");
+    /// # Ok::<(), mmap_io::MmapIoError>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::ResizeFailed` if size is zero or exceeds the maximum safe limit.
+    /// Returns `MmapIoError::Io` if the mapping fails.
+    pub fn anonymous(size: u64) -> Result<Self> {
+        check_new_size(size)?;
+        let mmap = MmapMut::map_anon(size as usize)?;
+        let inner = Inner {
+            path: PathBuf::new(),
+            file: None,
+            backing: Backing::Anonymous,
+            mode: MmapMode::ReadWrite,
+            cached_len: RwLock::new(size),
+            map: MapVariant::Rw(RwLock::new(mmap.into())),
+            flush_policy: FlushPolicy::Never,
+            written_since_last_flush: RwLock::new(0),
+            #[cfg(feature = "hugepages")]
+            huge_pages: false,
+            #[cfg(unix)]
+            reservation: None,
+            growth: None,
+            window_offset: None,
+            truncated: AtomicBool::new(false),
+        };
+        Ok(Self {
+            inner: Arc::new(inner),
+        })
+    }
+
+    /// Create a read-write mapping of `size` bytes backed by a temporary file in the system
+    /// temp directory. See [`MemoryMappedFile::temp_in`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::ResizeFailed` if size is zero or exceeds the maximum safe limit.
+    /// Returns `MmapIoError::Io` if the temporary file cannot be created or mapped.
+    pub fn temp(size: u64) -> Result<Self> {
+        Self::temp_in(std::env::temp_dir(), size)
+    }
+
+    /// Create a read-write mapping of `size` bytes backed by a temporary file in `dir`.
+    ///
+    /// The file never outlives the mapping, even if the process crashes: Linux uses an unnamed
+    /// `O_TMPFILE` when the filesystem supports it, other Unix systems unlink the file right after
+    /// creating it, and Windows opens it with `FILE_FLAG_DELETE_ON_CLOSE`. Unlike
+    /// [`MemoryMappedFile::anonymous`] the pages can be written back to disk under memory
+    /// pressure. [`MemoryMappedFile::path`] returns `dir`; `flush` is a no-op.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::ResizeFailed` if size is zero or exceeds the maximum safe limit.
+    /// Returns `MmapIoError::Io` if the temporary file cannot be created or mapped.
+    pub fn temp_in<P: AsRef<Path>>(dir: P, size: u64) -> Result<Self> {
+        check_new_size(size)?;
+        let dir = dir.as_ref();
+        let file = create_temp_file(dir)?;
+        file.set_len(size)?;
+        // SAFETY: The file is private to this mapping and has been sized above.
+        let mmap = unsafe { MmapMut::map_mut(&file)? };
+        let inner = Inner {
+            path: dir.to_path_buf(),
+            file: Some(file),
+            backing: Backing::Temp,
+            mode: MmapMode::ReadWrite,
+            cached_len: RwLock::new(size),
+            map: MapVariant::Rw(RwLock::new(mmap.into())),
+            flush_policy: FlushPolicy::Never,
+            written_since_last_flush: RwLock::new(0),
+            #[cfg(feature = "hugepages")]
+            huge_pages: false,
+            #[cfg(unix)]
+            reservation: None,
+            growth: None,
+            window_offset: None,
+            truncated: AtomicBool::new(false),
+        };
+        Ok(Self {
+            inner: Arc::new(inner),
+        })
+    }
+
+    /// Map an already-open backing object of `len` bytes in the given mode, as a window at
+    /// `window_offset` when one is given.
+    #[cfg_attr(not(feature = "shm"), allow(dead_code))]
+    pub(crate) fn from_file(
+        path: PathBuf,
+        file: File,
+        backing: Backing,
+        mode: MmapMode,
+        window_offset: Option<u64>,
+        len: u64,
+    ) -> Result<Self> {
+        if len == 0 {
+            return Err(MmapIoError::ResizeFailed(ERR_ZERO_LENGTH_FILE.into()));
+        }
+        // Map exactly `len` bytes: the caller's view of the length is authoritative.
+        let opts = window_options(window_offset.unwrap_or(0), len);
+        // SAFETY: The caller provides a descriptor opened with access rights matching `mode`.
+        let map = match mode {
+            MmapMode::ReadOnly => MapVariant::Ro(unsafe { opts.map(&file)? }),
+            MmapMode::ReadWrite => {
+                MapVariant::Rw(RwLock::new(unsafe { opts.map_mut(&file)? }.into()))
+            }
+            #[cfg(feature = "cow")]
+            MmapMode::CopyOnWrite => MapVariant::Cow(unsafe { opts.map(&file)? }),
+            #[cfg(not(feature = "cow"))]
+            MmapMode::CopyOnWrite => {
+                return Err(MmapIoError::InvalidMode(
+                    "CopyOnWrite mode requires 'cow' feature",
+                ))
+            }
+        };
+        let flush_policy = if backing.is_persistent() && mode == MmapMode::ReadWrite {
+            FlushPolicy::default()
+        } else {
+            FlushPolicy::Never
+        };
+        let inner = Inner {
+            path,
+            file: Some(file),
+            backing,
+            mode,
+            cached_len: RwLock::new(len),
+            map,
+            flush_policy,
+            written_since_last_flush: RwLock::new(0),
+            #[cfg(feature = "hugepages")]
+            huge_pages: false,
+            #[cfg(unix)]
+            reservation: None,
+            growth: None,
+            window_offset,
+            truncated: AtomicBool::new(false),
         };
         Ok(Self {
             inner: Arc::new(inner),
@@ -281,8 +600,81 @@ impl MemoryMappedFile {
         self.len() == 0
     }
 
+    /// Bytes of address space reserved for in-place growth, if the mapping was built with
+    /// [`MemoryMappedFileBuilder::reserve_address_space`].
+    #[cfg(unix)]
+    #[must_use]
+    pub fn reserved_len(&self) -> Option<u64> {
+        self.inner.reservation.as_ref().map(|r| r.reserved())
+    }
+
+    /// File offset where the mapped window starts, if the mapping was built with
+    /// [`MemoryMappedFileBuilder::offset`] or [`MemoryMappedFileBuilder::len`]. All other
+    /// offsets in this API are relative to it.
+    #[must_use]
+    pub fn window_offset(&self) -> Option<u64> {
+        self.inner.window_offset
+    }
+
+    /// Bytes in use: the end of the furthest write through the growing write paths, or the
+    /// file length at open. Equal to [`len`](Self::len) without a growth policy.
+    #[must_use]
+    pub fn len_used(&self) -> u64 {
+        match &self.inner.growth {
+            Some(growth) => growth.used().min(self.len()),
+            None => self.len(),
+        }
+    }
+
+    /// Trim the file to [`len_used`](Self::len_used), releasing capacity added by the growth
+    /// policy. Call it before closing a mapping that grew; a no-op when nothing is spare.
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from [`resize`](Self::resize).
+    pub fn shrink_to_fit(&self) -> Result<()> {
+        let used = self.len_used().max(1);
+        if used < self.len() {
+            self.resize(used)?;
+        }
+        Ok(())
+    }
+
+    // Grow to fit a write of [offset, offset+len) under the growth policy, if there is one.
+    pub(crate) fn grow_for_write(&self, offset: u64, len: u64) -> Result<()> {
+        match &self.inner.growth {
+            Some(growth) if len > 0 => growth.ensure(self, offset, len),
+            _ => Ok(()),
+        }
+    }
+
+    // Length that writes may reach: the growth policy's `max_size`, or the file length.
+    pub(crate) fn writable_len(&self) -> Result<u64> {
+        match &self.inner.growth {
+            Some(growth) => Ok(growth.max_size()),
+            None => self.current_len(),
+        }
+    }
+
+    // Refuse writes to a read-write mapping after a recovered SIGBUS (see `Inner::truncated`).
+    pub(crate) fn check_truncated(&self, offset: u64, len: u64) -> Result<()> {
+        if matches!(self.inner.map, MapVariant::Rw(_))
+            && self.inner.truncated.load(Ordering::Acquire)
+        {
+            return Err(MmapIoError::Truncated { offset, len });
+        }
+        Ok(())
+    }
+
+    pub(crate) fn mark_used(&self, end: usize) {
+        if let Some(growth) = &self.inner.growth {
+            growth.mark_used(end as u64);
+        }
+    }
+
     /// Get a zero-copy read-only slice for the given [offset, offset+len).
-    /// For RW mappings, cannot return a reference bound to a temporary guard; use `read_into` instead.
+    /// For RW mappings, cannot return a reference bound to a temporary guard; use `read_into`
+    /// or `as_slice_guarded` instead.
     ///
     /// # Performance
     ///
@@ -310,14 +702,38 @@ impl MemoryMappedFile {
         }
     }
 
+    /// Get a zero-copy read-only view of [offset, offset+len) that works in every mode.
+    ///
+    /// For read-write mappings the view holds the mapping's read lock, so writes and
+    /// `resize` on this mapping block until it is dropped. Do not write to the same mapping
+    /// from the thread holding the view.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::OutOfBounds` if range exceeds file bounds.
+    pub fn as_slice_guarded(&self, offset: u64, len: u64) -> Result<MappedSlice<'_>> {
+        let (start, end) = slice_range(offset, len, self.current_len()?)?;
+        let inner = match &self.inner.map {
+            MapVariant::Ro(m) | MapVariant::Cow(m) => MappedSliceInner::Borrowed(&m[start..end]),
+            MapVariant::Rw(lock) => MappedSliceInner::Guarded {
+                guard: lock.read(),
+                range: start..end,
+            },
+        };
+        Ok(MappedSlice { inner })
+    }
+
     /// Get a zero-copy mutable slice for the given [offset, offset+len).
     /// Only available in `ReadWrite` mode.
     ///
     /// # Errors
     ///
     /// Returns `MmapIoError::InvalidMode` if not in `ReadWrite` mode.
-    /// Returns `MmapIoError::OutOfBounds` if range exceeds file bounds.
+    /// Returns `MmapIoError::OutOfBounds` if range exceeds file bounds, or the `max_size` of a
+    /// growth policy.
     pub fn as_slice_mut(&self, offset: u64, len: u64) -> Result<MappedSliceMut<'_>> {
+        self.check_truncated(offset, len)?;
+        self.grow_for_write(offset, len)?;
         let (start, end) = slice_range(offset, len, self.current_len()?)?;
         match &self.inner.map {
             MapVariant::Ro(_) => Err(MmapIoError::InvalidMode(
@@ -325,6 +741,7 @@ impl MemoryMappedFile {
             )),
             MapVariant::Rw(lock) => {
                 let guard = lock.write();
+                self.mark_used(end);
                 Ok(MappedSliceMut {
                     guard,
                     range: start..end,
@@ -340,8 +757,30 @@ impl MemoryMappedFile {
         }
     }
 
+    /// Run `f` with the base pointer and length of a read-write mapping while holding only the
+    /// read lock, so `resize` cannot remap until `f` returns but other holders are not blocked.
+    ///
+    /// Callers may write through the pointer only to ranges that no other thread accesses at
+    /// the same time, and must not take this mapping's locks again inside `f`.
+    #[cfg(feature = "appender")]
+    pub(crate) fn with_shared_ptr<R>(&self, f: impl FnOnce(*mut u8, usize) -> R) -> Result<R> {
+        self.check_truncated(0, self.current_len()?)?;
+        match &self.inner.map {
+            MapVariant::Rw(lock) => {
+                let guard = lock.read();
+                // The pointer comes from the mapping itself, not from a shared slice, so
+                // writing through it does not alias any Rust reference handed out by this type
+                // as long as callers keep to their own ranges.
+                Ok(f(guard.as_ptr().cast_mut(), guard.len()))
+            }
+            _ => Err(MmapIoError::InvalidMode(
+                "shared writes require a ReadWrite mapping",
+            )),
+        }
+    }
+
     /// Copy the provided bytes into the mapped file at the given offset.
-    /// Bounds-checked, zero-copy write.
+    /// Bounds-checked, zero-copy write. With a growth policy the file first grows to fit.
     ///
     /// # Performance
     ///
@@ -352,7 +791,8 @@ impl MemoryMappedFile {
     /// # Errors
     ///
     /// Returns `MmapIoError::InvalidMode` if not in `ReadWrite` mode.
-    /// Returns `MmapIoError::OutOfBounds` if range exceeds file bounds.
+    /// Returns `MmapIoError::OutOfBounds` if range exceeds file bounds, or the `max_size` of a
+    /// growth policy.
     pub fn update_region(&self, offset: u64, data: &[u8]) -> Result<()> {
         if data.is_empty() {
             return Ok(());
@@ -363,6 +803,8 @@ impl MemoryMappedFile {
             ));
         }
         let len = data.len() as u64;
+        self.check_truncated(offset, len)?;
+        self.grow_for_write(offset, len)?;
         let (start, end) = slice_range(offset, len, self.current_len()?)?;
         match &self.inner.map {
             MapVariant::Ro(_) => Err(MmapIoError::InvalidMode(
@@ -373,6 +815,7 @@ impl MemoryMappedFile {
                     let mut guard = lock.write();
                     guard[start..end].copy_from_slice(data);
                 }
+                self.mark_used(end);
                 // Apply flush policy
                 self.apply_flush_policy(len)?;
                 Ok(())
@@ -417,12 +860,15 @@ impl MemoryMappedFile {
     ///
     /// Returns `MmapIoError::FlushFailed` if flush operation fails.
     pub fn flush(&self) -> Result<()> {
+        self.check_truncated(0, self.current_len()?)?;
         match &self.inner.map {
             MapVariant::Ro(_) => Ok(()),
             MapVariant::Cow(_) => Ok(()), // no-op for COW
             MapVariant::Rw(lock) => {
-                // Fast path: no pending writes => skip flushing I/O
-                if *self.inner.written_since_last_flush.read() == 0 {
+                // Fast path: no pending writes or nothing to persist => skip flushing I/O
+                if !self.inner.backing.is_persistent()
+                    || *self.inner.written_since_last_flush.read() == 0
+                {
                     return Ok(());
                 }
 
@@ -436,10 +882,12 @@ impl MemoryMappedFile {
                     }
                 }
 
-                // Fallback/full flush using memmap2 API
+                // Fallback/full flush using memmap2 API. A reserved mapping is longer than the
+                // file, so only the file's part is flushed.
                 let guard = lock.read();
+                let len = self.current_len()? as usize;
                 guard
-                    .flush()
+                    .flush_range(0, len)
                     .map_err(|e| MmapIoError::FlushFailed(e.to_string()))?;
                 // Reset accumulator after a successful flush
                 *self.inner.written_since_last_flush.write() = 0;
@@ -491,12 +939,15 @@ impl MemoryMappedFile {
             return Ok(());
         }
         ensure_in_bounds(offset, len, self.current_len()?)?;
+        self.check_truncated(offset, len)?;
         match &self.inner.map {
             MapVariant::Ro(_) => Ok(()),
             MapVariant::Cow(_) => Ok(()), // no-op for COW
             MapVariant::Rw(lock) => {
-                // If we have no accumulated writes, skip I/O
-                if *self.inner.written_since_last_flush.read() == 0 {
+                // If we have no accumulated writes or nothing to persist, skip I/O
+                if !self.inner.backing.is_persistent()
+                    || *self.inner.written_since_last_flush.read() == 0
+                {
                     return Ok(());
                 }
 
@@ -548,20 +999,58 @@ impl MemoryMappedFile {
     }
 
     /// Resize (grow or shrink) the mapped file (RW only). This remaps the file internally.
+    /// For a windowed mapping it resizes the window instead, extending the file if needed and
+    /// never truncating it.
     ///
     /// # Performance
     ///
     /// - **Time Complexity**: O(1) for the remap operation
     /// - **Memory Usage**: Allocates new virtual address space of `new_size`
     /// - **I/O Operations**: File truncate/extend + new mmap syscall
-    /// - **Note**: Existing pointers/slices become invalid after resize
+    /// - **Note**: Existing pointers/slices become invalid after resize, unless the mapping
+    ///   was built with `reserve_address_space`, which grows in place without remapping
     ///
     /// # Errors
     ///
     /// Returns `MmapIoError::InvalidMode` if not in `ReadWrite` mode.
-    /// Returns `MmapIoError::ResizeFailed` if new size is zero or exceeds the maximum safe limit.
+    /// Returns `MmapIoError::ResizeFailed` if new size is zero or exceeds the maximum safe limit,
+    /// or exceeds an address space reservation that cannot be extended in place.
     /// Returns `MmapIoError::Io` if resize operation fails.
     pub fn resize(&self, new_size: u64) -> Result<()> {
+        self.resize_mapping(new_size)?;
+        if let Some(growth) = &self.inner.growth {
+            growth.truncate_used(new_size);
+        }
+        Ok(())
+    }
+
+    /// Like [`resize`](Self::resize), but allocates disk blocks for the grown range first
+    /// (`fallocate`/`posix_fallocate`), so a full disk fails here instead of with `SIGBUS` on
+    /// a later write. Shrinking behaves exactly like `resize`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::Io` with `ENOSPC` if the filesystem does not have room for the
+    /// grown range; the mapping is left unchanged.
+    /// Returns errors from [`resize`](Self::resize).
+    pub fn resize_preallocated(&self, new_size: u64) -> Result<()> {
+        if self.inner.mode != MmapMode::ReadWrite {
+            return Err(MmapIoError::InvalidMode("Resize requires ReadWrite mode"));
+        }
+        let current = self.current_len()?;
+        if new_size > current && new_size <= MAX_MMAP_SIZE {
+            let base = self.inner.window_offset.unwrap_or(0);
+            crate::prealloc::preallocate(
+                self.inner.file()?,
+                &self.inner.path,
+                base + current,
+                base + new_size,
+            )?;
+        }
+        self.resize(new_size)
+    }
+
+    fn resize_mapping(&self, new_size: u64) -> Result<()> {
         if self.inner.mode != MmapMode::ReadWrite {
             return Err(MmapIoError::InvalidMode("Resize requires ReadWrite mode"));
         }
@@ -576,6 +1065,16 @@ impl MemoryMappedFile {
             )));
         }
 
+        #[cfg(unix)]
+        if let (Some(reservation), MapVariant::Rw(lock)) =
+            (&self.inner.reservation, &self.inner.map)
+        {
+            return reservation.resize(self.inner.file()?, lock, &self.inner.cached_len, new_size);
+        }
+        if let Some(offset) = self.inner.window_offset {
+            return self.resize_window(offset, new_size);
+        }
+
         let current = self.current_len()?;
 
         // On Windows, shrinking a file with an active mapping fails with:
@@ -603,10 +1102,13 @@ impl MemoryMappedFile {
         // Update length on disk for non-windows, or for growing on windows.
         // Silence unused variable warning when the Windows shrink early-return path is compiled.
         let _ = &current;
-        self.inner.file.set_len(new_size)?;
+        let Some(file) = &self.inner.file else {
+            return self.resize_anonymous(new_size);
+        };
+        file.set_len(new_size)?;
 
         // Remap with the new size.
-        let new_map = unsafe { MmapMut::map_mut(&self.inner.file)? };
+        let new_map = unsafe { MmapMut::map_mut(file)? };
         match &self.inner.map {
             MapVariant::Ro(_) => Err(MmapIoError::InvalidMode(
                 "Cannot remap read-only mapping as read-write",
@@ -616,7 +1118,7 @@ impl MemoryMappedFile {
             )),
             MapVariant::Rw(lock) => {
                 let mut guard = lock.write();
-                *guard = new_map;
+                *guard = new_map.into();
                 // Update cached length
                 *self.inner.cached_len.write() = new_size;
                 Ok(())
@@ -624,6 +1126,63 @@ impl MemoryMappedFile {
         }
     }
 
+    /// Re-read the file length and remap if another process grew or shrank the file.
+    ///
+    /// The length is otherwise cached at open, so readers of a file that is appended to
+    /// elsewhere never see the new bytes. Remapping invalidates every slice into the old
+    /// mapping, so this takes `&mut self` and requires this to be the only handle: it fails
+    /// if the mapping has been cloned. [`follow`](Self::follow) (feature `watch`) refreshes
+    /// automatically as the file changes. After a recovered SIGBUS (see [`crate::sigbus`]) it
+    /// remaps even if the length is unchanged, which lets a read-write mapping write again.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidMode` if other handles to the mapping exist, or for
+    /// anonymous, windowed and reserved mappings.
+    /// Returns `MmapIoError::Io` if the file cannot be inspected or remapped.
+    pub fn refresh(&mut self) -> Result<LengthChange> {
+        let inner = Arc::get_mut(&mut self.inner).ok_or(MmapIoError::InvalidMode(
+            "refresh requires the only handle to the mapping",
+        ))?;
+        #[cfg(unix)]
+        if inner.reservation.is_some() {
+            return Err(MmapIoError::InvalidMode(
+                "refresh is not supported on reserved mappings",
+            ));
+        }
+        if inner.window_offset.is_some() {
+            return Err(MmapIoError::InvalidMode(
+                "refresh is not supported on windowed mappings",
+            ));
+        }
+        let file = inner.file.as_ref().ok_or(MmapIoError::InvalidMode(
+            "anonymous mapping has no backing file",
+        ))?;
+        let from = *inner.cached_len.get_mut();
+        let to = file.metadata()?.len();
+        // A recovered SIGBUS left zero pages in the mapping, so remap even at the same length.
+        if to == from && !*inner.truncated.get_mut() {
+            return Ok(LengthChange::Unchanged);
+        }
+        // SAFETY: `&mut self` and the unique Arc mean no slice into the old mapping is alive.
+        inner.map = match &inner.map {
+            MapVariant::Ro(_) => MapVariant::Ro(unsafe { Mmap::map(file)? }),
+            MapVariant::Cow(_) => MapVariant::Cow(unsafe { Mmap::map(file)? }),
+            MapVariant::Rw(_) => {
+                MapVariant::Rw(RwLock::new(unsafe { MmapMut::map_mut(file)? }.into()))
+            }
+        };
+        *inner.cached_len.get_mut() = to;
+        *inner.truncated.get_mut() = false;
+        Ok(if to == from {
+            LengthChange::Unchanged
+        } else if to > from {
+            LengthChange::Grew { from, to }
+        } else {
+            LengthChange::Shrank { from, to }
+        })
+    }
+
     /// Path to the underlying file.
     #[must_use]
     pub fn path(&self) -> &Path {
@@ -729,14 +1288,57 @@ impl MemoryMappedFile {
 }
 
 impl MemoryMappedFile {
+    // Resize a windowed mapping by remapping the window with the new length. The file is
+    // extended when the window grows past its end but never truncated, since the bytes after
+    // the window are not ours.
+    fn resize_window(&self, offset: u64, new_size: u64) -> Result<()> {
+        let MapVariant::Rw(lock) = &self.inner.map else {
+            return Err(MmapIoError::InvalidMode("Resize requires ReadWrite mode"));
+        };
+        let file = self.inner.file()?;
+        let end = offset.saturating_add(new_size);
+        if file.metadata()?.len() < end {
+            file.set_len(end)?;
+        }
+        let new_map = unsafe { window_options(offset, new_size).map_mut(file)? };
+        let mut guard = lock.write();
+        *guard = new_map.into();
+        *self.inner.cached_len.write() = new_size;
+        Ok(())
+    }
+
+    // Resize an anonymous mapping: there is no file to extend, so the memory itself is remapped.
+    fn resize_anonymous(&self, new_size: u64) -> Result<()> {
+        let MapVariant::Rw(lock) = &self.inner.map else {
+            return Err(MmapIoError::InvalidMode(
+                "anonymous mappings are always read-write",
+            ));
+        };
+        let mut guard = lock.write();
+        #[cfg(target_os = "linux")]
+        {
+            // SAFETY: The write guard guarantees no outstanding borrows of the old mapping.
+            unsafe {
+                guard.mmap.remap(
+                    new_size as usize,
+                    memmap2::RemapOptions::new().may_move(true),
+                )?;
+            }
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            let mut new_map = MmapMut::map_anon(new_size as usize)?;
+            let keep = std::cmp::min(guard.len(), new_map.len());
+            new_map[..keep].copy_from_slice(&guard[..keep]);
+            *guard = new_map.into();
+        }
+        *self.inner.cached_len.write() = new_size;
+        Ok(())
+    }
+
     // Helper method to attempt Linux-specific async flush
     #[cfg(all(unix, target_os = "linux"))]
     fn try_linux_async_flush(&self, len: usize) -> Result<bool> {
-        use std::os::fd::AsRawFd;
-
-        // Get the file descriptor (unused but kept for potential future use)
-        let _fd = self.inner.file.as_raw_fd();
-
         // Try to get the mapping pointer for msync
         match &self.inner.map {
             MapVariant::Rw(lock) => {
@@ -762,6 +1364,67 @@ impl MemoryMappedFile {
 
 // (Removed duplicate import of RwLockWriteGuard)
 
+// Validate the size of a mapping about to be created.
+fn check_new_size(size: u64) -> Result<()> {
+    if size == 0 {
+        return Err(MmapIoError::ResizeFailed(ERR_ZERO_SIZE.into()));
+    }
+    if size > MAX_MMAP_SIZE {
+        return Err(MmapIoError::ResizeFailed(format!(
+            "Size {size} exceeds maximum safe limit of {MAX_MMAP_SIZE} bytes"
+        )));
+    }
+    Ok(())
+}
+
+/// Create a temporary file in `dir` that is removed by the OS once its last handle is closed.
+fn create_temp_file(dir: &Path) -> Result<File> {
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+
+        match OpenOptions::new()
+            .read(true)
+            .write(true)
+            .mode(0o600)
+            .custom_flags(libc::O_TMPFILE)
+            .open(dir)
+        {
+            Ok(file) => return Ok(file),
+            // Filesystems without O_TMPFILE support fall back to unlink-on-create below.
+            Err(e) if matches!(e.raw_os_error(), Some(libc::EOPNOTSUPP | libc::EISDIR)) => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    loop {
+        let path = dir.join(format!(
+            ".mmap-io-{}-{}.tmp",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let mut opts = OpenOptions::new();
+        opts.read(true).write(true).create_new(true);
+        #[cfg(windows)]
+        {
+            use std::os::windows::fs::OpenOptionsExt;
+            const FILE_FLAG_DELETE_ON_CLOSE: u32 = 0x0400_0000;
+            opts.custom_flags(FILE_FLAG_DELETE_ON_CLOSE);
+        }
+        match opts.open(&path) {
+            Ok(file) => {
+                #[cfg(not(windows))]
+                std::fs::remove_file(&path)?;
+                return Ok(file);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
 /// Create a memory mapping with optional huge pages support.
 ///
 /// When `huge` is true on Linux, this function attempts to use actual huge pages
@@ -867,6 +1530,41 @@ fn try_create_optimized_mapping(file: &File, len: u64) -> Result<MmapMut> {
     }
 }
 
+// Run `f`, which reads `bytes` found at `offset` in the mapping of `inner`, reporting
+// `Truncated` and marking the mapping when the SIGBUS handler recovered a fault inside them.
+fn guard_truncation<R>(
+    inner: &Inner,
+    offset: u64,
+    bytes: &[u8],
+    f: impl FnOnce() -> R,
+) -> Result<R> {
+    #[cfg(unix)]
+    {
+        let result = crate::sigbus::guarded(offset, bytes, f);
+        if let Err(MmapIoError::Truncated { .. }) = result {
+            inner.truncated.store(true, Ordering::Release);
+        }
+        result
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (inner, offset, bytes);
+        Ok(f())
+    }
+}
+
+pub(crate) fn copy_guarded(inner: &Inner, offset: u64, src: &[u8], dst: &mut [u8]) -> Result<()> {
+    guard_truncation(inner, offset, src, || dst.copy_from_slice(src))
+}
+
+// Options mapping `len` bytes of a file from `offset`. memmap2 aligns the offset down to the
+// page (or allocation granularity) boundary and hides the difference from the slice.
+fn window_options(offset: u64, len: u64) -> MmapOptions {
+    let mut opts = MmapOptions::new();
+    opts.offset(offset).len(len as usize);
+    opts
+}
+
 #[cfg(not(all(unix, target_os = "linux", feature = "hugepages")))]
 #[allow(dead_code)]
 fn try_create_optimized_mapping(_file: &File, _len: u64) -> Result<MmapMut> {
@@ -907,7 +1605,8 @@ impl MemoryMappedFile {
         };
         let inner = Inner {
             path: path_ref.to_path_buf(),
-            file,
+            file: Some(file),
+            backing: Backing::File,
             mode: MmapMode::CopyOnWrite,
             cached_len: RwLock::new(len),
             map: MapVariant::Cow(mmap),
@@ -916,6 +1615,11 @@ impl MemoryMappedFile {
             written_since_last_flush: RwLock::new(0),
             #[cfg(feature = "hugepages")]
             huge_pages: false,
+            #[cfg(unix)]
+            reservation: None,
+            growth: None,
+            window_offset: None,
+            truncated: AtomicBool::new(false),
         };
         Ok(Self {
             inner: Arc::new(inner),
@@ -924,7 +1628,7 @@ impl MemoryMappedFile {
 }
 
 impl MemoryMappedFile {
-    fn apply_flush_policy(&self, written: u64) -> Result<()> {
+    pub(crate) fn apply_flush_policy(&self, written: u64) -> Result<()> {
         match self.inner.flush_policy {
             FlushPolicy::Never | FlushPolicy::Manual => Ok(()),
             FlushPolicy::Always => {
@@ -997,6 +1701,8 @@ impl MemoryMappedFile {
     /// # Errors
     ///
     /// Returns `MmapIoError::OutOfBounds` if range exceeds file bounds.
+    /// Returns `MmapIoError::Truncated` if the file was truncated under the mapping while the
+    /// SIGBUS handler is installed (Unix, see [`crate::sigbus`]).
     pub fn read_into(&self, offset: u64, buf: &mut [u8]) -> Result<()> {
         let total = self.current_len()?;
         let len = buf.len() as u64;
@@ -1004,36 +1710,79 @@ impl MemoryMappedFile {
         match &self.inner.map {
             MapVariant::Ro(m) => {
                 let (start, end) = slice_range(offset, len, total)?;
-                buf.copy_from_slice(&m[start..end]);
-                Ok(())
+                copy_guarded(&self.inner, offset, &m[start..end], buf)
             }
             MapVariant::Rw(lock) => {
                 let guard = lock.read();
                 let (start, end) = slice_range(offset, len, total)?;
-                buf.copy_from_slice(&guard[start..end]);
-                Ok(())
+                copy_guarded(&self.inner, offset, &guard[start..end], buf)
             }
             MapVariant::Cow(m) => {
                 let (start, end) = slice_range(offset, len, total)?;
-                buf.copy_from_slice(&m[start..end]);
-                Ok(())
+                copy_guarded(&self.inner, offset, &m[start..end], buf)
             }
         }
     }
+
+    /// Run `f` on a zero-copy view of [offset, offset+len) in any mode.
+    ///
+    /// While the SIGBUS handler is installed (Unix, see [`crate::sigbus`]), pages cut away by
+    /// a truncation read as zeros inside `f` and the call returns `Truncated` instead of
+    /// killing the process. As with `as_slice_guarded`, the view holds the read lock of a
+    /// read-write mapping.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::OutOfBounds` if range exceeds file bounds.
+    /// Returns `MmapIoError::Truncated` if `f` touched pages that are no longer backed.
+    pub fn read_with<R>(&self, offset: u64, len: u64, f: impl FnOnce(&[u8]) -> R) -> Result<R> {
+        let view = self.as_slice_guarded(offset, len)?;
+        guard_truncation(&self.inner, offset, &view, || f(&view))
+    }
 }
 
 /// Builder for MemoryMappedFile construction with options.
 pub struct MemoryMappedFileBuilder {
     path: PathBuf,
+    backing: Backing,
     size: Option<u64>,
     mode: Option<MmapMode>,
     flush_policy: FlushPolicy,
     touch_hint: TouchHint,
     #[cfg(feature = "hugepages")]
     huge_pages: bool,
+    #[cfg(unix)]
+    reserve: Option<u64>,
+    growth_policy: Option<GrowthPolicy>,
+    max_size: u64,
+    shrink_on_drop: bool,
+    offset: Option<u64>,
+    len: Option<u64>,
+    preallocate: bool,
 }
 
 impl MemoryMappedFileBuilder {
+    pub(crate) fn with_backing(path: PathBuf, backing: Backing) -> Self {
+        Self {
+            path,
+            backing,
+            size: None,
+            mode: None,
+            flush_policy: FlushPolicy::default(),
+            touch_hint: TouchHint::default(),
+            #[cfg(feature = "hugepages")]
+            huge_pages: false,
+            #[cfg(unix)]
+            reserve: None,
+            growth_policy: None,
+            max_size: MAX_MMAP_SIZE,
+            shrink_on_drop: false,
+            offset: None,
+            len: None,
+            preallocate: false,
+        }
+    }
+
     /// Specify the size (required for create/ReadWrite new files).
     pub fn size(mut self, size: u64) -> Self {
         self.size = Some(size);
@@ -1058,6 +1807,145 @@ impl MemoryMappedFileBuilder {
         self
     }
 
+    /// Seals to add to a `memfd` region right after it is created and sized.
+    /// Ignored for other backings. Write seals cannot be applied here; add them with
+    /// [`MemoryMappedFile::seal`] once the contents are written.
+    #[cfg(all(any(target_os = "linux", target_os = "android"), feature = "shm"))]
+    pub fn seals(mut self, seals: crate::shm::MemfdSeals) -> Self {
+        if let Backing::Memfd { seals: current, .. } = &mut self.backing {
+            *current = seals;
+        }
+        self
+    }
+
+    /// Reserve `bytes` of address space for a ReadWrite file mapping so that `resize` grows it
+    /// in place and its base address never changes.
+    ///
+    /// The range past the end of the file is reserved `PROT_NONE` and costs no memory. Growth
+    /// maps new pages over it with `MAP_FIXED` without taking the write lock, so slices, atomic
+    /// views and raw pointers into the mapping stay valid. Growing past the reservation
+    /// falls back to an in-place `mremap` on Linux and fails elsewhere. Ignored for other
+    /// modes and backings, and takes precedence over huge pages.
+    #[cfg(unix)]
+    pub fn reserve_address_space(mut self, bytes: u64) -> Self {
+        self.reserve = Some(bytes);
+        self
+    }
+
+    /// Grow a ReadWrite mapping through `resize` when a write ends past the end of the file,
+    /// instead of failing with `OutOfBounds`.
+    ///
+    /// Applies to `update_region`, `as_slice_mut`, `SegmentMut` and `copy_range` into the
+    /// mapping; the crate has no separate write cursor type. The mapping then also tracks
+    /// [`MemoryMappedFile::len_used`], and [`MemoryMappedFile::shrink_to_fit`] trims the
+    /// spare capacity (see also [`shrink_on_drop`](Self::shrink_on_drop)). Ignored for other
+    /// modes.
+    pub fn growth_policy(mut self, policy: GrowthPolicy) -> Self {
+        self.growth_policy = Some(policy);
+        self
+    }
+
+    /// Upper bound on the file length reached through the growth policy (default: the
+    /// maximum mapping size). Writes that would end past it fail with `OutOfBounds`.
+    pub fn max_size(mut self, bytes: u64) -> Self {
+        self.max_size = bytes.min(MAX_MMAP_SIZE);
+        self
+    }
+
+    /// With a growth policy, trim the file to [`MemoryMappedFile::len_used`] when the last
+    /// handle to the mapping is dropped, as if [`MemoryMappedFile::shrink_to_fit`] ran on
+    /// close. Errors are ignored there; call `shrink_to_fit` yourself to see them. Windows
+    /// into a file are never trimmed. Off by default.
+    pub fn shrink_on_drop(mut self, enable: bool) -> Self {
+        self.shrink_on_drop = enable;
+        self
+    }
+
+    // Growth state for a ReadWrite mapping whose first `used` bytes are in use.
+    fn growth(&self, used: u64) -> Option<Growth> {
+        self.growth_policy
+            .map(|policy| Growth::new(policy, self.max_size, used, self.shrink_on_drop))
+    }
+
+    /// Map only a window of the file starting at byte `offset`, instead of the whole file.
+    ///
+    /// The offset does not need to be page-aligned: the mapping starts at the page boundary
+    /// below it, and every offset passed to the resulting `MemoryMappedFile` is relative to
+    /// the window. Without [`len`](Self::len) the window runs to the end of the file.
+    pub fn offset(mut self, offset: u64) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    /// Length of the mapped window. With `create()` and no `size`, the new file is sized to
+    /// end where the window ends.
+    pub fn len(mut self, len: u64) -> Self {
+        self.len = Some(len);
+        self
+    }
+
+    /// Allocate disk blocks for the whole file at `create()` instead of leaving it sparse.
+    ///
+    /// A full disk then fails `create()` with `ENOSPC` rather than raising `SIGBUS` on a
+    /// later write. Use [`MemoryMappedFile::resize_preallocated`] to keep growth allocated.
+    pub fn preallocate(mut self, enable: bool) -> Self {
+        self.preallocate = enable;
+        self
+    }
+
+    // The window to map in a file of `file_len` bytes as (offset, len), or `None` for the
+    // whole file.
+    fn window(&self, file_len: u64) -> Result<Option<(u64, u64)>> {
+        if self.offset.is_none() && self.len.is_none() {
+            return Ok(None);
+        }
+        let offset = self.offset.unwrap_or(0);
+        let len = self.len.unwrap_or(file_len.saturating_sub(offset));
+        if len == 0 {
+            return Err(MmapIoError::ResizeFailed(ERR_ZERO_LENGTH_WINDOW.into()));
+        }
+        ensure_in_bounds(offset, len, file_len)?;
+        if len > MAX_MMAP_SIZE {
+            return Err(MmapIoError::ResizeFailed(format!(
+                "Window of {len} bytes exceeds maximum safe limit of {MAX_MMAP_SIZE} bytes"
+            )));
+        }
+        Ok(Some((offset, len)))
+    }
+
+    // Map a ReadWrite file of `len` bytes, inside a reservation when one was requested.
+    #[cfg(unix)]
+    fn map_rw(
+        &self,
+        file: &File,
+        len: u64,
+        window: Option<(u64, u64)>,
+    ) -> Result<(RwMap, Option<crate::reserve::Reservation>)> {
+        if let (Some(reserve), Backing::File, None) = (self.reserve, &self.backing, window) {
+            let (mmap, reservation) = crate::reserve::map_reserved(file, len, reserve)?;
+            return Ok((mmap, Some(reservation)));
+        }
+        Ok((self.map_rw_plain(file, len, window)?.into(), None))
+    }
+
+    #[cfg(not(unix))]
+    fn map_rw(&self, file: &File, len: u64, window: Option<(u64, u64)>) -> Result<RwMap> {
+        Ok(self.map_rw_plain(file, len, window)?.into())
+    }
+
+    // Map with consideration for huge pages if requested.
+    #[cfg_attr(not(feature = "hugepages"), allow(unused_variables))]
+    fn map_rw_plain(&self, file: &File, len: u64, window: Option<(u64, u64)>) -> Result<MmapMut> {
+        if let Some((offset, len)) = window {
+            return Ok(unsafe { window_options(offset, len).map_mut(file)? });
+        }
+        #[cfg(feature = "hugepages")]
+        let mmap = map_mut_with_options(file, len, self.huge_pages)?;
+        #[cfg(not(feature = "hugepages"))]
+        let mmap = unsafe { MmapMut::map_mut(file)? };
+        Ok(mmap)
+    }
+
     /// Request Huge Pages (Linux MAP_HUGETLB). No-op on non-Linux platforms.
     #[cfg(feature = "hugepages")]
     pub fn huge_pages(mut self, enable: bool) -> Self {
@@ -1065,12 +1953,57 @@ impl MemoryMappedFileBuilder {
         self
     }
 
+    // Open (or create and truncate) the object backing this builder.
+    fn open_backing(&self, create: bool, write: bool) -> Result<File> {
+        match &self.backing {
+            Backing::File => {
+                let mut opts = OpenOptions::new();
+                opts.read(true).write(write);
+                if create {
+                    opts.create(true).truncate(true);
+                }
+                Ok(opts.open(&self.path)?)
+            }
+            #[cfg(all(unix, feature = "shm"))]
+            Backing::Shm(name) => crate::shm::open_shm(name, create, write),
+            #[cfg(all(any(target_os = "linux", target_os = "android"), feature = "shm"))]
+            Backing::Memfd { name, seals } => {
+                if !create {
+                    return Err(MmapIoError::InvalidMode(
+                        "memfd regions can only be created, not opened by name",
+                    ));
+                }
+                if seals.write {
+                    return Err(MmapIoError::InvalidMode(
+                        "write seals must be added with MemoryMappedFile::seal after writing",
+                    ));
+                }
+                crate::shm::create_memfd(name)
+            }
+            Backing::Anonymous | Backing::Temp => Err(MmapIoError::InvalidMode(
+                "use MemoryMappedFile::anonymous or temp for unnamed mappings",
+            )),
+        }
+    }
+
+    // Shared-memory backings are never persisted, so flush policies are no-ops for them.
+    fn effective_flush_policy(&self) -> FlushPolicy {
+        if self.backing.is_persistent() {
+            self.flush_policy
+        } else {
+            FlushPolicy::Never
+        }
+    }
+
     /// Create a new mapping; for ReadWrite requires size for creation.
     pub fn create(self) -> Result<MemoryMappedFile> {
         let mode = self.mode.unwrap_or(MmapMode::ReadWrite);
         match mode {
             MmapMode::ReadWrite => {
-                let size = self.size.ok_or_else(|| {
+                let window_end = self
+                    .len
+                    .map(|len| self.offset.unwrap_or(0).saturating_add(len));
+                let size = self.size.or(window_end).ok_or_else(|| {
                     MmapIoError::ResizeFailed(
                         "Size must be set for create() in ReadWrite mode".into(),
                     )
@@ -1078,24 +2011,28 @@ impl MemoryMappedFileBuilder {
                 if size == 0 {
                     return Err(MmapIoError::ResizeFailed(ERR_ZERO_SIZE.into()));
                 }
-                if size > MAX_MMAP_SIZE {
+                let window = self.window(size)?;
+                if window.is_none() && size > MAX_MMAP_SIZE {
                     return Err(MmapIoError::ResizeFailed(format!(
                         "Size {size} exceeds maximum safe limit of {MAX_MMAP_SIZE} bytes"
                     )));
                 }
                 let path_ref = &self.path;
-                let file = OpenOptions::new()
-                    .create(true)
-                    .write(true)
-                    .read(true)
-                    .truncate(true)
-                    .open(path_ref)?;
+                let file = self.open_backing(true, true)?;
+                // Allocate first so that a full disk leaves the file unsized
+                if self.preallocate {
+                    crate::prealloc::preallocate(&file, path_ref, 0, size)?;
+                }
                 file.set_len(size)?;
-                // Map with consideration for huge pages if requested
-                #[cfg(feature = "hugepages")]
-                let mmap = map_mut_with_options(&file, size, self.huge_pages)?;
-                #[cfg(not(feature = "hugepages"))]
-                let mmap = unsafe { MmapMut::map_mut(&file)? };
+                #[cfg(all(any(target_os = "linux", target_os = "android"), feature = "shm"))]
+                if let Backing::Memfd { seals, .. } = &self.backing {
+                    crate::shm::apply_seals(&file, *seals)?;
+                }
+                #[cfg(unix)]
+                let (mmap, reservation) = self.map_rw(&file, size, window)?;
+                #[cfg(not(unix))]
+                let mmap = self.map_rw(&file, size, window)?;
+                let len = window.map_or(size, |(_, len)| len);
 
                 // Set up time-based flusher if needed (placeholder, not used)
                 if let FlushPolicy::EveryMillis(ms) = self.flush_policy {
@@ -1121,14 +2058,20 @@ impl MemoryMappedFileBuilder {
 
                 let inner = Inner {
                     path: path_ref.clone(),
-                    file,
+                    file: Some(file),
+                    backing: self.backing.clone(),
                     mode,
-                    cached_len: RwLock::new(size),
+                    cached_len: RwLock::new(len),
                     map: MapVariant::Rw(RwLock::new(mmap)),
-                    flush_policy: self.flush_policy,
+                    flush_policy: self.effective_flush_policy(),
                     written_since_last_flush: RwLock::new(0),
                     #[cfg(feature = "hugepages")]
                     huge_pages: self.huge_pages,
+                    #[cfg(unix)]
+                    reservation,
+                    growth: self.growth(0),
+                    window_offset: window.map(|(offset, _)| offset),
+                    truncated: AtomicBool::new(false),
                 };
 
                 let mmap_file = MemoryMappedFile {
@@ -1137,7 +2080,7 @@ impl MemoryMappedFileBuilder {
 
                 // Apply touch hint if specified
                 if self.touch_hint == TouchHint::Eager {
-                    log::debug!("Eagerly touching all pages for {size} bytes");
+                    log::debug!("Eagerly touching all pages for {len} bytes");
                     if let Err(e) = mmap_file.touch_pages() {
                         log::warn!("Failed to eagerly touch pages: {e}");
                         // Don't fail the creation, just log the warning
@@ -1158,12 +2101,15 @@ impl MemoryMappedFileBuilder {
             }
             MmapMode::ReadOnly => {
                 let path_ref = &self.path;
-                let file = OpenOptions::new().read(true).open(path_ref)?;
+                let file = self.open_backing(false, false)?;
                 let len = file.metadata()?.len();
-                let mmap = unsafe { Mmap::map(&file)? };
+                let window = self.window(len)?;
+                let (offset, len) = window.unwrap_or((0, len));
+                let mmap = unsafe { window_options(offset, len).map(&file)? };
                 let inner = Inner {
                     path: path_ref.clone(),
-                    file,
+                    file: Some(file),
+                    backing: self.backing.clone(),
                     mode,
                     cached_len: RwLock::new(len),
                     map: MapVariant::Ro(mmap),
@@ -1171,6 +2117,11 @@ impl MemoryMappedFileBuilder {
                     written_since_last_flush: RwLock::new(0),
                     #[cfg(feature = "hugepages")]
                     huge_pages: false,
+                    #[cfg(unix)]
+                    reservation: None,
+                    growth: None,
+                    window_offset: window.map(|(offset, _)| offset),
+                    truncated: AtomicBool::new(false),
                 };
                 Ok(MemoryMappedFile {
                     inner: Arc::new(inner),
@@ -1179,19 +2130,18 @@ impl MemoryMappedFileBuilder {
             #[cfg(feature = "cow")]
             MmapMode::CopyOnWrite => {
                 let path_ref = &self.path;
-                let file = OpenOptions::new().read(true).open(path_ref)?;
+                let file = self.open_backing(false, false)?;
                 let len = file.metadata()?.len();
                 if len == 0 {
                     return Err(MmapIoError::ResizeFailed(ERR_ZERO_LENGTH_FILE.into()));
                 }
-                let mmap = unsafe {
-                    let mut opts = MmapOptions::new();
-                    opts.len(len as usize);
-                    opts.map(&file)?
-                };
+                let window = self.window(len)?;
+                let (offset, len) = window.unwrap_or((0, len));
+                let mmap = unsafe { window_options(offset, len).map(&file)? };
                 let inner = Inner {
                     path: path_ref.clone(),
-                    file,
+                    file: Some(file),
+                    backing: self.backing.clone(),
                     mode,
                     cached_len: RwLock::new(len),
                     map: MapVariant::Cow(mmap),
@@ -1199,6 +2149,11 @@ impl MemoryMappedFileBuilder {
                     written_since_last_flush: RwLock::new(0),
                     #[cfg(feature = "hugepages")]
                     huge_pages: false,
+                    #[cfg(unix)]
+                    reservation: None,
+                    growth: None,
+                    window_offset: window.map(|(offset, _)| offset),
+                    truncated: AtomicBool::new(false),
                 };
                 Ok(MemoryMappedFile {
                     inner: Arc::new(inner),
@@ -1217,12 +2172,15 @@ impl MemoryMappedFileBuilder {
         match mode {
             MmapMode::ReadOnly => {
                 let path_ref = &self.path;
-                let file = OpenOptions::new().read(true).open(path_ref)?;
+                let file = self.open_backing(false, false)?;
                 let len = file.metadata()?.len();
-                let mmap = unsafe { Mmap::map(&file)? };
+                let window = self.window(len)?;
+                let (offset, len) = window.unwrap_or((0, len));
+                let mmap = unsafe { window_options(offset, len).map(&file)? };
                 let inner = Inner {
                     path: path_ref.clone(),
-                    file,
+                    file: Some(file),
+                    backing: self.backing.clone(),
                     mode,
                     cached_len: RwLock::new(len),
                     map: MapVariant::Ro(mmap),
@@ -1230,6 +2188,11 @@ impl MemoryMappedFileBuilder {
                     written_since_last_flush: RwLock::new(0),
                     #[cfg(feature = "hugepages")]
                     huge_pages: false,
+                    #[cfg(unix)]
+                    reservation: None,
+                    growth: None,
+                    window_offset: window.map(|(offset, _)| offset),
+                    truncated: AtomicBool::new(false),
                 };
                 Ok(MemoryMappedFile {
                     inner: Arc::new(inner),
@@ -1237,25 +2200,33 @@ impl MemoryMappedFileBuilder {
             }
             MmapMode::ReadWrite => {
                 let path_ref = &self.path;
-                let file = OpenOptions::new().read(true).write(true).open(path_ref)?;
+                let file = self.open_backing(false, true)?;
                 let len = file.metadata()?.len();
                 if len == 0 {
                     return Err(MmapIoError::ResizeFailed(ERR_ZERO_LENGTH_FILE.into()));
                 }
-                #[cfg(feature = "hugepages")]
-                let mmap = map_mut_with_options(&file, len, self.huge_pages)?;
-                #[cfg(not(feature = "hugepages"))]
-                let mmap = unsafe { MmapMut::map_mut(&file)? };
+                let window = self.window(len)?;
+                #[cfg(unix)]
+                let (mmap, reservation) = self.map_rw(&file, len, window)?;
+                #[cfg(not(unix))]
+                let mmap = self.map_rw(&file, len, window)?;
+                let len = window.map_or(len, |(_, len)| len);
                 let inner = Inner {
                     path: path_ref.clone(),
-                    file,
+                    file: Some(file),
+                    backing: self.backing.clone(),
                     mode,
                     cached_len: RwLock::new(len),
                     map: MapVariant::Rw(RwLock::new(mmap)),
-                    flush_policy: self.flush_policy,
+                    flush_policy: self.effective_flush_policy(),
                     written_since_last_flush: RwLock::new(0),
                     #[cfg(feature = "hugepages")]
                     huge_pages: self.huge_pages,
+                    #[cfg(unix)]
+                    reservation,
+                    growth: self.growth(len),
+                    window_offset: window.map(|(offset, _)| offset),
+                    truncated: AtomicBool::new(false),
                 };
                 Ok(MemoryMappedFile {
                     inner: Arc::new(inner),
@@ -1264,19 +2235,18 @@ impl MemoryMappedFileBuilder {
             #[cfg(feature = "cow")]
             MmapMode::CopyOnWrite => {
                 let path_ref = &self.path;
-                let file = OpenOptions::new().read(true).open(path_ref)?;
+                let file = self.open_backing(false, false)?;
                 let len = file.metadata()?.len();
                 if len == 0 {
                     return Err(MmapIoError::ResizeFailed(ERR_ZERO_LENGTH_FILE.into()));
                 }
-                let mmap = unsafe {
-                    let mut opts = MmapOptions::new();
-                    opts.len(len as usize);
-                    opts.map(&file)?
-                };
+                let window = self.window(len)?;
+                let (offset, len) = window.unwrap_or((0, len));
+                let mmap = unsafe { window_options(offset, len).map(&file)? };
                 let inner = Inner {
                     path: path_ref.clone(),
-                    file,
+                    file: Some(file),
+                    backing: self.backing.clone(),
                     mode,
                     cached_len: RwLock::new(len),
                     map: MapVariant::Cow(mmap),
@@ -1284,6 +2254,11 @@ impl MemoryMappedFileBuilder {
                     written_since_last_flush: RwLock::new(0),
                     #[cfg(feature = "hugepages")]
                     huge_pages: false,
+                    #[cfg(unix)]
+                    reservation: None,
+                    growth: None,
+                    window_offset: window.map(|(offset, _)| offset),
+                    truncated: AtomicBool::new(false),
                 };
                 Ok(MemoryMappedFile {
                     inner: Arc::new(inner),
@@ -1298,12 +2273,37 @@ impl MemoryMappedFileBuilder {
 }
 
 // Move this to the top-level with other use statements:
-use parking_lot::RwLockWriteGuard;
+use parking_lot::{RwLockReadGuard, RwLockWriteGuard};
+
+/// Read-only view of a mapped range returned by [`MemoryMappedFile::as_slice_guarded`].
+/// For read-write mappings it holds the read lock for its lifetime.
+pub struct MappedSlice<'a> {
+    inner: MappedSliceInner<'a>,
+}
+
+enum MappedSliceInner<'a> {
+    Borrowed(&'a [u8]),
+    Guarded {
+        guard: RwLockReadGuard<'a, RwMap>,
+        range: std::ops::Range<usize>,
+    },
+}
+
+impl std::ops::Deref for MappedSlice<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match &self.inner {
+            MappedSliceInner::Borrowed(slice) => slice,
+            MappedSliceInner::Guarded { guard, range } => &guard[range.clone()],
+        }
+    }
+}
 
 /// Wrapper for a mutable slice that holds a write lock guard,
 /// ensuring exclusive access for the lifetime of the slice.
 pub struct MappedSliceMut<'a> {
-    guard: RwLockWriteGuard<'a, MmapMut>,
+    guard: RwLockWriteGuard<'a, RwMap>,
     range: std::ops::Range<usize>,
 }
 
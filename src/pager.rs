@@ -0,0 +1,628 @@
+//! Fixed-size page manager on top of a memory-mapped file.
+//!
+//! Page 0 holds the pager header; pages `1..` are handed out by [`Pager::allocate_page`].
+//! Freed pages form a singly linked list threaded through the pages themselves, with the head
+//! stored in the header, so the free list survives reopening. Opening walks the list once to
+//! learn which pages are free; page contents are never used to tell. The file grows in extents
+//! of several pages at a time through [`MemoryMappedFile::resize`].
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+
+use crate::errors::{MmapIoError, Result};
+use crate::mmap::{MemoryMappedFile, MmapMode};
+use crate::pod::read_pod;
+use crate::segment::{Segment, SegmentMut};
+
+/// Page number within a [`Pager`]. Page 0 is the header and is never handed out.
+pub type PageId = u64;
+
+const MAGIC: [u8; 8] = *b"MMAPPAGR";
+const VERSION: u32 = 1;
+// Header layout: magic (8) | version (4) | page size (4) | used pages (8) | free head (8)
+//                | free count (8) | extent pages (8) | rest of page 0 reserved
+const HEADER_LEN: u64 = 48;
+const USED_OFFSET: u64 = 16;
+const MIN_PAGE_SIZE: u64 = 512;
+const MAX_PAGE_SIZE: u64 = 1 << 20;
+/// Default number of pages added each time the file grows.
+pub const DEFAULT_EXTENT_PAGES: u64 = 64;
+
+/// Fixed-size page manager.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mmap_io::pager::Pager;
+///
+/// let mut pager = Pager::create("btree.db", 4096)?;
+/// let root = pager.allocate_page()?;
+/// pager.page_mut(root)?.write(b"root node")?;
+///
+/// let scratch = pager.allocate_page()?;
+/// pager.free_page(scratch)?;
+/// pager.flush()?;
+/// # Ok::<(), mmap_io::MmapIoError>(())
+/// ```
+#[derive(Debug)]
+pub struct Pager {
+    mmap: Arc<MemoryMappedFile>,
+    page_size: u64,
+    state: State,
+    /// Ids on the persisted free list.
+    free: HashSet<PageId>,
+    pins: Arc<Mutex<HashMap<PageId, usize>>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct State {
+    /// Pages in use or on the free list, including the header page.
+    used: u64,
+    free_head: PageId,
+    free_count: u64,
+    extent_pages: u64,
+}
+
+impl Pager {
+    /// Create a new pager file (truncating if it exists) with the given page size.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidMode` if `page_size` is not a power of two between
+    /// 512 bytes and 1 MiB.
+    /// Returns errors from `MemoryMappedFile::create_rw`.
+    pub fn create<P: AsRef<Path>>(path: P, page_size: u64) -> Result<Self> {
+        Self::create_with_extent(path, page_size, DEFAULT_EXTENT_PAGES)
+    }
+
+    /// Like [`Pager::create`], growing the file by `extent_pages` pages at a time.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Pager::create`]; `extent_pages` must be non-zero.
+    pub fn create_with_extent<P: AsRef<Path>>(
+        path: P,
+        page_size: u64,
+        extent_pages: u64,
+    ) -> Result<Self> {
+        if !page_size.is_power_of_two() || !(MIN_PAGE_SIZE..=MAX_PAGE_SIZE).contains(&page_size) {
+            return Err(MmapIoError::InvalidMode(
+                "page size must be a power of two between 512 bytes and 1 MiB",
+            ));
+        }
+        if extent_pages == 0 {
+            return Err(MmapIoError::InvalidMode("extent_pages must be non-zero"));
+        }
+        let mmap = MemoryMappedFile::create_rw(path, page_size)?;
+        let mut header = [0u8; 16];
+        header[..8].copy_from_slice(&MAGIC);
+        header[8..12].copy_from_slice(&VERSION.to_le_bytes());
+        header[12..16].copy_from_slice(&(page_size as u32).to_le_bytes());
+        mmap.update_region(0, &header)?;
+        let pager = Self {
+            mmap: Arc::new(mmap),
+            page_size,
+            state: State {
+                used: 1,
+                free_head: 0,
+                free_count: 0,
+                extent_pages,
+            },
+            free: HashSet::new(),
+            pins: Arc::default(),
+        };
+        pager.write_state()?;
+        Ok(pager)
+    }
+
+    /// Open an existing pager file read-write.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidFormat` if the file has no valid pager header.
+    /// Returns errors from `MemoryMappedFile::open_rw`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_mmap(MemoryMappedFile::open_rw(path)?)
+    }
+
+    /// Open a pager previously stored in `mmap`. Read-only mappings allow `page` only.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidFormat` if the header is missing or inconsistent with the
+    /// mapping's length, or the free list is corrupt.
+    pub fn from_mmap(mmap: MemoryMappedFile) -> Result<Self> {
+        let total = mmap.current_len()?;
+        if total < HEADER_LEN
+            || read_pod::<[u8; 8]>(&mmap, 0)? != MAGIC
+            || u32::from_le(read_pod(&mmap, 8)?) != VERSION
+        {
+            return Err(MmapIoError::InvalidFormat(
+                "missing or unsupported Pager header".into(),
+            ));
+        }
+        let page_size = u64::from(u32::from_le(read_pod(&mmap, 12)?));
+        let [used, free_head, free_count, extent_pages]: [u64; 4] =
+            read_pod::<[u64; 4]>(&mmap, USED_OFFSET)?.map(u64::from_le);
+        if !page_size.is_power_of_two()
+            || !(MIN_PAGE_SIZE..=MAX_PAGE_SIZE).contains(&page_size)
+            || used == 0
+            || used.saturating_mul(page_size) > total
+            || free_head >= used
+            || free_count >= used
+            || extent_pages == 0
+        {
+            return Err(MmapIoError::InvalidFormat(format!(
+                "Pager header inconsistent with file length {total}"
+            )));
+        }
+        // Every id on the list lies below `used`, so its link is inside the mapping.
+        let mut free = HashSet::new();
+        let mut id = free_head;
+        while id != 0 {
+            if id >= used || free.len() as u64 == free_count || !free.insert(id) {
+                return Err(MmapIoError::InvalidFormat(format!(
+                    "Pager free list is corrupt at page {id}"
+                )));
+            }
+            id = u64::from_le(read_pod(&mmap, id * page_size)?);
+        }
+        if free.len() as u64 != free_count {
+            return Err(MmapIoError::InvalidFormat(format!(
+                "Pager free list holds {} pages, header says {free_count}",
+                free.len()
+            )));
+        }
+        Ok(Self {
+            mmap: Arc::new(mmap),
+            page_size,
+            state: State {
+                used,
+                free_head,
+                free_count,
+                extent_pages,
+            },
+            free,
+            pins: Arc::default(),
+        })
+    }
+
+    /// Page size in bytes.
+    #[must_use]
+    pub fn page_size(&self) -> u64 {
+        self.page_size
+    }
+
+    /// Number of pages handed out so far, including the header page and freed pages.
+    #[must_use]
+    pub fn page_count(&self) -> u64 {
+        self.state.used
+    }
+
+    /// Number of pages on the free list.
+    #[must_use]
+    pub fn free_count(&self) -> u64 {
+        self.state.free_count
+    }
+
+    /// Shared handle to the underlying mapping.
+    #[must_use]
+    pub fn mmap(&self) -> &Arc<MemoryMappedFile> {
+        &self.mmap
+    }
+
+    /// Allocate a zeroed page, reusing freed pages before growing the file.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidMode` for read-only pagers.
+    /// Returns errors from `MemoryMappedFile::resize` or `update_region`.
+    pub fn allocate_page(&mut self) -> Result<PageId> {
+        self.check_writable()?;
+        let id = if self.state.free_head != 0 {
+            let id = self.state.free_head;
+            let next = u64::from_le(read_pod(&self.mmap, self.offset(id))?);
+            self.state.free_head = next;
+            self.state.free_count -= 1;
+            self.free.remove(&id);
+            id
+        } else {
+            let id = self.state.used;
+            let total = self.mmap.current_len()?;
+            if (id + 1) * self.page_size > total {
+                let extent = self.state.extent_pages * self.page_size;
+                self.mmap.resize(total.max(id * self.page_size) + extent)?;
+            }
+            self.state.used += 1;
+            id
+        };
+        self.mmap
+            .update_region(self.offset(id), &vec![0u8; self.page_size as usize])?;
+        self.write_state()?;
+        Ok(id)
+    }
+
+    /// Return a page to the free list.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidMode` if `id` is the header page, was never allocated,
+    /// is already free or is currently pinned.
+    pub fn free_page(&mut self, id: PageId) -> Result<()> {
+        self.check_writable()?;
+        if self.free.contains(&id) {
+            return Err(MmapIoError::InvalidMode("page is already free"));
+        }
+        self.check_id(id)?;
+        if self.pins.lock().contains_key(&id) {
+            return Err(MmapIoError::InvalidMode("cannot free a pinned page"));
+        }
+        self.mmap
+            .update_region(self.offset(id), &self.state.free_head.to_le_bytes())?;
+        self.state.free_head = id;
+        self.state.free_count += 1;
+        self.free.insert(id);
+        self.write_state()
+    }
+
+    /// Whether page `id` is on the free list.
+    #[must_use]
+    pub fn is_free(&self, id: PageId) -> bool {
+        self.free.contains(&id)
+    }
+
+    /// Read-only view of page `id`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidMode` if `id` is the header page, was never allocated or
+    /// is free.
+    pub fn page(&self, id: PageId) -> Result<Segment> {
+        self.check_id(id)?;
+        Segment::new(Arc::clone(&self.mmap), self.offset(id), self.page_size)
+    }
+
+    /// Writable view of page `id`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidMode` if `id` is the header page, was never allocated or
+    /// is free.
+    pub fn page_mut(&self, id: PageId) -> Result<SegmentMut> {
+        self.check_id(id)?;
+        SegmentMut::new(Arc::clone(&self.mmap), self.offset(id), self.page_size)
+    }
+
+    /// Pin page `id`: fault it in and prevent it from being freed until every pin for it
+    /// is dropped. Pins are reference counted.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidMode` if `id` is the header page, was never allocated or
+    /// is free.
+    pub fn pin(&self, id: PageId) -> Result<PinnedPage> {
+        self.check_id(id)?;
+        self.mmap
+            .touch_pages_range(self.offset(id), self.page_size)?;
+        *self.pins.lock().entry(id).or_insert(0) += 1;
+        Ok(PinnedPage {
+            id,
+            mmap: Arc::clone(&self.mmap),
+            offset: self.offset(id),
+            len: self.page_size,
+            pins: Arc::clone(&self.pins),
+        })
+    }
+
+    /// Whether page `id` currently has at least one pin.
+    #[must_use]
+    pub fn is_pinned(&self, id: PageId) -> bool {
+        self.pins.lock().contains_key(&id)
+    }
+
+    /// Flush all pages and the header to disk.
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from `MemoryMappedFile::flush`.
+    pub fn flush(&self) -> Result<()> {
+        self.mmap.flush()
+    }
+
+    /// Flush a single page to disk.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidMode` if `id` is not an allocated page.
+    /// Returns errors from `MemoryMappedFile::flush_range`.
+    pub fn flush_page(&self, id: PageId) -> Result<()> {
+        self.check_id(id)?;
+        self.mmap.flush_range(self.offset(id), self.page_size)
+    }
+
+    fn offset(&self, id: PageId) -> u64 {
+        id * self.page_size
+    }
+
+    fn check_id(&self, id: PageId) -> Result<()> {
+        if id == 0 || id >= self.state.used {
+            return Err(MmapIoError::InvalidMode("page id was never allocated"));
+        }
+        if self.free.contains(&id) {
+            return Err(MmapIoError::InvalidMode("page is free"));
+        }
+        Ok(())
+    }
+
+    fn check_writable(&self) -> Result<()> {
+        if self.mmap.mode() == MmapMode::ReadWrite {
+            Ok(())
+        } else {
+            Err(MmapIoError::InvalidMode(
+                "Pager allocation requires ReadWrite mode",
+            ))
+        }
+    }
+
+    fn write_state(&self) -> Result<()> {
+        let s = self.state;
+        let mut fields = [0u8; 32];
+        for (i, v) in [s.used, s.free_head, s.free_count, s.extent_pages]
+            .iter()
+            .enumerate()
+        {
+            fields[i * 8..i * 8 + 8].copy_from_slice(&v.to_le_bytes());
+        }
+        self.mmap.update_region(USED_OFFSET, &fields)
+    }
+}
+
+/// A pinned page, see [`Pager::pin`]. Unpins when dropped.
+#[derive(Debug)]
+pub struct PinnedPage {
+    id: PageId,
+    mmap: Arc<MemoryMappedFile>,
+    offset: u64,
+    len: u64,
+    pins: Arc<Mutex<HashMap<PageId, usize>>>,
+}
+
+impl PinnedPage {
+    /// Id of the pinned page.
+    #[must_use]
+    pub fn id(&self) -> PageId {
+        self.id
+    }
+
+    /// Read-only view of the page.
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from `Segment::new`.
+    pub fn segment(&self) -> Result<Segment> {
+        Segment::new(Arc::clone(&self.mmap), self.offset, self.len)
+    }
+
+    /// Writable view of the page.
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from `SegmentMut::new`.
+    pub fn segment_mut(&self) -> Result<SegmentMut> {
+        SegmentMut::new(Arc::clone(&self.mmap), self.offset, self.len)
+    }
+}
+
+impl Drop for PinnedPage {
+    fn drop(&mut self) {
+        let mut pins = self.pins.lock();
+        if let Some(count) = pins.get_mut(&self.id) {
+            *count -= 1;
+            if *count == 0 {
+                pins.remove(&self.id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn tmp_path(name: &str) -> PathBuf {
+        let mut p = std::env::temp_dir();
+        p.push(format!(
+            "mmap_io_pager_test_{}_{}",
+            name,
+            std::process::id()
+        ));
+        p
+    }
+
+    #[test]
+    fn test_allocate_free_and_reuse() {
+        let path = tmp_path("reuse");
+        let _ = fs::remove_file(&path);
+
+        let mut pager = Pager::create_with_extent(&path, 4096, 4).expect("create");
+        let ids: Vec<_> = (0..10)
+            .map(|_| pager.allocate_page().expect("alloc"))
+            .collect();
+        assert_eq!(ids, (1..=10).collect::<Vec<_>>());
+        // Grown in extents of 4 pages
+        assert_eq!(pager.mmap().len() % (4 * 4096), 4096);
+
+        pager
+            .page_mut(3)
+            .expect("page")
+            .write(b"dirty")
+            .expect("write");
+        pager.free_page(3).expect("free");
+        pager.free_page(7).expect("free");
+        assert_eq!(pager.free_count(), 2);
+        assert!(matches!(
+            pager.free_page(3),
+            Err(MmapIoError::InvalidMode(_))
+        ));
+        assert!(pager.free_page(0).is_err());
+        assert!(pager.free_page(11).is_err());
+
+        // LIFO reuse, and reused pages come back zeroed
+        assert_eq!(pager.allocate_page().expect("alloc"), 7);
+        assert_eq!(pager.allocate_page().expect("alloc"), 3);
+        let mut buf = [0xFFu8; 16];
+        pager.mmap().read_into(3 * 4096, &mut buf).expect("read");
+        assert_eq!(buf, [0u8; 16]);
+        assert_eq!(pager.allocate_page().expect("alloc"), 11);
+
+        fs::remove_file(&path).expect("cleanup");
+    }
+
+    #[test]
+    fn test_reopen_keeps_pages_and_free_list() {
+        let path = tmp_path("reopen");
+        let _ = fs::remove_file(&path);
+
+        let mut pager = Pager::create(&path, 16 * 1024).expect("create");
+        for i in 1..=5u8 {
+            let id = pager.allocate_page().expect("alloc");
+            pager
+                .page_mut(id)
+                .expect("page")
+                .write(&[i; 8])
+                .expect("write");
+        }
+        pager.free_page(2).expect("free");
+        pager.flush().expect("flush");
+        drop(pager);
+
+        let mut pager = Pager::open(&path).expect("reopen");
+        assert_eq!(pager.page_size(), 16 * 1024);
+        assert_eq!(pager.page_count(), 6);
+        assert_eq!(pager.free_count(), 1);
+        let mut buf = [0u8; 8];
+        pager
+            .mmap()
+            .read_into(pager.page(4).expect("page").offset(), &mut buf)
+            .expect("read");
+        assert_eq!(buf, [4u8; 8]);
+        assert_eq!(pager.allocate_page().expect("alloc"), 2);
+        drop(pager);
+
+        let ro = Pager::from_mmap(MemoryMappedFile::open_ro(&path).expect("ro")).expect("ro");
+        assert_eq!(
+            ro.page(5).expect("page").as_slice().expect("slice")[..8],
+            [5u8; 8]
+        );
+
+        fs::remove_file(&path).expect("cleanup");
+    }
+
+    #[test]
+    fn test_free_pages_are_tracked_outside_page_contents() {
+        let path = tmp_path("tracking");
+        let _ = fs::remove_file(&path);
+
+        let mut pager = Pager::create(&path, 4096).expect("create");
+        let a = pager.allocate_page().expect("alloc");
+        let b = pager.allocate_page().expect("alloc");
+        // Data that looks like the old in-page free marker must not matter
+        pager
+            .page_mut(a)
+            .expect("page")
+            .write(b"\0\0\0\0\0\0\0\0FREEPAGE")
+            .expect("write");
+        pager.free_page(a).expect("free live page");
+        assert!(pager.is_free(a) && !pager.is_free(b));
+
+        assert!(pager.page(a).is_err());
+        assert!(pager.page_mut(a).is_err());
+        assert!(pager.pin(a).is_err());
+        assert!(pager.flush_page(a).is_err());
+        pager.flush().expect("flush");
+        drop(pager);
+
+        let mut pager = Pager::open(&path).expect("reopen");
+        assert!(pager.is_free(a));
+        assert!(pager.page_mut(a).is_err());
+        assert!(matches!(
+            pager.free_page(a),
+            Err(MmapIoError::InvalidMode("page is already free"))
+        ));
+        assert_eq!(pager.allocate_page().expect("alloc"), a);
+        assert!(!pager.is_free(a));
+        pager.page_mut(a).expect("page after reuse");
+
+        fs::remove_file(&path).expect("cleanup");
+    }
+
+    #[test]
+    fn test_open_rejects_corrupt_free_list() {
+        let path = tmp_path("corrupt_free");
+        let _ = fs::remove_file(&path);
+
+        let mut pager = Pager::create(&path, 4096).expect("create");
+        let a = pager.allocate_page().expect("alloc");
+        let b = pager.allocate_page().expect("alloc");
+        pager.free_page(a).expect("free");
+        pager.free_page(b).expect("free");
+        // Link page `a` back to `b`, making a cycle
+        pager
+            .mmap()
+            .update_region(a * 4096, &b.to_le_bytes())
+            .expect("corrupt");
+        drop(pager);
+        assert!(matches!(
+            Pager::open(&path),
+            Err(MmapIoError::InvalidFormat(_))
+        ));
+
+        fs::remove_file(&path).expect("cleanup");
+    }
+
+    #[test]
+    fn test_pinned_pages_cannot_be_freed() {
+        let path = tmp_path("pins");
+        let _ = fs::remove_file(&path);
+
+        let mut pager = Pager::create(&path, 4096).expect("create");
+        let id = pager.allocate_page().expect("alloc");
+        let pin = pager.pin(id).expect("pin");
+        let second = pager.pin(id).expect("pin again");
+        pin.segment_mut()
+            .expect("segment")
+            .write(b"pinned")
+            .expect("write");
+        assert!(pager.is_pinned(id));
+        assert!(pager.free_page(id).is_err());
+
+        drop(pin);
+        assert!(pager.free_page(id).is_err(), "still pinned once");
+        drop(second);
+        assert!(!pager.is_pinned(id));
+        pager.free_page(id).expect("free after unpin");
+
+        fs::remove_file(&path).expect("cleanup");
+    }
+
+    #[test]
+    fn test_invalid_page_sizes_and_files() {
+        let path = tmp_path("invalid");
+        let _ = fs::remove_file(&path);
+
+        assert!(Pager::create(&path, 1000).is_err());
+        assert!(Pager::create(&path, 256).is_err());
+        crate::create_mmap(&path, 4096).expect("create");
+        assert!(matches!(
+            Pager::open(&path),
+            Err(MmapIoError::InvalidFormat(_))
+        ));
+
+        fs::remove_file(&path).expect("cleanup");
+    }
+}
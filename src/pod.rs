@@ -0,0 +1,71 @@
+//! Plain-old-data marker for types that can be stored directly in mapped memory.
+
+// The byte helpers are only used by feature-gated containers.
+#![cfg_attr(not(feature = "vec"), allow(dead_code))]
+
+use crate::errors::Result;
+use crate::mmap::MemoryMappedFile;
+
+/// Types that can be copied to and from mapped bytes without conversion.
+///
+/// # Safety
+///
+/// Implementors must be `Copy`, contain no padding bytes, no pointers or references, and
+/// accept every bit pattern as a valid value. `#[repr(C)]` structs made only of `Pod` fields
+/// with no padding between them satisfy this.
+pub unsafe trait Pod: Copy + Send + Sync + 'static {}
+
+macro_rules! impl_pod {
+    ($($t:ty),* $(,)?) => {
+        $(unsafe impl Pod for $t {})*
+    };
+}
+
+impl_pod!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
+
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+
+/// View a value as its raw bytes.
+pub(crate) fn bytes_of<T: Pod>(value: &T) -> &[u8] {
+    // SAFETY: T is Pod, so every byte of the value is initialized.
+    unsafe {
+        std::slice::from_raw_parts((value as *const T).cast::<u8>(), std::mem::size_of::<T>())
+    }
+}
+
+/// View a slice of values as its raw bytes.
+pub(crate) fn bytes_of_slice<T: Pod>(values: &[T]) -> &[u8] {
+    // SAFETY: T is Pod, so every byte of the slice is initialized.
+    unsafe {
+        std::slice::from_raw_parts(values.as_ptr().cast::<u8>(), std::mem::size_of_val(values))
+    }
+}
+
+/// Copy a value out of `bytes`, which must be exactly `size_of::<T>()` long (no alignment
+/// requirement). Crate-internal; callers only pass whole records.
+///
+/// # Panics
+///
+/// Panics if `bytes.len() != size_of::<T>()`.
+#[cfg_attr(not(feature = "extsort"), allow(dead_code))]
+pub(crate) fn pod_from_bytes<T: Pod>(bytes: &[u8]) -> T {
+    assert_eq!(
+        bytes.len(),
+        std::mem::size_of::<T>(),
+        "wrong length for Pod value"
+    );
+    // SAFETY: The length matches and every bit pattern is a valid T.
+    unsafe { std::ptr::read_unaligned(bytes.as_ptr().cast::<T>()) }
+}
+
+/// Copy a value out of the mapping at `offset` (no alignment requirement).
+pub(crate) fn read_pod<T: Pod>(mmap: &MemoryMappedFile, offset: u64) -> Result<T> {
+    let mut value = std::mem::MaybeUninit::<T>::zeroed();
+    // SAFETY: The zeroed buffer is fully initialized and exactly size_of::<T>() bytes long.
+    let bytes = unsafe {
+        std::slice::from_raw_parts_mut(value.as_mut_ptr().cast::<u8>(), std::mem::size_of::<T>())
+    };
+    mmap.read_into(offset, bytes)?;
+    // SAFETY: Every bit pattern is a valid T.
+    Ok(unsafe { value.assume_init() })
+}
@@ -0,0 +1,125 @@
+//! Disk block preallocation for read-write mappings.
+//!
+//! `set_len` only changes the file size, leaving a sparse file. On a full disk the first
+//! write to an unallocated page then raises `SIGBUS` instead of returning an error.
+//! Preallocating reserves the blocks up front, so running out of space is reported as
+//! `ENOSPC` when the file is created or resized.
+
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+use crate::errors::{MmapIoError, Result};
+
+/// Allocate disk blocks for `[from, to)` of `file`, extending it to at least `to` bytes.
+///
+/// Checks the free space first, so an obviously impossible request fails before anything
+/// is allocated.
+pub(crate) fn preallocate(file: &File, path: &Path, from: u64, to: u64) -> Result<()> {
+    if to <= from {
+        return Ok(());
+    }
+    // Blocks already allocated inside the range are counted as needed too; the check
+    // only has to catch requests that cannot possibly fit.
+    if let Ok(available) = crate::utils::file_available_space(file, path) {
+        if to - from > available {
+            return Err(MmapIoError::Io(no_space()));
+        }
+    }
+    allocate(file, from, to - from)?;
+    if file.metadata()?.len() < to {
+        file.set_len(to)?;
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn no_space() -> io::Error {
+    io::Error::from_raw_os_error(libc::ENOSPC)
+}
+
+#[cfg(windows)]
+fn no_space() -> io::Error {
+    // ERROR_DISK_FULL
+    io::Error::from_raw_os_error(112)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn no_space() -> io::Error {
+    io::Error::new(io::ErrorKind::Other, "not enough free space")
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn allocate(file: &File, offset: u64, len: u64) -> Result<()> {
+    use std::os::unix::io::AsRawFd;
+    let fd = file.as_raw_fd();
+    // SAFETY: plain syscall on an open descriptor.
+    if unsafe { libc::fallocate(fd, 0, offset as libc::off_t, len as libc::off_t) } == 0 {
+        return Ok(());
+    }
+    let err = io::Error::last_os_error();
+    if err.raw_os_error() != Some(libc::EOPNOTSUPP) {
+        return Err(MmapIoError::Io(err));
+    }
+    // The filesystem has no native fallocate; glibc emulates it by writing zeros.
+    posix_fallocate(fd, offset, len)
+}
+
+#[cfg(all(
+    unix,
+    not(any(target_os = "linux", target_os = "android", target_vendor = "apple"))
+))]
+fn allocate(file: &File, offset: u64, len: u64) -> Result<()> {
+    use std::os::unix::io::AsRawFd;
+    posix_fallocate(file.as_raw_fd(), offset, len)
+}
+
+#[cfg(all(unix, not(target_vendor = "apple")))]
+fn posix_fallocate(fd: libc::c_int, offset: u64, len: u64) -> Result<()> {
+    // SAFETY: plain call on an open descriptor; it returns the error instead of setting errno.
+    match unsafe { libc::posix_fallocate(fd, offset as libc::off_t, len as libc::off_t) } {
+        0 => Ok(()),
+        err => Err(MmapIoError::Io(io::Error::from_raw_os_error(err))),
+    }
+}
+
+#[cfg(target_vendor = "apple")]
+fn allocate(file: &File, offset: u64, len: u64) -> Result<()> {
+    use std::os::unix::io::AsRawFd;
+    let end = offset + len;
+    let size = file.metadata()?.len();
+    if end <= size {
+        // F_PREALLOCATE only extends past the end of the file; blocks inside it were
+        // allocated when it was written or extended here.
+        return Ok(());
+    }
+    let mut store = libc::fstore_t {
+        fst_flags: libc::F_ALLOCATECONTIG,
+        fst_posmode: libc::F_PEOFPOSMODE,
+        fst_offset: 0,
+        fst_length: (end - size) as libc::off_t,
+        fst_bytesalloc: 0,
+    };
+    let fd = file.as_raw_fd();
+    // SAFETY: `store` is a valid fstore_t for F_PREALLOCATE. Contiguous allocation is tried
+    // first, then any allocation.
+    unsafe {
+        if libc::fcntl(fd, libc::F_PREALLOCATE, &mut store) == -1 {
+            store.fst_flags = libc::F_ALLOCATEALL;
+            if libc::fcntl(fd, libc::F_PREALLOCATE, &mut store) == -1 {
+                return Err(MmapIoError::Io(io::Error::last_os_error()));
+            }
+        }
+    }
+    Ok(())
+}
+
+// NTFS allocates the blocks of a non-sparse file when it is extended.
+#[cfg(not(unix))]
+fn allocate(file: &File, offset: u64, len: u64) -> Result<()> {
+    let end = offset + len;
+    if file.metadata()?.len() < end {
+        file.set_len(end)?;
+    }
+    Ok(())
+}
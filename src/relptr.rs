@@ -0,0 +1,460 @@
+//! Position-independent pointers for structures stored inside memory-mapped files.
+//!
+//! Raw pointers into a mapping are only valid in one process and only until the next remap.
+//! [`OffsetPtr`] stores an offset from the start of the mapping instead, and [`RelPtr`] stores
+//! a distance from its own location, so whole blocks can be moved or copied without fixups.
+//! Both are [`Pod`] and can be embedded in mapped structs. Every dereference checks for null,
+//! alignment and bounds.
+
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+
+use crate::errors::{MmapIoError, Result};
+use crate::mmap::{MappedSlice, MemoryMappedFile};
+use crate::pod::{bytes_of, read_pod, Pod};
+
+/// Pointer to a `T` stored as a byte offset from the start of the mapping. Offset 0 is null.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mmap_io::relptr::OffsetPtr;
+/// use mmap_io::{MemoryMappedFile, Pod};
+///
+/// #[derive(Clone, Copy)]
+/// #[repr(C)]
+/// struct Node {
+///     value: u64,
+///     next: OffsetPtr<Node>,
+/// }
+/// // SAFETY: repr(C), two 8-byte Pod fields, no padding.
+/// unsafe impl Pod for Node {}
+///
+/// let mmap = MemoryMappedFile::create_rw("list.bin", 4096)?;
+/// let tail = OffsetPtr::<Node>::new(128);
+/// let head = OffsetPtr::<Node>::new(64);
+/// tail.write(&mmap, &Node { value: 2, next: OffsetPtr::null() })?;
+/// head.write(&mmap, &Node { value: 1, next: tail })?;
+///
+/// let mut sum = 0;
+/// let mut cur = head;
+/// while !cur.is_null() {
+///     let node = cur.read(&mmap)?;
+///     sum += node.value;
+///     cur = node.next;
+/// }
+/// assert_eq!(sum, 3);
+/// # Ok::<(), mmap_io::MmapIoError>(())
+/// ```
+#[repr(transparent)]
+pub struct OffsetPtr<T> {
+    offset: u64,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> OffsetPtr<T> {
+    /// The null pointer.
+    #[must_use]
+    pub const fn null() -> Self {
+        Self::new(0)
+    }
+
+    /// Pointer to the value at `offset`.
+    #[must_use]
+    pub const fn new(offset: u64) -> Self {
+        Self {
+            offset,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Whether this is the null pointer.
+    #[must_use]
+    pub const fn is_null(self) -> bool {
+        self.offset == 0
+    }
+
+    /// Byte offset from the start of the mapping.
+    #[must_use]
+    pub const fn offset(self) -> u64 {
+        self.offset
+    }
+
+    /// Pointer to the `count`-th `T` after this one, or `None` on overflow.
+    #[must_use]
+    pub fn checked_add(self, count: u64) -> Option<Self> {
+        (std::mem::size_of::<T>() as u64)
+            .checked_mul(count)
+            .and_then(|delta| self.offset.checked_add(delta))
+            .map(Self::new)
+    }
+
+    /// Reinterpret as a pointer to another type at the same offset.
+    #[must_use]
+    pub const fn cast<U>(self) -> OffsetPtr<U> {
+        OffsetPtr::new(self.offset)
+    }
+}
+
+impl<T: Pod> OffsetPtr<T> {
+    /// Copy the value out of the mapping.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidMode` for null pointers.
+    /// Returns `MmapIoError::Misaligned` if the offset is not aligned for `T`.
+    /// Returns `MmapIoError::OutOfBounds` if the value extends past the end of the mapping.
+    pub fn read(self, mmap: &MemoryMappedFile) -> Result<T> {
+        self.check()?;
+        read_pod(mmap, self.offset)
+    }
+
+    /// Store `value` in the mapping.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`OffsetPtr::read`], plus `MmapIoError::InvalidMode` for read-only mappings.
+    pub fn write(self, mmap: &MemoryMappedFile, value: &T) -> Result<()> {
+        self.check()?;
+        mmap.update_region(self.offset, bytes_of(value))
+    }
+
+    /// Zero-copy reference to the value. For read-write mappings the reference holds the
+    /// mapping's read lock until dropped.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`OffsetPtr::read`].
+    pub fn get(self, mmap: &MemoryMappedFile) -> Result<MappedRef<'_, T>> {
+        self.check()?;
+        let bytes = mmap.as_slice_guarded(self.offset, std::mem::size_of::<T>() as u64)?;
+        let required = std::mem::align_of::<T>();
+        if bytes.as_ptr() as usize % required != 0 {
+            return Err(MmapIoError::Misaligned {
+                required: required as u64,
+                offset: self.offset,
+            });
+        }
+        Ok(MappedRef {
+            bytes,
+            _marker: PhantomData,
+        })
+    }
+
+    fn check(self) -> Result<()> {
+        if self.is_null() {
+            return Err(MmapIoError::InvalidMode("null OffsetPtr dereference"));
+        }
+        let required = std::mem::align_of::<T>() as u64;
+        if self.offset % required != 0 {
+            return Err(MmapIoError::Misaligned {
+                required,
+                offset: self.offset,
+            });
+        }
+        Ok(())
+    }
+}
+
+// Manual impls so that `T` itself needs no bounds.
+impl<T> Clone for OffsetPtr<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for OffsetPtr<T> {}
+
+impl<T> PartialEq for OffsetPtr<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.offset == other.offset
+    }
+}
+
+impl<T> Eq for OffsetPtr<T> {}
+
+impl<T> Hash for OffsetPtr<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.offset.hash(state);
+    }
+}
+
+impl<T> Default for OffsetPtr<T> {
+    fn default() -> Self {
+        Self::null()
+    }
+}
+
+impl<T> fmt::Debug for OffsetPtr<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "OffsetPtr({:#x})", self.offset)
+    }
+}
+
+// SAFETY: a single u64 with no padding; every bit pattern is a valid offset.
+unsafe impl<T: 'static> Pod for OffsetPtr<T> {}
+
+/// Self-relative pointer: stores the distance from its own location to the target.
+///
+/// Because the distance does not depend on where the structure sits in the file, a block
+/// containing `RelPtr`s that point into the same block can be copied anywhere. The location
+/// of the pointer itself must be supplied when resolving. Distance 0 is null, so a `RelPtr`
+/// cannot point at itself.
+#[repr(transparent)]
+pub struct RelPtr<T> {
+    delta: i64,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> RelPtr<T> {
+    /// The null pointer.
+    #[must_use]
+    pub const fn null() -> Self {
+        Self {
+            delta: 0,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Pointer stored at offset `at` that refers to offset `target`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidMode` if `target == at` or the distance does not fit
+    /// in an `i64`.
+    pub fn between(at: u64, target: u64) -> Result<Self> {
+        let delta = i64::try_from(i128::from(target) - i128::from(at))
+            .map_err(|_| MmapIoError::InvalidMode("RelPtr distance does not fit in i64"))?;
+        if delta == 0 {
+            return Err(MmapIoError::InvalidMode("RelPtr cannot point at itself"));
+        }
+        Ok(Self {
+            delta,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Whether this is the null pointer.
+    #[must_use]
+    pub const fn is_null(self) -> bool {
+        self.delta == 0
+    }
+
+    /// Signed distance in bytes from the pointer's location to its target.
+    #[must_use]
+    pub const fn delta(self) -> i64 {
+        self.delta
+    }
+
+    /// Absolute pointer for a `RelPtr` stored at offset `at`. Null stays null.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::OutOfBounds` if the target would lie outside `0..=u64::MAX`.
+    pub fn resolve(self, at: u64) -> Result<OffsetPtr<T>> {
+        if self.is_null() {
+            return Ok(OffsetPtr::null());
+        }
+        at.checked_add_signed(self.delta)
+            .map(OffsetPtr::new)
+            .ok_or(MmapIoError::OutOfBounds {
+                offset: at,
+                len: self.delta.unsigned_abs(),
+                total: u64::MAX,
+            })
+    }
+}
+
+impl<T> Clone for RelPtr<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for RelPtr<T> {}
+
+impl<T> PartialEq for RelPtr<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.delta == other.delta
+    }
+}
+
+impl<T> Eq for RelPtr<T> {}
+
+impl<T> Default for RelPtr<T> {
+    fn default() -> Self {
+        Self::null()
+    }
+}
+
+impl<T> fmt::Debug for RelPtr<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "RelPtr({:+})", self.delta)
+    }
+}
+
+// SAFETY: a single i64 with no padding; every bit pattern is a valid distance.
+unsafe impl<T: 'static> Pod for RelPtr<T> {}
+
+/// Zero-copy typed reference into a mapping, returned by [`OffsetPtr::get`].
+pub struct MappedRef<'a, T: Pod> {
+    bytes: MappedSlice<'a>,
+    _marker: PhantomData<&'a T>,
+}
+
+impl<T: Pod> std::ops::Deref for MappedRef<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: `get` checked that the slice is exactly size_of::<T>() bytes and aligned,
+        // and every bit pattern is a valid T.
+        unsafe { &*self.bytes.as_ptr().cast::<T>() }
+    }
+}
+
+impl<T: Pod + fmt::Debug> fmt::Debug for MappedRef<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn tmp_path(name: &str) -> PathBuf {
+        let mut p = std::env::temp_dir();
+        p.push(format!(
+            "mmap_io_relptr_test_{}_{}",
+            name,
+            std::process::id()
+        ));
+        p
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    #[repr(C)]
+    struct Node {
+        value: u64,
+        next: OffsetPtr<Node>,
+        sibling: RelPtr<Node>,
+    }
+
+    // SAFETY: repr(C), three 8-byte Pod fields, no padding.
+    unsafe impl Pod for Node {}
+
+    const NODE: u64 = std::mem::size_of::<Node>() as u64;
+
+    #[test]
+    fn test_linked_list_survives_reopen() {
+        let path = tmp_path("list");
+        let _ = fs::remove_file(&path);
+
+        let mmap = MemoryMappedFile::create_rw(&path, 4096).expect("create");
+        let nodes: Vec<_> = (1..=5).map(|i| OffsetPtr::<Node>::new(i * 64)).collect();
+        for (i, ptr) in nodes.iter().enumerate() {
+            let next = nodes.get(i + 1).copied().unwrap_or_default();
+            let node = Node {
+                value: i as u64 * 10,
+                next,
+                sibling: RelPtr::null(),
+            };
+            ptr.write(&mmap, &node).expect("write");
+        }
+        mmap.flush().expect("flush");
+        drop(mmap);
+
+        let mmap = MemoryMappedFile::open_ro(&path).expect("reopen");
+        let mut values = Vec::new();
+        let mut cur = nodes[0];
+        while !cur.is_null() {
+            let node = cur.get(&mmap).expect("get");
+            values.push(node.value);
+            cur = node.next;
+        }
+        assert_eq!(values, [0, 10, 20, 30, 40]);
+
+        fs::remove_file(&path).expect("cleanup");
+    }
+
+    #[test]
+    fn test_dereference_checks() {
+        let mmap = MemoryMappedFile::anonymous(256).expect("anon");
+        assert!(matches!(
+            OffsetPtr::<Node>::null().read(&mmap),
+            Err(MmapIoError::InvalidMode(_))
+        ));
+        assert!(matches!(
+            OffsetPtr::<Node>::new(12).read(&mmap),
+            Err(MmapIoError::Misaligned {
+                required: 8,
+                offset: 12
+            })
+        ));
+        assert!(matches!(
+            OffsetPtr::<Node>::new(240).get(&mmap),
+            Err(MmapIoError::OutOfBounds { .. })
+        ));
+        assert!(OffsetPtr::<u8>::new(255).read(&mmap).is_ok());
+        assert_eq!(
+            OffsetPtr::<Node>::new(8)
+                .checked_add(2)
+                .map(OffsetPtr::offset),
+            Some(8 + 2 * NODE)
+        );
+        assert_eq!(OffsetPtr::<Node>::new(u64::MAX).checked_add(1), None);
+    }
+
+    #[test]
+    fn test_relative_pointers_survive_copying() {
+        let mmap = MemoryMappedFile::anonymous(4096).expect("anon");
+        // Two nodes at 64 and 64 + NODE, the first pointing at the second
+        let a = 64;
+        let b = a + NODE;
+        let sibling = RelPtr::between(a + 16, b).expect("between");
+        OffsetPtr::<Node>::new(a)
+            .write(
+                &mmap,
+                &Node {
+                    value: 1,
+                    next: OffsetPtr::null(),
+                    sibling,
+                },
+            )
+            .expect("write a");
+        OffsetPtr::<Node>::new(b)
+            .write(
+                &mmap,
+                &Node {
+                    value: 2,
+                    next: OffsetPtr::null(),
+                    sibling: RelPtr::null(),
+                },
+            )
+            .expect("write b");
+
+        // Copy the block elsewhere; the relative pointer still finds its sibling
+        let mut block = vec![0u8; (2 * NODE) as usize];
+        mmap.read_into(a, &mut block).expect("read");
+        let moved = 2048;
+        mmap.update_region(moved, &block).expect("copy");
+        let first = OffsetPtr::<Node>::new(moved).read(&mmap).expect("first");
+        let target = first.sibling.resolve(moved + 16).expect("resolve");
+        assert_eq!(target.offset(), moved + NODE);
+        assert_eq!(target.read(&mmap).expect("second").value, 2);
+
+        assert!(RelPtr::<Node>::null()
+            .resolve(moved)
+            .expect("null")
+            .is_null());
+        assert!(RelPtr::<Node>::between(8, 8).is_err());
+        assert!(RelPtr::<Node>::between(8, 0)
+            .expect("backwards")
+            .resolve(8)
+            .expect("resolve")
+            .is_null());
+    }
+}
@@ -0,0 +1,182 @@
+//! Address space reservations for read-write mappings that grow without moving.
+//!
+//! A reserved mapping maps the file over the whole reservation in one call, so memmap2 owns
+//! the range and unmaps it on drop. The part past the end of the file is then replaced with
+//! `PROT_NONE` anonymous memory. Growing maps the new file pages over that part with
+//! `MAP_FIXED`, so the base address and every existing page stay where they are. When a
+//! resize needs more than was reserved, Linux tries to extend the mapping in place with
+//! `mremap`. Other platforms report an error. The `MmapMut` keeps its original length, so
+//! the mapping's view is sized from the extent the reservation publishes instead.
+
+use std::fs::File;
+use std::os::unix::io::AsRawFd;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use memmap2::MmapOptions;
+use parking_lot::{Mutex, RwLock};
+
+use crate::errors::{MmapIoError, Result};
+use crate::mmap::RwMap;
+use crate::utils::{align_up, page_size};
+
+/// Bookkeeping for a reserved read-write mapping.
+pub(crate) struct Reservation {
+    // Start of the reservation; equal to the mapping's base pointer.
+    base: usize,
+    // Bytes owned by the `MmapMut`; memmap2 unmaps exactly this range.
+    initial: usize,
+    // Bytes currently reserved, including any in-place extension beyond `initial`.
+    reserved: Mutex<usize>,
+    // The mapping's view of `reserved`, so that it covers an extension too.
+    extent: Arc<AtomicUsize>,
+}
+
+impl Reservation {
+    /// Bytes of address space currently reserved.
+    pub(crate) fn reserved(&self) -> u64 {
+        *self.reserved.lock() as u64
+    }
+
+    /// Change the file length to `new_len`, mapping or releasing whole pages at the end of
+    /// the file in place, and update `cached_len`.
+    ///
+    /// Growing never touches pages that are already mapped, so it does not take the
+    /// mapping's write lock and existing references stay valid. Shrinking releases pages,
+    /// so it waits for the write lock.
+    pub(crate) fn resize(
+        &self,
+        file: &File,
+        map: &RwLock<RwMap>,
+        cached_len: &RwLock<u64>,
+        new_len: u64,
+    ) -> Result<()> {
+        let mut reserved = self.reserved.lock();
+        // Only changed while `reserved` is locked, so this cannot go stale below
+        let old_len = *cached_len.read();
+        let _exclusive = (new_len < old_len).then(|| map.write());
+        let page = page_size() as u64;
+        let old_mapped = align_up(old_len, page) as usize;
+        let new_mapped = align_up(new_len, page) as usize;
+        if new_mapped > *reserved {
+            *reserved = self.extend(*reserved, new_mapped)?;
+            self.extent.store(*reserved, Ordering::Release);
+        }
+        file.set_len(new_len)?;
+        if new_mapped > old_mapped {
+            // SAFETY: [old_mapped, new_mapped) lies inside the reservation and is currently
+            // PROT_NONE placeholder memory (or already this file's pages after an mremap), so
+            // MAP_FIXED only replaces memory nobody can be referencing.
+            check(unsafe {
+                libc::mmap(
+                    (self.base + old_mapped) as *mut libc::c_void,
+                    new_mapped - old_mapped,
+                    libc::PROT_READ | libc::PROT_WRITE,
+                    libc::MAP_SHARED | libc::MAP_FIXED,
+                    file.as_raw_fd(),
+                    old_mapped as libc::off_t,
+                )
+            })?;
+        } else if new_mapped < old_mapped {
+            protect_none(self.base + new_mapped, old_mapped - new_mapped)?;
+        }
+        *cached_len.write() = new_len;
+        Ok(())
+    }
+
+    /// Grow the reservation to `wanted` bytes without moving it.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn extend(&self, reserved: usize, wanted: usize) -> Result<usize> {
+        // SAFETY: without MREMAP_MAYMOVE the kernel either extends the mapping at the same
+        // address or fails, so no existing pointer is invalidated. This only succeeds when
+        // the reservation is one mapping, i.e. once it is fully mapped to the file.
+        let ptr = unsafe { libc::mremap(self.base as *mut libc::c_void, reserved, wanted, 0) };
+        if ptr == libc::MAP_FAILED {
+            return Err(MmapIoError::ResizeFailed(format!(
+                "growth to {wanted} bytes exceeds the {reserved} bytes of reserved address \
+                 space, and mremap could not extend it in place: {}",
+                std::io::Error::last_os_error()
+            )));
+        }
+        Ok(wanted)
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    fn extend(&self, reserved: usize, wanted: usize) -> Result<usize> {
+        Err(MmapIoError::ResizeFailed(format!(
+            "growth to {wanted} bytes exceeds the {reserved} bytes of reserved address space"
+        )))
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        let reserved = *self.reserved.get_mut();
+        if reserved > self.initial {
+            // SAFETY: the range was added by `extend` and is not owned by the MmapMut.
+            unsafe {
+                libc::munmap(
+                    (self.base + self.initial) as *mut libc::c_void,
+                    reserved - self.initial,
+                );
+            }
+        }
+    }
+}
+
+/// Map `len` bytes of `file` read-write at the start of a reservation of at least `reserve`
+/// bytes. The file must already be `len` bytes long.
+pub(crate) fn map_reserved(file: &File, len: u64, reserve: u64) -> Result<(RwMap, Reservation)> {
+    let page = page_size() as u64;
+    let reserved = align_up(reserve.max(len), page);
+    let mapped = align_up(len, page);
+    let reserved = usize::try_from(reserved).map_err(|_| {
+        MmapIoError::ResizeFailed(format!("reservation of {reserved} bytes is too large"))
+    })?;
+    // SAFETY: mapping past the end of the file is allowed; the pages past `mapped` are
+    // replaced below before anything can reach them, and bounds checks use the file length.
+    let mmap = unsafe { MmapOptions::new().len(reserved).map_mut(file)? };
+    let base = mmap.as_ptr() as usize;
+    protect_none(base + mapped as usize, reserved - mapped as usize)?;
+    let extent = Arc::new(AtomicUsize::new(reserved));
+    Ok((
+        RwMap::with_extent(mmap, Arc::clone(&extent)),
+        Reservation {
+            base,
+            initial: reserved,
+            reserved: Mutex::new(reserved),
+            extent,
+        },
+    ))
+}
+
+/// Replace `[addr, addr + len)` with inaccessible anonymous memory that commits nothing.
+fn protect_none(addr: usize, len: usize) -> Result<()> {
+    if len == 0 {
+        return Ok(());
+    }
+    // SAFETY: callers pass page-aligned ranges inside a reservation that no live reference
+    // points into.
+    check(unsafe {
+        libc::mmap(
+            addr as *mut libc::c_void,
+            len,
+            libc::PROT_NONE,
+            libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | libc::MAP_FIXED | MAP_NORESERVE,
+            -1,
+            0,
+        )
+    })
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const MAP_NORESERVE: libc::c_int = libc::MAP_NORESERVE;
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+const MAP_NORESERVE: libc::c_int = 0;
+
+fn check(ptr: *mut libc::c_void) -> Result<()> {
+    if ptr == libc::MAP_FAILED {
+        return Err(MmapIoError::Io(std::io::Error::last_os_error()));
+    }
+    Ok(())
+}
@@ -113,12 +113,15 @@ pub struct SegmentMut {
 impl SegmentMut {
     /// Create a new mutable segment view. Performs bounds checks.
     ///
+    /// If the parent has a growth policy, the segment may extend past the end of the file up
+    /// to its `max_size`; the file grows when the segment is written.
+    ///
     /// # Errors
     ///
     /// Returns `MmapIoError::OutOfBounds` if the segment exceeds file bounds.
     pub fn new(parent: Arc<MemoryMappedFile>, offset: u64, len: u64) -> Result<Self> {
         // Validate bounds once at construction
-        let total = parent.current_len()?;
+        let total = parent.writable_len()?;
         let _ = slice_range(offset, len, total)?;
         Ok(Self {
             parent,
@@ -0,0 +1,701 @@
+//! Named shared-memory (`shm_open`) and `memfd`-backed mappings for inter-process communication.
+//!
+//! These regions are created through the regular [`MemoryMappedFileBuilder`] so the rest of the
+//! API (segments, atomics, iterators, resize) works unchanged. Shared memory is never persisted
+//! to disk, so flushing and flush policies are no-ops for these mappings.
+//!
+//! Mappings can be handed to another process without a path using [`send_mapping`] and
+//! [`recv_mapping`], which pass the backing descriptor over a Unix domain socket.
+//!
+//! [`MemoryMappedFileBuilder`]: crate::mmap::MemoryMappedFileBuilder
+
+use std::ffi::{CString, OsStr};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::os::fd::{AsRawFd, FromRawFd, RawFd};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+
+use crate::errors::{MmapIoError, Result};
+use crate::mmap::{Backing, MemoryMappedFile, MemoryMappedFileBuilder, MmapMode};
+
+// Permissions for newly created shared memory objects (owner read/write).
+const SHM_PERMISSIONS: libc::mode_t = 0o600;
+
+/// Seals applied to a `memfd` region (Linux `F_ADD_SEALS`).
+///
+/// Seals are permanent: once added they cannot be removed for the lifetime of the region.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MemfdSeals {
+    /// Prevent the region from shrinking (`F_SEAL_SHRINK`).
+    pub shrink: bool,
+    /// Prevent the region from growing (`F_SEAL_GROW`).
+    pub grow: bool,
+    /// Prevent any further writes (`F_SEAL_WRITE`).
+    pub write: bool,
+}
+
+impl MemfdSeals {
+    /// No seals.
+    pub const NONE: Self = Self {
+        shrink: false,
+        grow: false,
+        write: false,
+    };
+
+    /// Fix the size of the region (shrink + grow).
+    pub const SIZE: Self = Self {
+        shrink: true,
+        grow: true,
+        write: false,
+    };
+
+    /// Fix both the size and the contents of the region.
+    pub const ALL: Self = Self {
+        shrink: true,
+        grow: true,
+        write: true,
+    };
+
+    /// Whether no seal is requested.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        !(self.shrink || self.grow || self.write)
+    }
+}
+
+impl MemoryMappedFile {
+    /// Builder for a mapping backed by a named POSIX shared memory object.
+    ///
+    /// The name is prefixed with `/` if it does not already start with one.
+    pub fn shm_builder(name: &str) -> MemoryMappedFileBuilder {
+        let name = normalize_shm_name(name);
+        MemoryMappedFileBuilder::with_backing(PathBuf::from(&name), Backing::Shm(name))
+    }
+
+    /// Create (truncating if it exists) a named shared memory region of `size` bytes and map it read-write.
+    ///
+    /// The region outlives this process until removed with [`MemoryMappedFile::shm_unlink`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use mmap_io::{MemoryMappedFile, MmapMode};
+    ///
+    /// let writer = MemoryMappedFile::shm_create("/my-region", 4096)?;
+    /// writer.update_region(0, b"hello")?;
+    ///
+    /// // In another process:
+    /// let reader = MemoryMappedFile::shm_open("/my-region", MmapMode::ReadOnly)?;
+    /// assert_eq!(reader.as_slice(0, 5)?, b"hello");
+    ///
+    /// MemoryMappedFile::shm_unlink("/my-region")?;
+    /// # Ok::<(), mmap_io::MmapIoError>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::ResizeFailed` if size is zero or exceeds the maximum safe limit.
+    /// Returns `MmapIoError::Io` if the object cannot be created or mapped.
+    pub fn shm_create(name: &str, size: u64) -> Result<Self> {
+        Self::shm_builder(name)
+            .mode(MmapMode::ReadWrite)
+            .size(size)
+            .create()
+    }
+
+    /// Open an existing named shared memory region in the given mode.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::Io` if the object does not exist or cannot be mapped.
+    pub fn shm_open(name: &str, mode: MmapMode) -> Result<Self> {
+        Self::shm_builder(name).mode(mode).open()
+    }
+
+    /// Remove a named shared memory region. Existing mappings stay valid until dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::Io` if the object does not exist or cannot be removed.
+    pub fn shm_unlink(name: &str) -> Result<()> {
+        let cname = shm_cstring(&normalize_shm_name(name))?;
+        // SAFETY: cname is a valid NUL-terminated string.
+        let ret = unsafe { libc::shm_unlink(cname.as_ptr()) };
+        if ret != 0 {
+            return Err(MmapIoError::Io(std::io::Error::last_os_error()));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+impl MemoryMappedFile {
+    /// Builder for a mapping backed by an anonymous `memfd` region.
+    ///
+    /// The name is only used for debugging (it appears in `/proc/<pid>/fd`). Use
+    /// [`MemoryMappedFileBuilder::seals`] to fix the size at creation time.
+    pub fn memfd_builder(name: &str) -> MemoryMappedFileBuilder {
+        MemoryMappedFileBuilder::with_backing(
+            PathBuf::from(format!("memfd:{name}")),
+            Backing::Memfd {
+                name: name.to_owned(),
+                seals: MemfdSeals::NONE,
+            },
+        )
+    }
+
+    /// Create an anonymous `memfd` region of `size` bytes and map it read-write.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::ResizeFailed` if size is zero or exceeds the maximum safe limit.
+    /// Returns `MmapIoError::Io` if the region cannot be created or mapped.
+    pub fn memfd(name: &str, size: u64) -> Result<Self> {
+        Self::memfd_builder(name)
+            .mode(MmapMode::ReadWrite)
+            .size(size)
+            .create()
+    }
+
+    /// Add seals to a `memfd`-backed mapping.
+    ///
+    /// Size seals are applied in place. A write seal can only be added once no writable
+    /// mapping of the region exists, so when `seals.write` is set this consumes the
+    /// read-write mapping, seals the region and returns a read-only mapping of it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidMode` if the mapping is not `memfd`-backed, or if a
+    /// write seal is requested while other clones of this mapping are still alive.
+    /// Returns `MmapIoError::Io` if the kernel rejects the seals.
+    pub fn seal(self, seals: MemfdSeals) -> Result<Self> {
+        if !matches!(self.inner.backing, Backing::Memfd { .. }) {
+            return Err(MmapIoError::InvalidMode(
+                "seal requires a memfd-backed mapping",
+            ));
+        }
+        if !seals.write {
+            apply_seals(self.inner.file()?, seals)?;
+            return Ok(self);
+        }
+        let mut inner = std::sync::Arc::try_unwrap(self.inner).map_err(|_| {
+            MmapIoError::InvalidMode("cannot add a write seal while the mapping is shared")
+        })?;
+        let path = std::mem::take(&mut inner.path);
+        let backing = inner.backing.clone();
+        let len = *inner.cached_len.read();
+        let file = inner
+            .file
+            .take()
+            .ok_or(MmapIoError::InvalidMode("memfd mapping has no descriptor"))?;
+        // The writable mapping must be gone before the kernel accepts F_SEAL_WRITE.
+        drop(inner);
+        apply_seals(&file, seals)?;
+        Self::from_file(path, file, backing, MmapMode::ReadOnly, None, len)
+    }
+}
+
+// Wire descriptor sent alongside the file descriptor:
+// magic (4) | version (1) | mode (1) | backing kind (1) | flags (1) | len (8) | window offset (8)
+// | path len (4)
+const DESCRIPTOR_MAGIC: [u8; 4] = *b"MMIO";
+const DESCRIPTOR_VERSION: u8 = 2;
+const DESCRIPTOR_LEN: usize = 28;
+// Flag set when the mapping is a window starting at the carried offset.
+const FLAG_WINDOW: u8 = 1;
+// Upper bound on the path/name carried in a descriptor.
+const MAX_DESCRIPTOR_PATH: usize = 4096;
+
+const KIND_FILE: u8 = 0;
+const KIND_SHM: u8 = 1;
+const KIND_MEMFD: u8 = 2;
+const KIND_TEMP: u8 = 3;
+
+/// Send a mapping to another process over a Unix domain socket.
+///
+/// The backing file descriptor is passed with `SCM_RIGHTS` together with a small descriptor
+/// holding the mapping mode, length, window offset and path (or shared memory name). The peer
+/// reconstructs an equivalent mapping, over the same range of the file, with [`recv_mapping`]. This works for any mapping, but is mostly useful for
+/// `memfd` and shared memory regions that cannot be reopened by path.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::os::unix::net::UnixStream;
+/// use mmap_io::{recv_mapping, send_mapping, MemoryMappedFile};
+///
+/// let (parent, child) = UnixStream::pair()?;
+/// let region = MemoryMappedFile::memfd("worker-input", 4096)?;
+/// region.update_region(0, b"job")?;
+/// send_mapping(&parent, &region)?;
+///
+/// // In the worker:
+/// let received = recv_mapping(&child)?;
+/// let mut buf = [0u8; 3];
+/// received.read_into(0, &mut buf)?;
+/// assert_eq!(&buf, b"job");
+/// # Ok::<(), mmap_io::MmapIoError>(())
+/// ```
+///
+/// # Errors
+///
+/// Returns `MmapIoError::InvalidMode` for anonymous mappings, which have no descriptor.
+/// Returns `MmapIoError::Io` if the path is too long or the socket operation fails.
+pub fn send_mapping(stream: &UnixStream, mmap: &MemoryMappedFile) -> Result<()> {
+    let path = mmap.path().as_os_str().as_bytes();
+    if path.len() > MAX_DESCRIPTOR_PATH {
+        return Err(invalid_data("mapping path too long to send"));
+    }
+    let mut message = Vec::with_capacity(DESCRIPTOR_LEN + path.len());
+    message.extend_from_slice(&DESCRIPTOR_MAGIC);
+    message.push(DESCRIPTOR_VERSION);
+    message.push(match mmap.mode() {
+        MmapMode::ReadOnly => 0,
+        MmapMode::ReadWrite => 1,
+        MmapMode::CopyOnWrite => 2,
+    });
+    message.push(match &mmap.inner.backing {
+        Backing::File => KIND_FILE,
+        Backing::Shm(_) => KIND_SHM,
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        Backing::Memfd { .. } => KIND_MEMFD,
+        Backing::Temp => KIND_TEMP,
+        Backing::Anonymous => {
+            return Err(MmapIoError::InvalidMode(
+                "anonymous mappings have no descriptor to send",
+            ))
+        }
+    });
+    let window = mmap.inner.window_offset;
+    message.push(if window.is_some() { FLAG_WINDOW } else { 0 });
+    message.extend_from_slice(&mmap.len().to_le_bytes());
+    message.extend_from_slice(&window.unwrap_or(0).to_le_bytes());
+    message.extend_from_slice(&(path.len() as u32).to_le_bytes());
+    message.extend_from_slice(path);
+
+    let fd: RawFd = mmap.inner.file()?.as_raw_fd();
+    let fd_len = std::mem::size_of::<RawFd>() as u32;
+    // SAFETY: CMSG_SPACE only computes a buffer size.
+    let space = unsafe { libc::CMSG_SPACE(fd_len) } as usize;
+    // u64 storage keeps the control buffer aligned for cmsghdr.
+    let mut control = vec![0u64; space.div_ceil(8)];
+    let mut iov = libc::iovec {
+        iov_base: message.as_ptr() as *mut libc::c_void,
+        iov_len: message.len(),
+    };
+    // SAFETY: msghdr is a plain C struct for which all-zero is a valid value.
+    let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+    msg.msg_controllen = space as _;
+
+    // SAFETY: The control buffer holds exactly one SCM_RIGHTS header with room for one fd, and
+    // the iovec points at `message`, which outlives the sendmsg call.
+    let sent = unsafe {
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        (*cmsg).cmsg_level = libc::SOL_SOCKET;
+        (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+        (*cmsg).cmsg_len = libc::CMSG_LEN(fd_len) as _;
+        std::ptr::write_unaligned(libc::CMSG_DATA(cmsg) as *mut RawFd, fd);
+        libc::sendmsg(stream.as_raw_fd(), &msg, 0)
+    };
+    if sent < 0 {
+        return Err(MmapIoError::Io(std::io::Error::last_os_error()));
+    }
+    // The descriptor travels with the first byte; stream the remainder if the send was short.
+    let sent = sent as usize;
+    if sent < message.len() {
+        (&mut &*stream).write_all(&message[sent..])?;
+    }
+    Ok(())
+}
+
+/// Receive a mapping sent with [`send_mapping`] and map it in the sender's mode, length and
+/// window offset.
+///
+/// # Errors
+///
+/// Returns `MmapIoError::Io` if the socket operation fails, the peer closed the connection,
+/// no file descriptor was attached, or the descriptor is malformed. A length larger than the
+/// received object, e.g. a file truncated in flight, is rejected with `InvalidData`.
+/// Returns `MmapIoError::InvalidMode` if the sender used a mode this build cannot map.
+pub fn recv_mapping(stream: &UnixStream) -> Result<MemoryMappedFile> {
+    let mut header = [0u8; DESCRIPTOR_LEN];
+    let fd_len = std::mem::size_of::<RawFd>() as u32;
+    // SAFETY: CMSG_SPACE only computes a buffer size.
+    let space = unsafe { libc::CMSG_SPACE(fd_len) } as usize;
+    let mut control = vec![0u64; space.div_ceil(8)];
+    let mut iov = libc::iovec {
+        iov_base: header.as_mut_ptr() as *mut libc::c_void,
+        iov_len: header.len(),
+    };
+    // SAFETY: msghdr is a plain C struct for which all-zero is a valid value.
+    let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+    msg.msg_controllen = space as _;
+
+    cfg_if::cfg_if! {
+        if #[cfg(any(target_os = "linux", target_os = "android"))] {
+            let flags = libc::MSG_CMSG_CLOEXEC;
+        } else {
+            let flags = 0;
+        }
+    }
+    // SAFETY: The iovec and control buffer are valid for the duration of the call.
+    let received = unsafe { libc::recvmsg(stream.as_raw_fd(), &mut msg, flags) };
+    if received < 0 {
+        return Err(MmapIoError::Io(std::io::Error::last_os_error()));
+    }
+
+    // Take ownership of any received descriptor first so it is closed on every error path.
+    let mut file: Option<File> = None;
+    // SAFETY: The kernel filled the control buffer; we only walk the headers it reported.
+    unsafe {
+        let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+        while !cmsg.is_null() {
+            if (*cmsg).cmsg_level == libc::SOL_SOCKET && (*cmsg).cmsg_type == libc::SCM_RIGHTS {
+                let fd = std::ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const RawFd);
+                file = Some(File::from_raw_fd(fd));
+            }
+            cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+        }
+    }
+    if received == 0 {
+        return Err(MmapIoError::Io(std::io::Error::new(
+            std::io::ErrorKind::UnexpectedEof,
+            "connection closed before a mapping was received",
+        )));
+    }
+    if msg.msg_flags & libc::MSG_CTRUNC != 0 {
+        return Err(invalid_data(
+            "ancillary data truncated while receiving mapping",
+        ));
+    }
+    let file = file.ok_or_else(|| invalid_data("no file descriptor received with mapping"))?;
+
+    let received = received as usize;
+    if received < DESCRIPTOR_LEN {
+        (&mut &*stream).read_exact(&mut header[received..])?;
+    }
+    if header[..4] != DESCRIPTOR_MAGIC || header[4] != DESCRIPTOR_VERSION {
+        return Err(invalid_data("unrecognized mapping descriptor"));
+    }
+    let mode = match header[5] {
+        0 => MmapMode::ReadOnly,
+        1 => MmapMode::ReadWrite,
+        2 => MmapMode::CopyOnWrite,
+        _ => return Err(invalid_data("unknown mapping mode in descriptor")),
+    };
+    let mut len_bytes = [0u8; 8];
+    len_bytes.copy_from_slice(&header[8..16]);
+    let len = u64::from_le_bytes(len_bytes);
+    let mut offset_bytes = [0u8; 8];
+    offset_bytes.copy_from_slice(&header[16..24]);
+    let window = match header[7] {
+        0 => None,
+        FLAG_WINDOW => Some(u64::from_le_bytes(offset_bytes)),
+        _ => return Err(invalid_data("unknown flags in descriptor")),
+    };
+    let mut path_len_bytes = [0u8; 4];
+    path_len_bytes.copy_from_slice(&header[24..28]);
+    let path_len = u32::from_le_bytes(path_len_bytes) as usize;
+    if path_len > MAX_DESCRIPTOR_PATH {
+        return Err(invalid_data("mapping path in descriptor too long"));
+    }
+    let mut path = vec![0u8; path_len];
+    (&mut &*stream).read_exact(&mut path)?;
+    let path = PathBuf::from(OsStr::from_bytes(&path));
+
+    let backing = match header[6] {
+        KIND_FILE => Backing::File,
+        KIND_SHM => Backing::Shm(path.to_string_lossy().into_owned()),
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        KIND_MEMFD => {
+            let display = path.to_string_lossy();
+            Backing::Memfd {
+                name: display
+                    .strip_prefix("memfd:")
+                    .unwrap_or(&display)
+                    .to_owned(),
+                seals: MemfdSeals::NONE,
+            }
+        }
+        KIND_TEMP => Backing::Temp,
+        _ => return Err(invalid_data("unsupported backing kind in descriptor")),
+    };
+    // Mapping past the end of the object would raise SIGBUS on first access.
+    let file_len = file.metadata()?.len();
+    let end = window.unwrap_or(0).checked_add(len);
+    if end.map_or(true, |end| end > file_len) {
+        return Err(invalid_data("mapping length exceeds the received object"));
+    }
+    MemoryMappedFile::from_file(path, file, backing, mode, window, len)
+}
+
+fn invalid_data(msg: &'static str) -> MmapIoError {
+    MmapIoError::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, msg))
+}
+
+// Prefix a shared memory name with '/' as required by POSIX.
+fn normalize_shm_name(name: &str) -> String {
+    if name.starts_with('/') {
+        name.to_owned()
+    } else {
+        format!("/{name}")
+    }
+}
+
+fn shm_cstring(name: &str) -> Result<CString> {
+    CString::new(name).map_err(|_| {
+        MmapIoError::Io(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "shared memory name contains an interior NUL byte",
+        ))
+    })
+}
+
+/// Open (or create and truncate) a POSIX shared memory object.
+pub(crate) fn open_shm(name: &str, create: bool, write: bool) -> Result<File> {
+    let cname = shm_cstring(name)?;
+    let mut flags = if write { libc::O_RDWR } else { libc::O_RDONLY };
+    if create {
+        flags |= libc::O_CREAT | libc::O_TRUNC;
+    }
+    // SAFETY: cname is a valid NUL-terminated string; the returned descriptor is checked below.
+    let fd = unsafe {
+        cfg_if::cfg_if! {
+            if #[cfg(any(target_os = "macos", target_os = "ios"))] {
+                libc::shm_open(cname.as_ptr(), flags, libc::c_uint::from(SHM_PERMISSIONS))
+            } else {
+                libc::shm_open(cname.as_ptr(), flags, SHM_PERMISSIONS)
+            }
+        }
+    };
+    if fd < 0 {
+        return Err(MmapIoError::Io(std::io::Error::last_os_error()));
+    }
+    // SAFETY: fd is a freshly opened descriptor owned by nobody else.
+    Ok(unsafe { File::from_raw_fd(fd) })
+}
+
+/// Create an anonymous `memfd` region that allows sealing.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn create_memfd(name: &str) -> Result<File> {
+    let cname = shm_cstring(name)?;
+    // SAFETY: cname is a valid NUL-terminated string; the returned descriptor is checked below.
+    let fd =
+        unsafe { libc::memfd_create(cname.as_ptr(), libc::MFD_CLOEXEC | libc::MFD_ALLOW_SEALING) };
+    if fd < 0 {
+        return Err(MmapIoError::Io(std::io::Error::last_os_error()));
+    }
+    // SAFETY: fd is a freshly created descriptor owned by nobody else.
+    Ok(unsafe { File::from_raw_fd(fd) })
+}
+
+/// Add the requested seals to a `memfd` descriptor.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn apply_seals(file: &File, seals: MemfdSeals) -> Result<()> {
+    if seals.is_empty() {
+        return Ok(());
+    }
+    let mut flags = 0;
+    if seals.shrink {
+        flags |= libc::F_SEAL_SHRINK;
+    }
+    if seals.grow {
+        flags |= libc::F_SEAL_GROW;
+    }
+    if seals.write {
+        flags |= libc::F_SEAL_WRITE;
+    }
+    // SAFETY: fcntl on a valid descriptor owned by `file`.
+    let ret = unsafe { libc::fcntl(file.as_raw_fd(), libc::F_ADD_SEALS, flags) };
+    if ret != 0 {
+        return Err(MmapIoError::Io(std::io::Error::last_os_error()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shm_name(name: &str) -> String {
+        format!("/mmap_io_shm_test_{}_{}", name, std::process::id())
+    }
+
+    #[test]
+    fn test_shm_create_open_unlink() {
+        let name = shm_name("roundtrip");
+        let _ = MemoryMappedFile::shm_unlink(&name);
+
+        let writer = MemoryMappedFile::shm_create(&name, 4096).expect("shm create");
+        assert_eq!(writer.len(), 4096);
+        writer.update_region(10, b"shared").expect("write");
+        // Flush is a no-op for shared memory but must succeed
+        writer.flush().expect("flush");
+
+        let reader = MemoryMappedFile::shm_open(&name, MmapMode::ReadOnly).expect("shm open");
+        assert_eq!(reader.as_slice(10, 6).expect("slice"), b"shared");
+
+        // Resizing goes through ftruncate like a regular file
+        writer.resize(8192).expect("resize");
+        assert_eq!(writer.len(), 8192);
+
+        MemoryMappedFile::shm_unlink(&name).expect("unlink");
+        assert!(MemoryMappedFile::shm_open(&name, MmapMode::ReadOnly).is_err());
+    }
+
+    #[test]
+    fn test_shm_name_normalized() {
+        let name = shm_name("normalize");
+        let bare = name.trim_start_matches('/');
+        let _ = MemoryMappedFile::shm_unlink(&name);
+
+        let mmap = MemoryMappedFile::shm_create(bare, 128).expect("create");
+        assert_eq!(mmap.path(), std::path::Path::new(&name));
+        MemoryMappedFile::shm_unlink(bare).expect("unlink");
+    }
+
+    #[test]
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn test_memfd_seals() {
+        let mmap = MemoryMappedFile::memfd_builder("sealed")
+            .size(4096)
+            .seals(MemfdSeals::SIZE)
+            .create()
+            .expect("memfd");
+        mmap.update_region(0, b"memfd").expect("write");
+        assert!(mmap.resize(8192).is_err(), "grow seal must reject resize");
+
+        let ro = mmap.seal(MemfdSeals::ALL).expect("write seal");
+        assert_eq!(ro.mode(), MmapMode::ReadOnly);
+        assert_eq!(ro.as_slice(0, 5).expect("slice"), b"memfd");
+    }
+
+    #[test]
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn test_send_recv_memfd_mapping() {
+        let (tx, rx) = UnixStream::pair().expect("socket pair");
+        let region = MemoryMappedFile::memfd("ipc", 8192).expect("memfd");
+        region.update_region(100, b"before").expect("write");
+
+        send_mapping(&tx, &region).expect("send");
+        let received = recv_mapping(&rx).expect("recv");
+        assert_eq!(received.mode(), MmapMode::ReadWrite);
+        assert_eq!(received.len(), 8192);
+        assert_eq!(received.path(), region.path());
+
+        // Both sides share the same pages
+        let mut buf = [0u8; 6];
+        received.read_into(100, &mut buf).expect("read");
+        assert_eq!(&buf, b"before");
+        region.update_region(100, b"after!").expect("write");
+        received.read_into(100, &mut buf).expect("read");
+        assert_eq!(&buf, b"after!");
+    }
+
+    #[test]
+    fn test_send_recv_file_mapping_read_only() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("mmap_io_shm_test_send_file_{}", std::process::id()));
+        let writer = crate::create_mmap(&path, 256).expect("create");
+        writer.update_region(0, b"on disk").expect("write");
+        writer.flush().expect("flush");
+        let ro = MemoryMappedFile::open_ro(&path).expect("open ro");
+
+        let (tx, rx) = UnixStream::pair().expect("socket pair");
+        send_mapping(&tx, &ro).expect("send");
+        let received = recv_mapping(&rx).expect("recv");
+        assert_eq!(received.mode(), MmapMode::ReadOnly);
+        assert_eq!(received.as_slice(0, 7).expect("slice"), b"on disk");
+
+        std::fs::remove_file(&path).expect("cleanup");
+    }
+
+    #[test]
+    fn test_send_recv_windowed_mapping() {
+        let mut path = std::env::temp_dir();
+        path.push(format!(
+            "mmap_io_shm_test_send_window_{}",
+            std::process::id()
+        ));
+        let page = crate::utils::page_size() as u64;
+        let mut data = vec![b'A'; page as usize];
+        data.extend(vec![b'B'; page as usize]);
+        std::fs::write(&path, &data).expect("seed file");
+        let window = MemoryMappedFile::builder(&path)
+            .mode(MmapMode::ReadWrite)
+            .offset(page - 2)
+            .len(4)
+            .open()
+            .expect("open window");
+
+        let (tx, rx) = UnixStream::pair().expect("socket pair");
+        send_mapping(&tx, &window).expect("send");
+        let received = recv_mapping(&rx).expect("recv");
+        assert_eq!(received.len(), 4);
+        let (mut sent, mut got) = ([0u8; 4], [0u8; 4]);
+        window.read_into(0, &mut sent).expect("read");
+        received.read_into(0, &mut got).expect("read");
+        assert_eq!(&sent, b"AABB");
+        assert_eq!(got, sent);
+
+        drop((window, received));
+        std::fs::remove_file(&path).expect("cleanup");
+    }
+
+    #[test]
+    fn test_recv_mapping_rejects_length_past_end() {
+        let mut path = std::env::temp_dir();
+        path.push(format!("mmap_io_shm_test_truncated_{}", std::process::id()));
+        let ro = {
+            let writer = crate::create_mmap(&path, 8192).expect("create");
+            writer.flush().expect("flush");
+            MemoryMappedFile::open_ro(&path).expect("open ro")
+        };
+
+        let (tx, rx) = UnixStream::pair().expect("socket pair");
+        send_mapping(&tx, &ro).expect("send");
+        // Truncated after the descriptor was sent
+        std::fs::OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .and_then(|f| f.set_len(100))
+            .expect("truncate");
+        match recv_mapping(&rx) {
+            Err(MmapIoError::Io(e)) => assert_eq!(e.kind(), std::io::ErrorKind::InvalidData),
+            other => panic!("expected InvalidData, got {other:?}"),
+        }
+
+        drop(ro);
+        std::fs::remove_file(&path).expect("cleanup");
+    }
+
+    #[test]
+    fn test_recv_mapping_without_descriptor() {
+        let (tx, rx) = UnixStream::pair().expect("socket pair");
+        (&tx).write_all(&[0u8; DESCRIPTOR_LEN]).expect("write");
+        assert!(matches!(recv_mapping(&rx), Err(MmapIoError::Io(_))));
+
+        drop(tx);
+        assert!(matches!(recv_mapping(&rx), Err(MmapIoError::Io(_))));
+    }
+
+    #[test]
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn test_memfd_write_seal_requires_unique_mapping() {
+        let mmap = MemoryMappedFile::memfd("shared", 64).expect("memfd");
+        let clone = mmap.clone();
+        assert!(matches!(
+            mmap.seal(MemfdSeals::ALL),
+            Err(MmapIoError::InvalidMode(_))
+        ));
+        drop(clone);
+    }
+}
@@ -0,0 +1,205 @@
+//! Recovery from `SIGBUS` when a mapped file is truncated by someone else (Unix).
+//!
+//! Reading a page of a shared mapping that lies wholly past the end of the file raises
+//! `SIGBUS`, which kills the process by default. After [`install_handler`], reads through
+//! `read_into`, `read_with`, `touch_pages` and the chunk and page iterators mark their range
+//! as a recovery point for the current thread. A fault inside that range is handled by
+//! mapping a zero page over the missing one, so the read completes, and the call returns
+//! `MmapIoError::Truncated` instead. Faults anywhere else are passed to the handler that was
+//! installed before, so unrelated crashes behave as they did.
+//!
+//! The patched pages stay zero for every user of the mapping until it is dropped or
+//! refreshed with [`MemoryMappedFile::refresh`](crate::MemoryMappedFile::refresh).
+//! A patched page is private memory, so anything written to it would never reach the file.
+//! After a recovered fault, a read-write mapping therefore rejects writes, atomic views and
+//! flushes with `Truncated` until `refresh` remaps it, which it does even if the file length
+//! is unchanged.
+
+use std::cell::Cell;
+use std::sync::atomic::{compiler_fence, AtomicBool, AtomicUsize, Ordering};
+use std::sync::OnceLock;
+
+use parking_lot::{const_mutex, Mutex};
+
+use crate::errors::{MmapIoError, Result};
+use crate::utils::page_size;
+
+static INSTALLED: AtomicBool = AtomicBool::new(false);
+static INSTALL_LOCK: Mutex<()> = const_mutex(());
+static PREVIOUS: OnceLock<libc::sigaction> = OnceLock::new();
+static PAGE_SIZE: AtomicUsize = AtomicUsize::new(0);
+
+type Handler = extern "C" fn(libc::c_int, *mut libc::siginfo_t, *mut libc::c_void);
+
+// Address range the current thread is reading, and whether a fault was recovered inside it.
+#[derive(Clone, Copy)]
+struct RecoveryPoint {
+    start: usize,
+    end: usize,
+    hit: bool,
+}
+
+const NO_RECOVERY_POINT: RecoveryPoint = RecoveryPoint {
+    start: 0,
+    end: 0,
+    hit: false,
+};
+
+thread_local! {
+    // Const-initialized without a destructor, so the handler can read it without allocating.
+    static RECOVERY: Cell<RecoveryPoint> = const { Cell::new(NO_RECOVERY_POINT) };
+}
+
+/// Install the process-wide `SIGBUS` handler. Calling it again has no effect.
+///
+/// # Errors
+///
+/// Returns `MmapIoError::Io` if `sigaction` fails.
+pub fn install_handler() -> Result<()> {
+    let _lock = INSTALL_LOCK.lock();
+    if INSTALLED.load(Ordering::Acquire) {
+        return Ok(());
+    }
+    PAGE_SIZE.store(page_size(), Ordering::Relaxed);
+    // SAFETY: both structs are plain data for which all-zero is a valid value, and the new
+    // action points at a handler with the `SA_SIGINFO` signature.
+    let previous = unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = on_sigbus as Handler as usize;
+        action.sa_flags = libc::SA_SIGINFO | libc::SA_ONSTACK;
+        libc::sigemptyset(&mut action.sa_mask);
+        let mut previous: libc::sigaction = std::mem::zeroed();
+        if libc::sigaction(libc::SIGBUS, &action, &mut previous) != 0 {
+            return Err(MmapIoError::Io(std::io::Error::last_os_error()));
+        }
+        previous
+    };
+    let _ = PREVIOUS.set(previous);
+    INSTALLED.store(true, Ordering::Release);
+    Ok(())
+}
+
+/// Whether [`install_handler`] has run.
+#[must_use]
+pub fn is_installed() -> bool {
+    INSTALLED.load(Ordering::Acquire)
+}
+
+/// Run `f`, which reads `bytes` of a mapping found at `offset`, as a recovery point.
+/// Returns `Truncated` if part of `bytes` was no longer backed by the file.
+pub(crate) fn guarded<R>(offset: u64, bytes: &[u8], f: impl FnOnce() -> R) -> Result<R> {
+    if !INSTALLED.load(Ordering::Acquire) {
+        return Ok(f());
+    }
+    let start = bytes.as_ptr() as usize;
+    let restore = Restore(RECOVERY.with(|point| {
+        point.replace(RecoveryPoint {
+            start,
+            end: start + bytes.len(),
+            hit: false,
+        })
+    }));
+    compiler_fence(Ordering::SeqCst);
+    let result = f();
+    compiler_fence(Ordering::SeqCst);
+    let hit = RECOVERY.with(Cell::get).hit;
+    drop(restore);
+    if hit {
+        return Err(MmapIoError::Truncated {
+            offset,
+            len: bytes.len() as u64,
+        });
+    }
+    Ok(result)
+}
+
+// Restores the enclosing recovery point, also when `f` panics. A recovered fault is passed
+// on so that an enclosing call reports it too.
+struct Restore(RecoveryPoint);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        let mut outer = self.0;
+        RECOVERY.with(|point| {
+            outer.hit |= point.get().hit && outer.end > outer.start;
+            point.set(outer);
+        });
+    }
+}
+
+extern "C" fn on_sigbus(
+    signum: libc::c_int,
+    info: *mut libc::siginfo_t,
+    context: *mut libc::c_void,
+) {
+    // SAFETY: the kernel passes a valid siginfo for handlers installed with SA_SIGINFO.
+    let addr = unsafe { fault_addr(info) };
+    let recovered = RECOVERY
+        .try_with(|point| {
+            let mut current = point.get();
+            if addr < current.start || addr >= current.end || !map_zero_page(addr) {
+                return false;
+            }
+            current.hit = true;
+            point.set(current);
+            true
+        })
+        .unwrap_or(false);
+    if !recovered {
+        // SAFETY: forwards the arguments this handler received.
+        unsafe { forward(signum, info, context) };
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+unsafe fn fault_addr(info: *const libc::siginfo_t) -> usize {
+    (*info).si_addr() as usize
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+unsafe fn fault_addr(info: *const libc::siginfo_t) -> usize {
+    (*info).si_addr as usize
+}
+
+// Replace the page containing `addr` with a private zero page so the faulting read resumes.
+fn map_zero_page(addr: usize) -> bool {
+    let page = PAGE_SIZE.load(Ordering::Relaxed);
+    let start = addr & !(page - 1);
+    // SAFETY: the page belongs to a live mapping the current thread is reading, and reading
+    // it faulted, so no data can be lost by replacing it.
+    let ptr = unsafe {
+        libc::mmap(
+            start as *mut libc::c_void,
+            page,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | libc::MAP_FIXED,
+            -1,
+            0,
+        )
+    };
+    ptr != libc::MAP_FAILED
+}
+
+// Hand a fault that is not ours to the previous disposition. For the default action, reset
+// it and return: the faulting instruction runs again and the process dies as it would have.
+unsafe fn forward(signum: libc::c_int, info: *mut libc::siginfo_t, context: *mut libc::c_void) {
+    match PREVIOUS.get() {
+        Some(previous)
+            if previous.sa_sigaction != libc::SIG_DFL && previous.sa_sigaction != libc::SIG_IGN =>
+        {
+            if previous.sa_flags & libc::SA_SIGINFO != 0 {
+                let handler: Handler = std::mem::transmute(previous.sa_sigaction);
+                handler(signum, info, context);
+            } else {
+                let handler: extern "C" fn(libc::c_int) =
+                    std::mem::transmute(previous.sa_sigaction);
+                handler(signum);
+            }
+        }
+        _ => {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = libc::SIG_DFL;
+            libc::sigaction(signum, &action, std::ptr::null_mut());
+        }
+    }
+}
@@ -0,0 +1,216 @@
+//! Sparse file support: punching holes and finding the allocated parts of a file.
+//!
+//! Ranges are found with `lseek(SEEK_DATA/SEEK_HOLE)` where the platform has it, at the
+//! filesystem's block granularity. Elsewhere, or on filesystems without hole tracking,
+//! the whole file is reported as allocated.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::Path;
+
+use crate::errors::{MmapIoError, Result};
+use crate::mmap::{MapVariant, MemoryMappedFile, MmapMode};
+use crate::utils::ensure_in_bounds;
+
+const COPY_CHUNK: usize = 1 << 20;
+
+impl MemoryMappedFile {
+    /// Deallocate the disk blocks behind `[offset, offset + len)` while keeping the file
+    /// length (ReadWrite only).
+    ///
+    /// The range reads as zeros afterwards and the mapping stays valid; writing to it
+    /// allocates blocks again. Unflushed writes inside the range are discarded. On Linux this
+    /// is `fallocate(FALLOC_FL_PUNCH_HOLE | FALLOC_FL_KEEP_SIZE)`, which frees whole blocks
+    /// and zeroes the partial blocks at either end. Where holes are not supported the range
+    /// is only zeroed.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidMode` if not in `ReadWrite` mode or the mapping has no
+    /// backing file.
+    /// Returns `MmapIoError::OutOfBounds` if the range exceeds the mapping.
+    /// Returns `MmapIoError::Io` if the hole cannot be punched.
+    pub fn punch_hole(&self, offset: u64, len: u64) -> Result<()> {
+        if self.inner.mode != MmapMode::ReadWrite {
+            return Err(MmapIoError::InvalidMode(
+                "Punch hole requires ReadWrite mode.",
+            ));
+        }
+        ensure_in_bounds(offset, len, self.current_len()?)?;
+        if len == 0 {
+            return Ok(());
+        }
+        let file = self.inner.file()?;
+        let base = self.inner.window_offset.unwrap_or(0);
+        let punched = {
+            // Hold writers off so no write lands in the range while its blocks are dropped
+            let _guard = match &self.inner.map {
+                MapVariant::Rw(lock) => Some(lock.write()),
+                _ => None,
+            };
+            punch(file, base + offset, len)?
+        };
+        if !punched {
+            let zeros = vec![0u8; COPY_CHUNK.min(len as usize)];
+            let mut done = 0;
+            while done < len {
+                let n = (len - done).min(zeros.len() as u64);
+                self.update_region(offset + done, &zeros[..n as usize])?;
+                done += n;
+            }
+        }
+        Ok(())
+    }
+
+    /// Ranges of the mapping that have disk blocks allocated, in ascending order.
+    ///
+    /// Offsets are relative to the mapping. Anonymous mappings, and files on platforms or
+    /// filesystems without hole tracking, report a single range covering the mapping.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::Io` if the file cannot be inspected.
+    pub fn allocated_ranges(&self) -> Result<Vec<Range<u64>>> {
+        let len = self.current_len()?;
+        let Some(file) = &self.inner.file else {
+            return Ok(whole(0, len));
+        };
+        let base = self.inner.window_offset.unwrap_or(0);
+        Ok(data_ranges(file, base, base + len)?
+            .into_iter()
+            .map(|range| range.start - base..range.end - base)
+            .collect())
+    }
+
+    /// Ranges of the mapping that are holes, in ascending order: the gaps between
+    /// [`allocated_ranges`](Self::allocated_ranges).
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::Io` if the file cannot be inspected.
+    pub fn hole_ranges(&self) -> Result<Vec<Range<u64>>> {
+        let len = self.current_len()?;
+        let mut holes = Vec::new();
+        let mut pos = 0;
+        for range in self.allocated_ranges()? {
+            if range.start > pos {
+                holes.push(pos..range.start);
+            }
+            pos = range.end;
+        }
+        if pos < len {
+            holes.push(pos..len);
+        }
+        Ok(holes)
+    }
+}
+
+/// Copy `src` to `dst`, writing only the allocated ranges so that holes stay holes.
+/// Falls back to `fs::copy` when `src` has no holes.
+pub(crate) fn copy_sparse(src: &Path, dst: &Path) -> Result<()> {
+    let mut from = File::open(src)?;
+    let meta = from.metadata()?;
+    let ranges = data_ranges(&from, 0, meta.len())?;
+    if ranges == whole(0, meta.len()) {
+        fs::copy(src, dst)?;
+        return Ok(());
+    }
+    let mut to = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(dst)?;
+    to.set_len(meta.len())?;
+    let mut buf = vec![0u8; COPY_CHUNK];
+    for range in ranges {
+        from.seek(SeekFrom::Start(range.start))?;
+        to.seek(SeekFrom::Start(range.start))?;
+        let mut left = range.end - range.start;
+        while left > 0 {
+            let n = left.min(COPY_CHUNK as u64) as usize;
+            from.read_exact(&mut buf[..n])?;
+            to.write_all(&buf[..n])?;
+            left -= n as u64;
+        }
+    }
+    fs::set_permissions(dst, meta.permissions())?;
+    Ok(())
+}
+
+fn whole(start: u64, end: u64) -> Vec<Range<u64>> {
+    if start < end {
+        std::iter::once(start..end).collect()
+    } else {
+        Vec::new()
+    }
+}
+
+/// Allocated ranges of `file` within `[start, end)`, clipped to it.
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd"))]
+fn data_ranges(file: &File, start: u64, end: u64) -> Result<Vec<Range<u64>>> {
+    use std::os::unix::io::AsRawFd;
+    let fd = file.as_raw_fd();
+    // The returned offset is used directly, so the shared file position does not matter.
+    let seek = |pos: u64, whence| {
+        // SAFETY: plain syscall on an open descriptor.
+        match unsafe { libc::lseek(fd, pos as libc::off_t, whence) } {
+            -1 => Err(std::io::Error::last_os_error()),
+            found => Ok(found as u64),
+        }
+    };
+    let mut ranges = Vec::new();
+    let mut pos = start;
+    while pos < end {
+        let data = match seek(pos, libc::SEEK_DATA) {
+            Ok(data) => data,
+            // No data past `pos`
+            Err(err) if err.raw_os_error() == Some(libc::ENXIO) => break,
+            Err(err) if err.raw_os_error() == Some(libc::EINVAL) && ranges.is_empty() => {
+                return Ok(whole(start, end));
+            }
+            Err(err) => return Err(MmapIoError::Io(err)),
+        };
+        if data >= end {
+            break;
+        }
+        let hole = seek(data, libc::SEEK_HOLE)?.min(end);
+        ranges.push(data..hole);
+        pos = hole;
+    }
+    Ok(ranges)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "freebsd")))]
+fn data_ranges(_file: &File, start: u64, end: u64) -> Result<Vec<Range<u64>>> {
+    Ok(whole(start, end))
+}
+
+// Punch a hole, returning `false` if the filesystem cannot and the range must be zeroed.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn punch(file: &File, offset: u64, len: u64) -> Result<bool> {
+    use std::os::unix::io::AsRawFd;
+    let mode = libc::FALLOC_FL_PUNCH_HOLE | libc::FALLOC_FL_KEEP_SIZE;
+    // SAFETY: plain syscall on an open descriptor.
+    let ret = unsafe {
+        libc::fallocate(
+            file.as_raw_fd(),
+            mode,
+            offset as libc::off_t,
+            len as libc::off_t,
+        )
+    };
+    if ret == 0 {
+        return Ok(true);
+    }
+    let err = std::io::Error::last_os_error();
+    if err.raw_os_error() == Some(libc::EOPNOTSUPP) {
+        return Ok(false);
+    }
+    Err(MmapIoError::Io(err))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn punch(_file: &File, _offset: u64, _len: u64) -> Result<bool> {
+    Ok(false)
+}
@@ -0,0 +1,613 @@
+//! Immutable sorted string tables (SSTables) built and read through memory mappings.
+//!
+//! File layout:
+//!
+//! ```text
+//! data blocks   entries of key_len (u32) | value_len (u32) | key | value, sorted by key
+//! block index   one u64 offset per block; each block's first key is read from the data
+//! footer        index offset (8) | block count (8) | entry count (8) | version (4)
+//!               | reserved (4) | magic "MMAPSST1" (8)
+//! ```
+//!
+//! Integers are little-endian. The reader binary-searches the block index and scans a single
+//! block, returning keys and values straight from the read-only mapping.
+
+use std::ops::{Bound, RangeBounds};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::errors::{MmapIoError, Result};
+use crate::mmap::{MemoryMappedFile, MmapMode};
+use crate::segment::Segment;
+
+const MAGIC: [u8; 8] = *b"MMAPSST1";
+const VERSION: u32 = 1;
+const FOOTER_LEN: u64 = 40;
+const ENTRY_HEADER_LEN: u64 = 8;
+/// Default target size of a data block in bytes.
+pub const DEFAULT_BLOCK_SIZE: u64 = 4096;
+const INITIAL_FILE_LEN: u64 = 64 * 1024;
+
+/// Writes sorted key/value pairs into a new SSTable file.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mmap_io::sstable::SsTableBuilder;
+///
+/// let mut builder = SsTableBuilder::create("level0.sst")?;
+/// builder.add(b"apple", b"red")?;
+/// builder.add(b"banana", b"yellow")?;
+/// let table = builder.finish()?;
+///
+/// assert_eq!(table.get(b"apple")?, Some(&b"red"[..]));
+/// # Ok::<(), mmap_io::MmapIoError>(())
+/// ```
+#[derive(Debug)]
+pub struct SsTableBuilder {
+    mmap: MemoryMappedFile,
+    pos: u64,
+    block_start: u64,
+    block_size: u64,
+    index: Vec<u64>,
+    entries: u64,
+    last_key: Option<Vec<u8>>,
+}
+
+impl SsTableBuilder {
+    /// Start a new table at `path` (truncating if it exists).
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from `MemoryMappedFile::create_rw`.
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self {
+            mmap: MemoryMappedFile::create_rw(path, INITIAL_FILE_LEN)?,
+            pos: 0,
+            block_start: 0,
+            block_size: DEFAULT_BLOCK_SIZE,
+            index: Vec::new(),
+            entries: 0,
+            last_key: None,
+        })
+    }
+
+    /// Set the target data block size (default [`DEFAULT_BLOCK_SIZE`]). Smaller blocks make
+    /// point lookups scan less at the cost of a larger index.
+    #[must_use]
+    pub fn block_size(mut self, block_size: u64) -> Self {
+        self.block_size = block_size.max(1);
+        self
+    }
+
+    /// Append an entry. Keys must be added in strictly increasing byte order.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidMode` if `key` is not greater than the previous key or
+    /// either slice is 4 GiB or larger.
+    /// Returns errors from `MemoryMappedFile::resize` or `update_region`.
+    pub fn add(&mut self, key: &[u8], value: &[u8]) -> Result<()> {
+        if self.last_key.as_deref().is_some_and(|last| key <= last) {
+            return Err(MmapIoError::InvalidMode(
+                "SSTable keys must be added in strictly increasing order",
+            ));
+        }
+        let (Ok(key_len), Ok(value_len)) = (u32::try_from(key.len()), u32::try_from(value.len()))
+        else {
+            return Err(MmapIoError::InvalidMode(
+                "SSTable keys and values must be shorter than 4 GiB",
+            ));
+        };
+        if self.index.is_empty() || self.pos - self.block_start >= self.block_size {
+            self.block_start = self.pos;
+            self.index.push(self.pos);
+        }
+        let mut header = [0u8; ENTRY_HEADER_LEN as usize];
+        header[..4].copy_from_slice(&key_len.to_le_bytes());
+        header[4..].copy_from_slice(&value_len.to_le_bytes());
+        self.write(&header)?;
+        self.write(key)?;
+        self.write(value)?;
+        self.entries += 1;
+        self.last_key = Some(key.to_vec());
+        Ok(())
+    }
+
+    /// Write the index and footer, trim the file, flush it and open it for reading.
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from `MemoryMappedFile::resize`, `update_region` or `flush`, and from
+    /// [`SsTable::open`].
+    pub fn finish(mut self) -> Result<SsTable> {
+        let index_offset = self.pos;
+        let index: Vec<u8> = self.index.iter().flat_map(|o| o.to_le_bytes()).collect();
+        self.write(&index)?;
+        let mut footer = [0u8; FOOTER_LEN as usize];
+        footer[..8].copy_from_slice(&index_offset.to_le_bytes());
+        footer[8..16].copy_from_slice(&(self.index.len() as u64).to_le_bytes());
+        footer[16..24].copy_from_slice(&self.entries.to_le_bytes());
+        footer[24..28].copy_from_slice(&VERSION.to_le_bytes());
+        footer[32..].copy_from_slice(&MAGIC);
+        self.write(&footer)?;
+        self.mmap.resize(self.pos)?;
+        self.mmap.flush()?;
+        let path = self.mmap.path().to_path_buf();
+        drop(self.mmap);
+        SsTable::open(path)
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        let end = self.pos + bytes.len() as u64;
+        let total = self.mmap.current_len()?;
+        if end > total {
+            self.mmap.resize(end.max(total.saturating_mul(2)))?;
+        }
+        self.mmap.update_region(self.pos, bytes)?;
+        self.pos = end;
+        Ok(())
+    }
+}
+
+/// Read-only SSTable with zero-copy lookups and range scans.
+#[derive(Debug, Clone)]
+pub struct SsTable {
+    mmap: Arc<MemoryMappedFile>,
+    index_offset: u64,
+    blocks: u64,
+    entries: u64,
+}
+
+impl SsTable {
+    /// Map the table at `path` read-only.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidFormat` if the file is not a valid SSTable.
+    /// Returns errors from `MemoryMappedFile::open_ro`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_mmap(MemoryMappedFile::open_ro(path)?)
+    }
+
+    /// Read a table from an existing read-only or copy-on-write mapping.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidMode` for read-write mappings.
+    /// Returns `MmapIoError::InvalidFormat` if the footer or index is invalid.
+    pub fn from_mmap(mmap: MemoryMappedFile) -> Result<Self> {
+        if mmap.mode() == MmapMode::ReadWrite {
+            return Err(MmapIoError::InvalidMode(
+                "SsTable requires a ReadOnly or CopyOnWrite mapping",
+            ));
+        }
+        let total = mmap.current_len()?;
+        if total < FOOTER_LEN {
+            return Err(invalid("file too small for SSTable footer"));
+        }
+        let footer = mmap.as_slice(total - FOOTER_LEN, FOOTER_LEN)?;
+        if footer[32..] != MAGIC || read_u32(footer, 24)? != VERSION {
+            return Err(invalid("missing or unsupported SSTable footer"));
+        }
+        let index_offset = read_u64(footer, 0)?;
+        let blocks = read_u64(footer, 8)?;
+        let entries = read_u64(footer, 16)?;
+        let index_end = blocks
+            .checked_mul(8)
+            .and_then(|len| len.checked_add(index_offset));
+        if index_end != Some(total - FOOTER_LEN) || (blocks == 0) != (entries == 0) {
+            return Err(invalid("SSTable footer inconsistent with file length"));
+        }
+        let table = Self {
+            mmap: Arc::new(mmap),
+            index_offset,
+            blocks,
+            entries,
+        };
+        // Block offsets must be increasing and inside the data section
+        let mut prev = None;
+        for i in 0..blocks {
+            let offset = table.block_offset(i)?;
+            if offset >= index_offset || prev.is_some_and(|p| offset <= p) {
+                return Err(invalid("SSTable block index is not sorted"));
+            }
+            prev = Some(offset);
+        }
+        Ok(table)
+    }
+
+    /// Number of entries.
+    #[must_use]
+    pub fn len(&self) -> u64 {
+        self.entries
+    }
+
+    /// Whether the table has no entries.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries == 0
+    }
+
+    /// Underlying mapping.
+    #[must_use]
+    pub fn mmap(&self) -> &Arc<MemoryMappedFile> {
+        &self.mmap
+    }
+
+    /// Value stored for `key`, borrowed from the mapping.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidFormat` if the entry data is corrupt.
+    pub fn get(&self, key: &[u8]) -> Result<Option<&[u8]>> {
+        let Some(block) = self.seek_block(key)? else {
+            return Ok(None);
+        };
+        let data = self.data()?;
+        let mut pos = self.block_offset(block)?;
+        let end = self.block_end(block)?;
+        while pos < end {
+            let entry = parse_entry(data, pos)?;
+            match data[entry.key.clone()].cmp(key) {
+                std::cmp::Ordering::Less => pos = entry.next,
+                std::cmp::Ordering::Equal => return Ok(Some(&data[entry.value])),
+                std::cmp::Ordering::Greater => break,
+            }
+        }
+        Ok(None)
+    }
+
+    /// Iterate over all entries in key order.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidFormat` if the index is corrupt.
+    pub fn iter(&self) -> Result<Iter<'_>> {
+        self.range::<std::ops::RangeFull>(..)
+    }
+
+    /// Iterate over the entries whose keys fall in `range`, in key order, yielding
+    /// `Segment`s for key and value that point straight into the mapping.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidFormat` if the index or entry data is corrupt.
+    pub fn range<'k, R: RangeBounds<&'k [u8]>>(&self, range: R) -> Result<Iter<'_>> {
+        let data_end = self.index_offset;
+        let mut pos = data_end;
+        let start = match range.start_bound() {
+            Bound::Included(k) => Bound::Included(*k),
+            Bound::Excluded(k) => Bound::Excluded(*k),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        match start {
+            Bound::Unbounded => {
+                if self.blocks > 0 {
+                    pos = self.block_offset(0)?;
+                }
+            }
+            Bound::Included(key) | Bound::Excluded(key) => {
+                let data = self.data()?;
+                let first_block = self.seek_block(key)?.unwrap_or(0);
+                if self.blocks > 0 {
+                    pos = self.block_offset(first_block)?;
+                }
+                while pos < data_end {
+                    let entry = parse_entry(data, pos)?;
+                    let k = &data[entry.key];
+                    let before = match start {
+                        Bound::Included(_) => k < key,
+                        _ => k <= key,
+                    };
+                    if !before {
+                        break;
+                    }
+                    pos = entry.next;
+                }
+            }
+        }
+        let end = match range.end_bound() {
+            Bound::Included(k) => Bound::Included(k.to_vec()),
+            Bound::Excluded(k) => Bound::Excluded(k.to_vec()),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        Ok(Iter {
+            table: self,
+            pos,
+            end,
+        })
+    }
+
+    /// Last block whose first key is <= `key`, or `None` if `key` sorts before every block.
+    fn seek_block(&self, key: &[u8]) -> Result<Option<u64>> {
+        let data = self.data()?;
+        let (mut lo, mut hi) = (0, self.blocks);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            let first = parse_entry(data, self.block_offset(mid)?)?;
+            if &data[first.key] <= key {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        Ok(lo.checked_sub(1))
+    }
+
+    fn data(&self) -> Result<&[u8]> {
+        self.mmap.as_slice(0, self.index_offset)
+    }
+
+    fn block_offset(&self, block: u64) -> Result<u64> {
+        read_u64(self.mmap.as_slice(self.index_offset + block * 8, 8)?, 0)
+    }
+
+    fn block_end(&self, block: u64) -> Result<u64> {
+        if block + 1 < self.blocks {
+            self.block_offset(block + 1)
+        } else {
+            Ok(self.index_offset)
+        }
+    }
+}
+
+/// Iterator over SSTable entries, see [`SsTable::range`].
+#[derive(Debug)]
+pub struct Iter<'a> {
+    table: &'a SsTable,
+    pos: u64,
+    end: Bound<Vec<u8>>,
+}
+
+impl Iter<'_> {
+    fn next_entry(&mut self) -> Result<Option<(Segment, Segment)>> {
+        if self.pos >= self.table.index_offset {
+            return Ok(None);
+        }
+        let data = self.table.data()?;
+        let entry = parse_entry(data, self.pos)?;
+        let key = &data[entry.key.clone()];
+        let in_range = match &self.end {
+            Bound::Included(end) => key <= end.as_slice(),
+            Bound::Excluded(end) => key < end.as_slice(),
+            Bound::Unbounded => true,
+        };
+        if !in_range {
+            self.pos = self.table.index_offset;
+            return Ok(None);
+        }
+        self.pos = entry.next;
+        let segment = |r: std::ops::Range<usize>| {
+            Segment::new(
+                Arc::clone(&self.table.mmap),
+                r.start as u64,
+                (r.end - r.start) as u64,
+            )
+        };
+        Ok(Some((segment(entry.key)?, segment(entry.value)?)))
+    }
+}
+
+impl Iterator for Iter<'_> {
+    type Item = Result<(Segment, Segment)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_entry() {
+            Ok(entry) => entry.map(Ok),
+            Err(e) => {
+                // Stop after reporting corruption
+                self.pos = self.table.index_offset;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+struct Entry {
+    key: std::ops::Range<usize>,
+    value: std::ops::Range<usize>,
+    next: u64,
+}
+
+fn parse_entry(data: &[u8], pos: u64) -> Result<Entry> {
+    let key_len = u64::from(read_u32(data, pos)?);
+    let value_len = u64::from(read_u32(data, pos + 4)?);
+    let key_start = pos + ENTRY_HEADER_LEN;
+    let value_start = key_start + key_len;
+    let next = value_start + value_len;
+    if next > data.len() as u64 {
+        return Err(invalid("SSTable entry extends past the data section"));
+    }
+    Ok(Entry {
+        key: key_start as usize..value_start as usize,
+        value: value_start as usize..next as usize,
+        next,
+    })
+}
+
+fn read_u64(data: &[u8], offset: u64) -> Result<u64> {
+    let bytes = usize::try_from(offset)
+        .ok()
+        .and_then(|o| data.get(o..o.checked_add(8)?))
+        .ok_or_else(|| invalid("SSTable data truncated"))?;
+    Ok(u64::from_le_bytes(bytes.try_into().expect("8 bytes")))
+}
+
+fn read_u32(data: &[u8], offset: u64) -> Result<u32> {
+    let bytes = usize::try_from(offset)
+        .ok()
+        .and_then(|o| data.get(o..o.checked_add(4)?))
+        .ok_or_else(|| invalid("SSTable data truncated"))?;
+    Ok(u32::from_le_bytes(bytes.try_into().expect("4 bytes")))
+}
+
+fn invalid(msg: &str) -> MmapIoError {
+    MmapIoError::InvalidFormat(msg.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn tmp_path(name: &str) -> PathBuf {
+        let mut p = std::env::temp_dir();
+        p.push(format!(
+            "mmap_io_sstable_test_{}_{}",
+            name,
+            std::process::id()
+        ));
+        p
+    }
+
+    fn key(i: u32) -> Vec<u8> {
+        format!("key-{i:05}").into_bytes()
+    }
+
+    fn build(path: &Path, n: u32, block_size: u64) -> SsTable {
+        let mut builder = SsTableBuilder::create(path)
+            .expect("create")
+            .block_size(block_size);
+        for i in 0..n {
+            builder
+                .add(&key(i * 2), format!("value-{i}").as_bytes())
+                .expect("add");
+        }
+        builder.finish().expect("finish")
+    }
+
+    fn collect(iter: Iter<'_>) -> Vec<(Vec<u8>, Vec<u8>)> {
+        iter.map(|entry| {
+            let (k, v) = entry.expect("entry");
+            (
+                k.as_slice().expect("key").to_vec(),
+                v.as_slice().expect("value").to_vec(),
+            )
+        })
+        .collect()
+    }
+
+    #[test]
+    fn test_point_lookups_across_blocks() {
+        let path = tmp_path("lookup");
+        let _ = fs::remove_file(&path);
+
+        let table = build(&path, 1000, 256);
+        assert_eq!(table.len(), 1000);
+        assert!(table.blocks > 10);
+        for i in 0..1000 {
+            assert_eq!(
+                table.get(&key(i * 2)).expect("get"),
+                Some(format!("value-{i}").as_bytes())
+            );
+            assert_eq!(table.get(&key(i * 2 + 1)).expect("get missing"), None);
+        }
+        assert_eq!(table.get(b"").expect("before first"), None);
+        assert_eq!(table.get(b"zzz").expect("after last"), None);
+
+        // The file is trimmed to exactly data + index + footer
+        let reopened = SsTable::open(&path).expect("reopen");
+        assert_eq!(
+            reopened.mmap().len(),
+            reopened.index_offset + reopened.blocks * 8 + FOOTER_LEN
+        );
+
+        fs::remove_file(&path).expect("cleanup");
+    }
+
+    #[test]
+    fn test_range_iteration() {
+        let path = tmp_path("range");
+        let _ = fs::remove_file(&path);
+
+        let table = build(&path, 100, 128);
+        assert_eq!(collect(table.iter().expect("iter")).len(), 100);
+
+        let (lo, hi) = (key(10), key(20));
+        let inclusive = collect(table.range(lo.as_slice()..=hi.as_slice()).expect("range"));
+        let keys: Vec<_> = inclusive.iter().map(|(k, _)| k.clone()).collect();
+        assert_eq!(keys, [key(10), key(12), key(14), key(16), key(18), key(20)]);
+
+        let exclusive = collect(table.range(lo.as_slice()..hi.as_slice()).expect("range"));
+        assert_eq!(exclusive.len(), 5);
+        assert_eq!(exclusive[0].1, b"value-5");
+
+        // Bounds that fall between keys
+        let (lo, hi) = (key(11), key(15));
+        let between = collect(table.range(lo.as_slice()..hi.as_slice()).expect("range"));
+        assert_eq!(between.len(), 2);
+
+        let lo = key(196);
+        let start = (Bound::Excluded(lo.as_slice()), Bound::Unbounded);
+        let tail = collect(table.range(start).expect("range"));
+        assert_eq!(tail, [(key(198), b"value-99".to_vec())]);
+
+        let hi = key(3);
+        assert_eq!(
+            collect(table.range(..hi.as_slice()).expect("range")).len(),
+            2
+        );
+        let lo = b"zzz".as_slice();
+        assert!(collect(table.range(lo..).expect("range")).is_empty());
+
+        fs::remove_file(&path).expect("cleanup");
+    }
+
+    #[test]
+    fn test_builder_rejects_unsorted_keys_and_empty_table_works() {
+        let path = tmp_path("unsorted");
+        let _ = fs::remove_file(&path);
+
+        let mut builder = SsTableBuilder::create(&path).expect("create");
+        builder.add(b"b", b"1").expect("add");
+        assert!(builder.add(b"a", b"2").is_err());
+        assert!(builder.add(b"b", b"3").is_err());
+        builder.add(b"c", b"").expect("add");
+        let table = builder.finish().expect("finish");
+        assert_eq!(table.get(b"c").expect("get"), Some(&b""[..]));
+
+        let empty = SsTableBuilder::create(&path)
+            .expect("create")
+            .finish()
+            .expect("finish empty");
+        assert!(empty.is_empty());
+        assert_eq!(empty.get(b"a").expect("get"), None);
+        assert!(collect(empty.iter().expect("iter")).is_empty());
+
+        fs::remove_file(&path).expect("cleanup");
+    }
+
+    #[test]
+    fn test_open_rejects_invalid_files() {
+        let path = tmp_path("invalid");
+        let _ = fs::remove_file(&path);
+
+        crate::create_mmap(&path, 100).expect("create");
+        assert!(matches!(
+            SsTable::open(&path),
+            Err(MmapIoError::InvalidFormat(_))
+        ));
+
+        build(&path, 10, 64);
+        let rw = MemoryMappedFile::open_rw(&path).expect("rw");
+        assert!(matches!(
+            SsTable::from_mmap(rw),
+            Err(MmapIoError::InvalidMode(_))
+        ));
+        let len = fs::metadata(&path).expect("meta").len();
+        let bytes = fs::read(&path).expect("read");
+        // Prepending a byte breaks the footer/index relationship
+        let mut shifted = vec![0u8];
+        shifted.extend_from_slice(&bytes);
+        fs::write(&path, &shifted).expect("write");
+        assert_eq!(fs::metadata(&path).expect("meta").len(), len + 1);
+        assert!(matches!(
+            SsTable::open(&path),
+            Err(MmapIoError::InvalidFormat(_))
+        ));
+
+        fs::remove_file(&path).expect("cleanup");
+    }
+}
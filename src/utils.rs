@@ -1,6 +1,7 @@
 //! Utility helpers for alignment, page size, and safe range calculations.
 
 use crate::errors::{MmapIoError, Result};
+use std::path::Path;
 
 /// Get the system page size in bytes.
 #[must_use]
@@ -58,6 +59,95 @@ fn unix_page_size() -> usize {
     }
 }
 
+/// Bytes available to unprivileged users on the filesystem holding `path`.
+///
+/// # Errors
+///
+/// Returns `MmapIoError::Io` if the filesystem cannot be queried.
+pub fn available_space<P: AsRef<Path>>(path: P) -> Result<u64> {
+    cfg_if::cfg_if! {
+        if #[cfg(target_os = "windows")] {
+            windows_available_space(path.as_ref())
+        } else {
+            unix_available_space(path.as_ref())
+        }
+    }
+}
+
+/// [`available_space`] for an open file, queried through its descriptor where the
+/// platform allows it.
+pub(crate) fn file_available_space(file: &std::fs::File, path: &Path) -> Result<u64> {
+    cfg_if::cfg_if! {
+        if #[cfg(target_os = "windows")] {
+            let _ = file;
+            windows_available_space(path)
+        } else {
+            use std::os::unix::io::AsRawFd;
+            let _ = path;
+            // SAFETY: the descriptor is open for the duration of the call.
+            statvfs_available(|stat| unsafe { libc::fstatvfs(file.as_raw_fd(), stat) })
+        }
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+fn unix_available_space(path: &Path) -> Result<u64> {
+    use std::os::unix::ffi::OsStrExt;
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes())
+        .map_err(|e| MmapIoError::Io(std::io::Error::new(std::io::ErrorKind::InvalidInput, e)))?;
+    // SAFETY: `path` is NUL-terminated.
+    statvfs_available(|stat| unsafe { libc::statvfs(path.as_ptr(), stat) })
+}
+
+// Runs a `statvfs`-style query that fills `*stat` and returns 0 on success.
+#[cfg(not(target_os = "windows"))]
+fn statvfs_available(query: impl FnOnce(*mut libc::statvfs) -> libc::c_int) -> Result<u64> {
+    let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    if query(stat.as_mut_ptr()) != 0 {
+        return Err(MmapIoError::Io(std::io::Error::last_os_error()));
+    }
+    // SAFETY: the query succeeded, so it filled `stat`.
+    let stat = unsafe { stat.assume_init() };
+    #[allow(clippy::useless_conversion)]
+    Ok(u64::from(stat.f_bavail).saturating_mul(u64::from(stat.f_frsize)))
+}
+
+#[cfg(target_os = "windows")]
+fn windows_available_space(path: &Path) -> Result<u64> {
+    use std::os::windows::ffi::OsStrExt;
+    extern "system" {
+        fn GetDiskFreeSpaceExW(
+            lpDirectoryName: *const u16,
+            lpFreeBytesAvailableToCaller: *mut u64,
+            lpTotalNumberOfBytes: *mut u64,
+            lpTotalNumberOfFreeBytes: *mut u64,
+        ) -> i32;
+    }
+    // The query needs a directory; use the parent of a file that may not exist yet.
+    let dir = if path.is_dir() {
+        path
+    } else {
+        path.parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .unwrap_or_else(|| Path::new("."))
+    };
+    let wide: Vec<u16> = dir.as_os_str().encode_wide().chain(Some(0)).collect();
+    let mut available = 0u64;
+    // SAFETY: `wide` is NUL-terminated; null is allowed for the totals we do not need.
+    let ok = unsafe {
+        GetDiskFreeSpaceExW(
+            wide.as_ptr(),
+            &mut available,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    };
+    if ok == 0 {
+        return Err(MmapIoError::Io(std::io::Error::last_os_error()));
+    }
+    Ok(available)
+}
+
 /// Align a value up to the nearest multiple of `alignment`.
 #[must_use]
 pub fn align_up(value: u64, alignment: u64) -> u64 {
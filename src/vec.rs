@@ -0,0 +1,556 @@
+//! Growable persistent vector of plain-old-data values stored in a memory-mapped file.
+//!
+//! The file starts with a 64-byte header (magic, element size, length, capacity) followed by
+//! the elements. The file grows geometrically through [`MemoryMappedFile::resize`], and
+//! reopening it restores the vector.
+
+use std::marker::PhantomData;
+use std::path::Path;
+
+use crate::errors::{MmapIoError, Result};
+use crate::mmap::{MappedSlice, MemoryMappedFile, MmapMode};
+use crate::pod::{bytes_of, bytes_of_slice, read_pod, Pod};
+
+const MAGIC: [u8; 8] = *b"MMAPVEC\0";
+const VERSION: u32 = 1;
+// Header layout: magic (8) | version (4) | element size (4) | len (8) | capacity (8) | reserved
+const HEADER_LEN: u64 = 64;
+const ELEM_SIZE_OFFSET: u64 = 12;
+const LEN_OFFSET: u64 = 16;
+const CAPACITY_OFFSET: u64 = 24;
+// Smallest capacity allocated when the vector first grows.
+const MIN_CAPACITY: u64 = 16;
+
+/// Growable vector of `T` persisted in a memory-mapped file.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mmap_io::vec::MmapVec;
+///
+/// let mut v = MmapVec::<u64>::create("numbers.vec", 0)?;
+/// v.push(1)?;
+/// v.extend_from_slice(&[2, 3, 4])?;
+/// v.flush()?;
+/// drop(v);
+///
+/// let v = MmapVec::<u64>::open("numbers.vec")?;
+/// assert_eq!(&*v.as_slice()?, &[1, 2, 3, 4]);
+/// # Ok::<(), mmap_io::MmapIoError>(())
+/// ```
+pub struct MmapVec<T: Pod> {
+    mmap: MemoryMappedFile,
+    len: u64,
+    capacity: u64,
+    _marker: PhantomData<T>,
+}
+
+impl<T: Pod> std::fmt::Debug for MmapVec<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MmapVec")
+            .field("path", &self.mmap.path())
+            .field("len", &self.len)
+            .field("capacity", &self.capacity)
+            .finish()
+    }
+}
+
+impl<T: Pod> MmapVec<T> {
+    const ELEM_SIZE: u64 = std::mem::size_of::<T>() as u64;
+
+    /// Create a new vector file (truncating if it exists) with room for `capacity` elements.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidMode` for zero-sized element types.
+    /// Returns `MmapIoError::Misaligned` if `T` needs more than 64-byte alignment.
+    /// Returns errors from `MemoryMappedFile::create_rw`.
+    pub fn create<P: AsRef<Path>>(path: P, capacity: u64) -> Result<Self> {
+        Self::check_type()?;
+        let size = Self::file_len(capacity)?;
+        Self::new_in(MemoryMappedFile::create_rw(path, size)?)
+    }
+
+    /// Open an existing vector file read-write.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidFormat` if the file is not a vector of `T`.
+    /// Returns errors from `MemoryMappedFile::open_rw`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_mmap(MemoryMappedFile::open_rw(path)?)
+    }
+
+    /// Format an existing mapping as an empty vector, using all space after the header as capacity.
+    /// Works with any read-write mapping, including anonymous and temporary ones.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidMode` if the mapping is not read-write.
+    /// Returns `MmapIoError::OutOfBounds` if the mapping is smaller than the header.
+    pub fn new_in(mmap: MemoryMappedFile) -> Result<Self> {
+        Self::check_type()?;
+        if mmap.mode() != MmapMode::ReadWrite {
+            return Err(MmapIoError::InvalidMode(
+                "MmapVec::new_in requires ReadWrite mode",
+            ));
+        }
+        let total = mmap.current_len()?;
+        if total < HEADER_LEN {
+            return Err(MmapIoError::OutOfBounds {
+                offset: 0,
+                len: HEADER_LEN,
+                total,
+            });
+        }
+        let capacity = (total - HEADER_LEN) / Self::ELEM_SIZE;
+        let mut header = [0u8; HEADER_LEN as usize];
+        header[..8].copy_from_slice(&MAGIC);
+        header[8..12].copy_from_slice(&VERSION.to_le_bytes());
+        header[12..16].copy_from_slice(&(Self::ELEM_SIZE as u32).to_le_bytes());
+        header[24..32].copy_from_slice(&capacity.to_le_bytes());
+        mmap.update_region(0, &header)?;
+        Ok(Self {
+            mmap,
+            len: 0,
+            capacity,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Open a vector previously stored in `mmap`. Read-only mappings give a read-only vector.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidFormat` if the header is missing, was written for a
+    /// different element size, or describes more data than the mapping holds.
+    pub fn from_mmap(mmap: MemoryMappedFile) -> Result<Self> {
+        Self::check_type()?;
+        let total = mmap.current_len()?;
+        if total < HEADER_LEN {
+            return Err(MmapIoError::InvalidFormat(
+                "file too small for MmapVec header".into(),
+            ));
+        }
+        let magic: [u8; 8] = read_pod(&mmap, 0)?;
+        let version: u32 = read_pod(&mmap, 8)?;
+        if magic != MAGIC || u32::from_le(version) != VERSION {
+            return Err(MmapIoError::InvalidFormat(
+                "missing or unsupported MmapVec header".into(),
+            ));
+        }
+        let elem_size = u32::from_le(read_pod(&mmap, ELEM_SIZE_OFFSET)?);
+        if u64::from(elem_size) != Self::ELEM_SIZE {
+            return Err(MmapIoError::InvalidFormat(format!(
+                "element size mismatch: file has {elem_size}, expected {}",
+                Self::ELEM_SIZE
+            )));
+        }
+        let len = u64::from_le(read_pod(&mmap, LEN_OFFSET)?);
+        let capacity = u64::from_le(read_pod(&mmap, CAPACITY_OFFSET)?);
+        if len > capacity || Self::file_len(capacity)? > total {
+            return Err(MmapIoError::InvalidFormat(format!(
+                "header describes len={len}, capacity={capacity} but file holds {total} bytes"
+            )));
+        }
+        Ok(Self {
+            mmap,
+            len,
+            capacity,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Number of elements.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    /// Whether the vector has no elements.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of elements the file can hold without growing.
+    #[must_use]
+    pub fn capacity(&self) -> usize {
+        self.capacity as usize
+    }
+
+    /// Underlying mapping.
+    #[must_use]
+    pub fn mmap(&self) -> &MemoryMappedFile {
+        &self.mmap
+    }
+
+    /// Append an element, growing the file if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidMode` for read-only vectors.
+    /// Returns errors from `MemoryMappedFile::resize` or `update_region`.
+    pub fn push(&mut self, value: T) -> Result<()> {
+        self.extend_from_slice(std::slice::from_ref(&value))
+    }
+
+    /// Append all elements of `values`, growing the file at most once.
+    ///
+    /// Elements are written before the persisted length is updated, so a crash never exposes
+    /// uninitialized elements.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidMode` for read-only vectors.
+    /// Returns errors from `MemoryMappedFile::resize` or `update_region`.
+    pub fn extend_from_slice(&mut self, values: &[T]) -> Result<()> {
+        if values.is_empty() {
+            return Ok(());
+        }
+        self.reserve(values.len())?;
+        self.mmap
+            .update_region(Self::elem_offset(self.len), bytes_of_slice(values))?;
+        self.set_len(self.len + values.len() as u64)
+    }
+
+    /// Ensure room for at least `additional` more elements. Capacity at least doubles when
+    /// the file has to grow, so repeated pushes are amortized O(1).
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from `MemoryMappedFile::resize`.
+    pub fn reserve(&mut self, additional: usize) -> Result<()> {
+        let required = self
+            .len
+            .checked_add(additional as u64)
+            .ok_or_else(|| MmapIoError::ResizeFailed("MmapVec capacity overflow".into()))?;
+        if required <= self.capacity {
+            return Ok(());
+        }
+        let new_capacity = required
+            .max(self.capacity.saturating_mul(2))
+            .max(MIN_CAPACITY);
+        self.set_capacity(new_capacity)
+    }
+
+    /// Copy out the element at `index`, or `None` if out of range.
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from `MemoryMappedFile::read_into`.
+    pub fn get(&self, index: usize) -> Result<Option<T>> {
+        if index as u64 >= self.len {
+            return Ok(None);
+        }
+        read_pod(&self.mmap, Self::elem_offset(index as u64)).map(Some)
+    }
+
+    /// Overwrite the element at `index`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::OutOfBounds` if `index >= len()`.
+    /// Returns errors from `MemoryMappedFile::update_region`.
+    pub fn set(&mut self, index: usize, value: T) -> Result<()> {
+        if index as u64 >= self.len {
+            return Err(MmapIoError::OutOfBounds {
+                offset: index as u64,
+                len: 1,
+                total: self.len,
+            });
+        }
+        self.mmap
+            .update_region(Self::elem_offset(index as u64), bytes_of(&value))
+    }
+
+    /// Zero-copy view of all elements. For read-write mappings the view holds the
+    /// mapping's read lock until dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from `MemoryMappedFile::as_slice_guarded`.
+    pub fn as_slice(&self) -> Result<MmapVecSlice<'_, T>> {
+        let bytes = self
+            .mmap
+            .as_slice_guarded(HEADER_LEN, self.len * Self::ELEM_SIZE)?;
+        let align = std::mem::align_of::<T>();
+        if bytes.as_ptr() as usize % align != 0 {
+            return Err(MmapIoError::Misaligned {
+                required: align as u64,
+                offset: HEADER_LEN,
+            });
+        }
+        Ok(MmapVecSlice {
+            bytes,
+            len: self.len as usize,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Iterate over copies of the elements. Holds the same lock as [`MmapVec::as_slice`].
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from [`MmapVec::as_slice`].
+    pub fn iter(&self) -> Result<Iter<'_, T>> {
+        Ok(Iter {
+            slice: self.as_slice()?,
+            pos: 0,
+        })
+    }
+
+    /// Shorten the vector to `len` elements. Has no effect if `len >= self.len()`.
+    /// The file keeps its capacity; see [`MmapVec::shrink_to_fit`].
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from `MemoryMappedFile::update_region`.
+    pub fn truncate(&mut self, len: usize) -> Result<()> {
+        if len as u64 >= self.len {
+            return Ok(());
+        }
+        self.set_len(len as u64)
+    }
+
+    /// Remove all elements.
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from `MemoryMappedFile::update_region`.
+    pub fn clear(&mut self) -> Result<()> {
+        self.truncate(0)
+    }
+
+    /// Shrink the file so that capacity equals length.
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from `MemoryMappedFile::resize`.
+    pub fn shrink_to_fit(&mut self) -> Result<()> {
+        if self.capacity == self.len {
+            return Ok(());
+        }
+        self.set_capacity(self.len)
+    }
+
+    /// Flush elements and header to disk.
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from `MemoryMappedFile::flush`.
+    pub fn flush(&self) -> Result<()> {
+        self.mmap.flush()
+    }
+
+    fn set_len(&mut self, len: u64) -> Result<()> {
+        self.mmap.update_region(LEN_OFFSET, &len.to_le_bytes())?;
+        self.len = len;
+        Ok(())
+    }
+
+    fn set_capacity(&mut self, capacity: u64) -> Result<()> {
+        self.mmap.resize(Self::file_len(capacity)?)?;
+        self.mmap
+            .update_region(CAPACITY_OFFSET, &capacity.to_le_bytes())?;
+        self.capacity = capacity;
+        Ok(())
+    }
+
+    fn elem_offset(index: u64) -> u64 {
+        HEADER_LEN + index * Self::ELEM_SIZE
+    }
+
+    fn file_len(capacity: u64) -> Result<u64> {
+        capacity
+            .checked_mul(Self::ELEM_SIZE)
+            .and_then(|bytes| bytes.checked_add(HEADER_LEN))
+            .ok_or_else(|| MmapIoError::ResizeFailed("MmapVec capacity overflow".into()))
+    }
+
+    fn check_type() -> Result<()> {
+        if Self::ELEM_SIZE == 0 {
+            return Err(MmapIoError::InvalidMode(
+                "MmapVec does not support zero-sized types",
+            ));
+        }
+        let align = std::mem::align_of::<T>() as u64;
+        if HEADER_LEN % align != 0 {
+            return Err(MmapIoError::Misaligned {
+                required: align,
+                offset: HEADER_LEN,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Zero-copy view of the elements of an [`MmapVec`].
+pub struct MmapVecSlice<'a, T: Pod> {
+    bytes: MappedSlice<'a>,
+    len: usize,
+    _marker: PhantomData<&'a [T]>,
+}
+
+impl<T: Pod> std::ops::Deref for MmapVecSlice<'_, T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        // SAFETY: Alignment was checked when the view was created, the byte range holds
+        // exactly `len` elements, and any bit pattern is a valid T.
+        unsafe { std::slice::from_raw_parts(self.bytes.as_ptr().cast::<T>(), self.len) }
+    }
+}
+
+/// Iterator over copies of the elements of an [`MmapVec`].
+pub struct Iter<'a, T: Pod> {
+    slice: MmapVecSlice<'a, T>,
+    pos: usize,
+}
+
+impl<T: Pod> Iterator for Iter<'_, T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let item = self.slice.get(self.pos).copied()?;
+        self.pos += 1;
+        Some(item)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.slice.len - self.pos;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<T: Pod> ExactSizeIterator for Iter<'_, T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn tmp_path(name: &str) -> PathBuf {
+        let mut p = std::env::temp_dir();
+        p.push(format!("mmap_io_vec_test_{}_{}", name, std::process::id()));
+        p
+    }
+
+    #[test]
+    fn test_push_get_and_reopen() {
+        let path = tmp_path("reopen");
+        let _ = fs::remove_file(&path);
+
+        let mut v = MmapVec::<u64>::create(&path, 0).expect("create");
+        assert!(v.is_empty());
+        for i in 0..100u64 {
+            v.push(i * 3).expect("push");
+        }
+        v.extend_from_slice(&[7, 8, 9]).expect("extend");
+        assert_eq!(v.len(), 103);
+        assert_eq!(v.get(10).expect("get"), Some(30));
+        assert_eq!(v.get(103).expect("get"), None);
+        v.set(0, 42).expect("set");
+        v.flush().expect("flush");
+        drop(v);
+
+        let v = MmapVec::<u64>::open(&path).expect("open");
+        assert_eq!(v.len(), 103);
+        let slice = v.as_slice().expect("slice");
+        assert_eq!(slice[0], 42);
+        assert_eq!(slice[99], 297);
+        assert_eq!(&slice[100..], &[7, 8, 9]);
+        drop(slice);
+        assert_eq!(
+            v.iter().expect("iter").sum::<u64>(),
+            v.as_slice().expect("slice").iter().sum()
+        );
+
+        fs::remove_file(&path).expect("cleanup");
+    }
+
+    #[test]
+    fn test_geometric_growth_truncate_and_shrink() {
+        let path = tmp_path("growth");
+        let _ = fs::remove_file(&path);
+
+        let mut v = MmapVec::<u32>::create(&path, 0).expect("create");
+        v.push(1).expect("push");
+        assert_eq!(v.capacity(), MIN_CAPACITY as usize);
+        let mut resizes = 0;
+        let mut last_capacity = v.capacity();
+        for i in 0..1000 {
+            v.push(i).expect("push");
+            if v.capacity() != last_capacity {
+                assert!(
+                    v.capacity() >= last_capacity * 2,
+                    "growth must be geometric"
+                );
+                last_capacity = v.capacity();
+                resizes += 1;
+            }
+        }
+        assert!(resizes <= 7, "too many resizes: {resizes}");
+
+        v.truncate(10).expect("truncate");
+        assert_eq!(v.len(), 10);
+        assert_eq!(v.get(10).expect("get"), None);
+        v.shrink_to_fit().expect("shrink");
+        assert_eq!(v.capacity(), 10);
+        assert_eq!(v.mmap().len(), HEADER_LEN + 40);
+        drop(v);
+
+        let v = MmapVec::<u32>::open(&path).expect("reopen");
+        assert_eq!(v.len(), 10);
+        assert_eq!(v.capacity(), 10);
+
+        fs::remove_file(&path).expect("cleanup");
+    }
+
+    #[test]
+    fn test_open_rejects_wrong_type_and_garbage() {
+        let path = tmp_path("wrong_type");
+        let _ = fs::remove_file(&path);
+
+        let mut v = MmapVec::<u32>::create(&path, 4).expect("create");
+        v.push(1).expect("push");
+        drop(v);
+        assert!(matches!(
+            MmapVec::<u64>::open(&path),
+            Err(MmapIoError::InvalidFormat(_))
+        ));
+
+        crate::create_mmap(&path, 128).expect("overwrite");
+        assert!(matches!(
+            MmapVec::<u32>::open(&path),
+            Err(MmapIoError::InvalidFormat(_))
+        ));
+
+        fs::remove_file(&path).expect("cleanup");
+    }
+
+    #[test]
+    fn test_read_only_vector_and_anonymous_backing() {
+        let path = tmp_path("read_only");
+        let _ = fs::remove_file(&path);
+
+        let mut v = MmapVec::<[u8; 4]>::create(&path, 2).expect("create");
+        v.push(*b"abcd").expect("push");
+        v.flush().expect("flush");
+        drop(v);
+
+        let mut ro = MmapVec::<[u8; 4]>::from_mmap(MemoryMappedFile::open_ro(&path).expect("ro"))
+            .expect("from_mmap");
+        assert_eq!(&*ro.as_slice().expect("slice"), &[*b"abcd"]);
+        assert!(ro.push(*b"efgh").is_err());
+
+        let mut anon =
+            MmapVec::<i64>::new_in(MemoryMappedFile::anonymous(HEADER_LEN).expect("anon"))
+                .expect("new_in");
+        anon.extend_from_slice(&[-1, -2, -3]).expect("extend");
+        assert_eq!(&*anon.as_slice().expect("slice"), &[-1, -2, -3]);
+
+        fs::remove_file(&path).expect("cleanup");
+    }
+}
@@ -1,9 +1,12 @@
 //! File change watching and notification support.
 
 use crate::errors::Result;
-use crate::mmap::MemoryMappedFile;
+use crate::mmap::{LengthChange, MappedSlice, MemoryMappedFile};
+use parking_lot::{Condvar, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 // Watch polling interval in milliseconds
 const WATCH_POLL_INTERVAL_MS: u64 = 100;
@@ -32,15 +35,17 @@ pub struct ChangeEvent {
 
 /// Handle for controlling a file watch operation.
 pub struct WatchHandle {
-    // Thread handle is kept to ensure the watch thread is properly joined on drop
+    // Thread handle is kept so callers can check whether the watch thread is still running
     thread: thread::JoinHandle<()>,
+    // Set on drop; the watch thread checks it after every poll and exits
+    stop: Arc<AtomicBool>,
 }
 
 impl Drop for WatchHandle {
     fn drop(&mut self) {
-        // The thread will naturally exit when it detects the file is removed
-        // or when the handle is dropped. We don't join here to avoid blocking.
-        // The thread will clean up on its own.
+        // The thread exits at its next poll, or earlier if it detects the file was
+        // removed. We don't join here to avoid blocking for up to a poll interval.
+        self.stop.store(true, Ordering::Release);
     }
 }
 
@@ -91,6 +96,8 @@ impl MemoryMappedFile {
         F: Fn(ChangeEvent) + Send + 'static,
     {
         let path = self.path().to_path_buf();
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = Arc::clone(&stop);
 
         // For this implementation, we'll use a simple polling approach
         // In a production implementation, you'd use platform-specific APIs
@@ -101,6 +108,9 @@ impl MemoryMappedFile {
 
             loop {
                 thread::sleep(Duration::from_millis(WATCH_POLL_INTERVAL_MS));
+                if stopped.load(Ordering::Acquire) {
+                    break;
+                }
 
                 // Check if file still exists
                 let metadata = match std::fs::metadata(&path) {
@@ -129,7 +139,121 @@ impl MemoryMappedFile {
             }
         });
 
-        Ok(WatchHandle { thread })
+        Ok(WatchHandle { thread, stop })
+    }
+
+    /// Follow the file as another process appends to it, the way `tail -f` does.
+    ///
+    /// The returned [`Follower`] starts at the current end of the file and refreshes the
+    /// mapping whenever the file changes. It takes ownership of the mapping because
+    /// [`refresh`](MemoryMappedFile::refresh) needs the only handle, so clones must be
+    /// dropped first.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use mmap_io::MemoryMappedFile;
+    /// use std::time::Duration;
+    ///
+    /// let mut follower = MemoryMappedFile::open_ro("app.log")?.follow()?;
+    /// loop {
+    ///     if let Some(new) = follower.wait_new(Duration::from_secs(1))? {
+    ///         print!("{}", String::from_utf8_lossy(&new));
+    ///     }
+    /// }
+    /// # Ok::<(), mmap_io::MmapIoError>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from [`watch`](Self::watch).
+    #[cfg(feature = "watch")]
+    pub fn follow(self) -> Result<Follower> {
+        let signal = Arc::new((Mutex::new(false), Condvar::new()));
+        let notify = Arc::clone(&signal);
+        let watch = self.watch(move |_event| {
+            *notify.0.lock() = true;
+            notify.1.notify_all();
+        })?;
+        Ok(Follower {
+            position: self.len(),
+            mmap: self,
+            signal,
+            _watch: watch,
+        })
+    }
+}
+
+/// A mapping that follows a growing file, created by [`MemoryMappedFile::follow`].
+///
+/// Each call to [`wait_new`](Self::wait_new) returns the bytes appended since the previous
+/// one. Watch events wake a waiting reader early; the file length is also polled, so growth
+/// that does not change the modification time is still seen.
+#[cfg(feature = "watch")]
+pub struct Follower {
+    mmap: MemoryMappedFile,
+    position: u64,
+    signal: Arc<(Mutex<bool>, Condvar)>,
+    _watch: WatchHandle,
+}
+
+#[cfg(feature = "watch")]
+impl Follower {
+    /// The followed mapping.
+    #[must_use]
+    pub fn mmap(&self) -> &MemoryMappedFile {
+        &self.mmap
+    }
+
+    /// Offset up to which bytes have been returned.
+    #[must_use]
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Move the position, e.g. to 0 to replay the file from the start.
+    pub fn set_position(&mut self, position: u64) {
+        self.position = position;
+    }
+
+    /// Refresh the mapping now. If the file was truncated below the position, the position
+    /// moves to the new end.
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from [`MemoryMappedFile::refresh`].
+    pub fn refresh(&mut self) -> Result<LengthChange> {
+        let change = self.mmap.refresh()?;
+        self.position = self.position.min(self.mmap.len());
+        Ok(change)
+    }
+
+    /// Wait up to `timeout` for bytes past the position and return all of them, advancing
+    /// the position to the end of the file. Returns `None` if nothing was appended in time.
+    ///
+    /// # Errors
+    ///
+    /// Returns errors from [`refresh`](Self::refresh).
+    pub fn wait_new(&mut self, timeout: Duration) -> Result<Option<MappedSlice<'_>>> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            self.refresh()?;
+            let len = self.mmap.len();
+            if len > self.position {
+                let start = std::mem::replace(&mut self.position, len);
+                return self.mmap.as_slice_guarded(start, len - start).map(Some);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(None);
+            }
+            let poll = now + Duration::from_millis(WATCH_POLL_INTERVAL_MS);
+            let mut changed = self.signal.0.lock();
+            if !*changed {
+                self.signal.1.wait_until(&mut changed, poll.min(deadline));
+            }
+            *changed = false;
+        }
     }
 }
 
@@ -144,6 +268,8 @@ where
     F: Fn(ChangeEvent) + Send + 'static,
 {
     let path = path.to_path_buf();
+    let stop = Arc::new(AtomicBool::new(false));
+    let stopped = Arc::clone(&stop);
 
     let thread = thread::spawn(move || {
         let mut last_modified = std::fs::metadata(&path)
@@ -153,6 +279,9 @@ where
 
         loop {
             thread::sleep(Duration::from_millis(WATCH_POLL_INTERVAL_MS));
+            if stopped.load(Ordering::Acquire) {
+                break;
+            }
 
             // Check if file still exists
             let metadata = match std::fs::metadata(&path) {
@@ -190,7 +319,7 @@ where
         }
     });
 
-    Ok(WatchHandle { thread })
+    Ok(WatchHandle { thread, stop })
 }
 
 #[cfg(test)]
@@ -415,4 +544,88 @@ mod tests {
 
         fs::remove_file(&path).expect("cleanup");
     }
+
+    #[test]
+    #[cfg(feature = "watch")]
+    fn test_follow_growing_file() {
+        use std::io::Write;
+
+        let path = tmp_path("follow");
+        fs::write(&path, b"old line\n").expect("seed file");
+
+        let mut follower = MemoryMappedFile::open_ro(&path)
+            .expect("open ro")
+            .follow()
+            .expect("follow");
+        assert_eq!(follower.position(), 9);
+        assert!(follower
+            .wait_new(Duration::from_millis(50))
+            .expect("wait")
+            .is_none());
+
+        let writer = {
+            let path = path.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(WATCH_POLL_INTERVAL_MS));
+                let mut file = fs::OpenOptions::new()
+                    .append(true)
+                    .open(&path)
+                    .expect("open for append");
+                file.write_all(b"new line\n").expect("append");
+            })
+        };
+        let new = follower
+            .wait_new(Duration::from_secs(5))
+            .expect("wait")
+            .expect("appended bytes");
+        assert_eq!(&*new, b"new line\n");
+        drop(new);
+        writer.join().expect("writer");
+        assert_eq!(follower.position(), 18);
+
+        follower.set_position(0);
+        let all = follower
+            .wait_new(Duration::ZERO)
+            .expect("wait")
+            .expect("replay");
+        assert_eq!(&*all, b"old line\nnew line\n");
+
+        drop(all);
+        drop(follower);
+        fs::remove_file(&path).expect("cleanup");
+    }
+
+    #[test]
+    #[cfg(feature = "watch")]
+    fn test_dropping_handles_stops_watch_threads() {
+        let path = tmp_path("stop");
+        create_mmap(&path, 64).expect("create");
+
+        // The callback (and what it captures) is dropped when the thread exits
+        let token = Arc::new(());
+        let captured = Arc::clone(&token);
+        let mmap = MemoryMappedFile::open_ro(&path).expect("open");
+        let handle = mmap
+            .watch(move |_| {
+                let _ = &captured;
+            })
+            .expect("watch");
+        assert!(handle.is_active());
+        drop(handle);
+
+        let follower = mmap.follow().expect("follow");
+        let signal = Arc::clone(&follower.signal);
+        drop(follower);
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while (Arc::strong_count(&token) > 1 || Arc::strong_count(&signal) > 1)
+            && Instant::now() < deadline
+        {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(Arc::strong_count(&token), 1, "watch thread still running");
+        assert_eq!(Arc::strong_count(&signal), 1, "follow thread still running");
+
+        fs::remove_file(&path).expect("cleanup");
+    }
 }
@@ -0,0 +1,340 @@
+//! Sliding-window access to files larger than the address space.
+//!
+//! A [`WindowedFile`] exposes the whole file's offset space but keeps only a few fixed-size
+//! windows mapped at a time. Windows start at multiples of the window size and are mapped on
+//! first use with the builder's `offset`/`len`. When all slots are taken, the least recently
+//! used window is flushed and unmapped. Peak address space use is bounded by
+//! `window_size * max_windows`, independent of the file length.
+
+use std::path::{Path, PathBuf};
+
+use parking_lot::Mutex;
+
+use crate::errors::{MmapIoError, Result};
+use crate::mmap::{MemoryMappedFile, MmapMode};
+use crate::utils::{align_up, ensure_in_bounds, page_size};
+
+const DEFAULT_WINDOW_SIZE: u64 = 64 * 1024 * 1024;
+const DEFAULT_MAX_WINDOWS: usize = 4;
+
+struct Window {
+    start: u64,
+    map: MemoryMappedFile,
+    last_used: u64,
+}
+
+struct Windows {
+    mapped: Vec<Window>,
+    clock: u64,
+}
+
+/// A file accessed through a small LRU of mapped windows.
+///
+/// The file length is read at open and fixed; offsets past it are out of bounds. Accesses
+/// that cross a window boundary are split across both windows.
+///
+/// # Examples
+///
+/// ```no_run
+/// use mmap_io::{MmapMode, WindowedFile};
+///
+/// let file = WindowedFile::open("huge.bin", MmapMode::ReadWrite)?
+///     .window_size(16 << 20)
+///     .max_windows(8);
+/// file.update_region(5 << 30, b"far into the file")?;
+/// let mut buf = [0u8; 17];
+/// file.read_into(5 << 30, &mut buf)?;
+/// file.flush()?;
+/// # Ok::<(), mmap_io::MmapIoError>(())
+/// ```
+pub struct WindowedFile {
+    path: PathBuf,
+    mode: MmapMode,
+    len: u64,
+    window_size: u64,
+    max_windows: usize,
+    windows: Mutex<Windows>,
+}
+
+impl WindowedFile {
+    /// Open an existing file in `mode`. No window is mapped until the first access.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::Io` if the file metadata cannot be read.
+    pub fn open<P: AsRef<Path>>(path: P, mode: MmapMode) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let len = std::fs::metadata(&path)?.len();
+        Ok(Self {
+            path,
+            mode,
+            len,
+            window_size: DEFAULT_WINDOW_SIZE,
+            max_windows: DEFAULT_MAX_WINDOWS,
+            windows: Mutex::new(Windows {
+                mapped: Vec::new(),
+                clock: 0,
+            }),
+        })
+    }
+
+    /// Set the window size (builder style, default 64 MiB). Rounded up to a whole number of
+    /// pages.
+    #[must_use]
+    pub fn window_size(mut self, bytes: u64) -> Self {
+        self.window_size = align_up(bytes.max(1), page_size() as u64);
+        self
+    }
+
+    /// Set how many windows may be mapped at once (builder style, default 4, at least 1).
+    #[must_use]
+    pub fn max_windows(mut self, count: usize) -> Self {
+        self.max_windows = count.max(1);
+        self
+    }
+
+    /// Length of the file in bytes.
+    #[must_use]
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Whether the file is empty.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Path to the underlying file.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Mode the windows are mapped in.
+    #[must_use]
+    pub fn mode(&self) -> MmapMode {
+        self.mode
+    }
+
+    /// Number of windows currently mapped.
+    #[must_use]
+    pub fn mapped_windows(&self) -> usize {
+        self.windows.lock().mapped.len()
+    }
+
+    /// Read `buf.len()` bytes starting at file offset `offset`, mapping windows as needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::OutOfBounds` if the range exceeds the file length.
+    /// Returns errors from mapping a window or flushing an evicted one.
+    pub fn read_into(&self, offset: u64, buf: &mut [u8]) -> Result<()> {
+        ensure_in_bounds(offset, buf.len() as u64, self.len)?;
+        let mut done = 0;
+        while done < buf.len() {
+            let pos = offset + done as u64;
+            let (window, start) = self.window_for(pos)?;
+            let n = self.chunk_len(pos, start, buf.len() - done);
+            window.read_into(pos - start, &mut buf[done..done + n])?;
+            done += n;
+        }
+        Ok(())
+    }
+
+    /// Write `data` at file offset `offset`, mapping windows as needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MmapIoError::InvalidMode` if the file was not opened `ReadWrite`.
+    /// Returns `MmapIoError::OutOfBounds` if the range exceeds the file length.
+    /// Returns errors from mapping a window or flushing an evicted one.
+    pub fn update_region(&self, offset: u64, data: &[u8]) -> Result<()> {
+        if self.mode != MmapMode::ReadWrite {
+            return Err(MmapIoError::InvalidMode(
+                "Update region requires ReadWrite mode.",
+            ));
+        }
+        ensure_in_bounds(offset, data.len() as u64, self.len)?;
+        let mut done = 0;
+        while done < data.len() {
+            let pos = offset + done as u64;
+            let (window, start) = self.window_for(pos)?;
+            let n = self.chunk_len(pos, start, data.len() - done);
+            window.update_region(pos - start, &data[done..done + n])?;
+            done += n;
+        }
+        Ok(())
+    }
+
+    /// Flush every mapped window.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error from `MemoryMappedFile::flush`.
+    pub fn flush(&self) -> Result<()> {
+        let maps: Vec<MemoryMappedFile> = self
+            .windows
+            .lock()
+            .mapped
+            .iter()
+            .map(|w| w.map.clone())
+            .collect();
+        maps.iter().try_for_each(MemoryMappedFile::flush)
+    }
+
+    // Bytes of a `wanted`-byte access at `pos` that fall inside the window starting at `start`.
+    fn chunk_len(&self, pos: u64, start: u64, wanted: usize) -> usize {
+        let left = start + self.window_size - pos;
+        usize::try_from(left).map_or(wanted, |left| left.min(wanted))
+    }
+
+    // The window containing `pos` and its start offset, mapping it (and evicting the least
+    // recently used window) if needed. The returned handle keeps the window mapped even if it
+    // is evicted while the caller uses it.
+    fn window_for(&self, pos: u64) -> Result<(MemoryMappedFile, u64)> {
+        let start = pos - pos % self.window_size;
+        let mut windows = self.windows.lock();
+        windows.clock += 1;
+        let clock = windows.clock;
+        if let Some(window) = windows.mapped.iter_mut().find(|w| w.start == start) {
+            window.last_used = clock;
+            return Ok((window.map.clone(), start));
+        }
+        if windows.mapped.len() >= self.max_windows {
+            let lru = windows
+                .mapped
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, w)| w.last_used)
+                .map(|(i, _)| i)
+                .unwrap_or(0);
+            let evicted = windows.mapped.swap_remove(lru);
+            evicted.map.flush()?;
+        }
+        let map = MemoryMappedFile::builder(&self.path)
+            .mode(self.mode)
+            .offset(start)
+            .len(self.window_size.min(self.len - start))
+            .open()?;
+        windows.mapped.push(Window {
+            start,
+            map: map.clone(),
+            last_used: clock,
+        });
+        Ok((map, start))
+    }
+}
+
+impl std::fmt::Debug for WindowedFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WindowedFile")
+            .field("path", &self.path)
+            .field("mode", &self.mode)
+            .field("len", &self.len)
+            .field("window_size", &self.window_size)
+            .field("max_windows", &self.max_windows)
+            .field("mapped_windows", &self.mapped_windows())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn tmp_path(name: &str) -> PathBuf {
+        let mut p = std::env::temp_dir();
+        p.push(format!(
+            "mmap_io_windowed_test_{}_{}",
+            name,
+            std::process::id()
+        ));
+        p
+    }
+
+    fn pattern(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i % 251) as u8).collect()
+    }
+
+    #[test]
+    fn test_reads_across_windows_stay_within_limit() {
+        let path = tmp_path("read");
+        let page = page_size();
+        let data = pattern(10 * page + 123);
+        fs::write(&path, &data).expect("seed file");
+
+        let file = WindowedFile::open(&path, MmapMode::ReadOnly)
+            .expect("open")
+            .window_size(page as u64)
+            .max_windows(2);
+        assert_eq!(file.mapped_windows(), 0);
+
+        // Every access straddles a window boundary
+        for i in 1..=10 {
+            let offset = (i * page - 50) as u64;
+            let mut buf = vec![0u8; 100.min(data.len() - offset as usize)];
+            file.read_into(offset, &mut buf).expect("read");
+            assert_eq!(buf, data[offset as usize..offset as usize + buf.len()]);
+            assert!(file.mapped_windows() <= 2);
+        }
+
+        let mut all = vec![0u8; data.len()];
+        file.read_into(0, &mut all).expect("read whole file");
+        assert_eq!(all, data);
+
+        drop(file);
+        fs::remove_file(&path).expect("cleanup");
+    }
+
+    #[test]
+    fn test_writes_survive_eviction() {
+        let path = tmp_path("write");
+        let page = page_size();
+        fs::write(&path, vec![0u8; 8 * page]).expect("seed file");
+
+        let file = WindowedFile::open(&path, MmapMode::ReadWrite)
+            .expect("open")
+            .window_size(2 * page as u64)
+            .max_windows(1);
+        let mut expected = vec![0u8; 8 * page];
+        for i in 0..8 {
+            let offset = i * page + page / 2;
+            let bytes = vec![i as u8 + 1; page];
+            file.update_region(offset as u64, &bytes[..page.min(8 * page - offset)])
+                .expect("write");
+            let end = (offset + page).min(8 * page);
+            expected[offset..end].copy_from_slice(&bytes[..end - offset]);
+        }
+        assert_eq!(file.mapped_windows(), 1);
+        file.flush().expect("flush");
+        drop(file);
+
+        assert_eq!(fs::read(&path).expect("read file"), expected);
+        fs::remove_file(&path).expect("cleanup");
+    }
+
+    #[test]
+    fn test_bounds_and_mode_errors() {
+        let path = tmp_path("errors");
+        fs::write(&path, vec![1u8; 1000]).expect("seed file");
+
+        let file = WindowedFile::open(&path, MmapMode::ReadOnly).expect("open");
+        assert_eq!(file.len(), 1000);
+        let mut buf = [0u8; 10];
+        assert!(matches!(
+            file.read_into(995, &mut buf),
+            Err(MmapIoError::OutOfBounds { .. })
+        ));
+        assert!(matches!(
+            file.update_region(0, b"x"),
+            Err(MmapIoError::InvalidMode(_))
+        ));
+        file.read_into(990, &mut buf).expect("read at end");
+        assert_eq!(buf, [1u8; 10]);
+
+        drop(file);
+        fs::remove_file(&path).expect("cleanup");
+    }
+}
@@ -0,0 +1,123 @@
+//! Tests for anonymous and temporary-file-backed mappings.
+
+use mmap_io::segment::{Segment, SegmentMut};
+use mmap_io::{MemoryMappedFile, MmapMode};
+use std::sync::Arc;
+
+#[test]
+fn anonymous_read_write_resize() {
+    let mmap = MemoryMappedFile::anonymous(4096).expect("anonymous");
+    assert_eq!(mmap.mode(), MmapMode::ReadWrite);
+    assert_eq!(mmap.len(), 4096);
+    assert!(mmap.path().as_os_str().is_empty());
+
+    mmap.update_region(4000, b"tail-data").expect("write");
+    mmap.flush().expect("flush is a no-op");
+
+    // Growing preserves existing contents and zero-fills the new tail
+    mmap.resize(3 * 4096).expect("grow");
+    let mut buf = [0u8; 9];
+    mmap.read_into(4000, &mut buf).expect("read");
+    assert_eq!(&buf, b"tail-data");
+    mmap.read_into(3 * 4096 - 9, &mut buf)
+        .expect("read new tail");
+    assert_eq!(buf, [0u8; 9]);
+
+    // Shrinking keeps the prefix
+    mmap.resize(4005).expect("shrink");
+    assert_eq!(mmap.len(), 4005);
+    let mut buf = [0u8; 5];
+    mmap.read_into(4000, &mut buf).expect("read");
+    assert_eq!(&buf, b"tail-");
+    assert!(mmap.read_into(4001, &mut buf).is_err());
+}
+
+#[test]
+fn anonymous_segments() {
+    let mmap = Arc::new(MemoryMappedFile::anonymous(1024).expect("anonymous"));
+    let seg = SegmentMut::new(Arc::clone(&mmap), 128, 16).expect("segment");
+    seg.write(b"segment-payload!").expect("write");
+
+    let mut buf = [0u8; 16];
+    mmap.read_into(128, &mut buf).expect("read");
+    assert_eq!(&buf, b"segment-payload!");
+    assert!(Segment::new(Arc::clone(&mmap), 1020, 8).is_err());
+}
+
+#[test]
+fn anonymous_mappings_are_private() {
+    let a = MemoryMappedFile::anonymous(64).expect("a");
+    let b = MemoryMappedFile::anonymous(64).expect("b");
+    a.update_region(0, b"only-a").expect("write");
+    let mut buf = [0u8; 6];
+    b.read_into(0, &mut buf).expect("read");
+    assert_eq!(buf, [0u8; 6]);
+}
+
+#[cfg(feature = "iterator")]
+#[test]
+fn anonymous_iterators() {
+    let mmap = MemoryMappedFile::anonymous(4096).expect("anonymous");
+    mmap.chunks_mut(1024)
+        .for_each_mut(|offset, chunk| {
+            chunk.fill((offset / 1024) as u8);
+            Ok::<(), std::io::Error>(())
+        })
+        .expect("chunks_mut")
+        .expect("fill");
+
+    let chunks: Vec<_> = mmap
+        .chunks(1024)
+        .collect::<Result<Vec<_>, _>>()
+        .expect("chunks");
+    assert_eq!(chunks.len(), 4);
+    for (i, chunk) in chunks.iter().enumerate() {
+        assert!(chunk.iter().all(|&b| b == i as u8));
+    }
+}
+
+#[cfg(feature = "atomic")]
+#[test]
+fn anonymous_atomics() {
+    use std::sync::atomic::Ordering;
+
+    let mmap = MemoryMappedFile::anonymous(64).expect("anonymous");
+    let counter = mmap.atomic_u64(8).expect("atomic");
+    counter.fetch_add(5, Ordering::SeqCst);
+    assert_eq!(counter.load(Ordering::SeqCst), 5);
+}
+
+#[test]
+fn temp_in_leaves_no_file_behind() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let count = || std::fs::read_dir(dir.path()).expect("read_dir").count();
+
+    let mmap = MemoryMappedFile::temp_in(dir.path(), 8192).expect("temp");
+    assert_eq!(mmap.path(), dir.path());
+    // The file is unnamed (O_TMPFILE) or already unlinked while the mapping is alive
+    #[cfg(unix)]
+    assert_eq!(count(), 0);
+
+    mmap.update_region(8000, b"temp").expect("write");
+    mmap.resize(16384).expect("grow");
+    let mut buf = [0u8; 4];
+    mmap.read_into(8000, &mut buf).expect("read");
+    assert_eq!(&buf, b"temp");
+
+    drop(mmap);
+    assert_eq!(count(), 0);
+}
+
+#[test]
+fn temp_default_dir() {
+    let mmap = MemoryMappedFile::temp(4096).expect("temp");
+    assert_eq!(mmap.path(), std::env::temp_dir());
+    mmap.update_region(0, b"scratch").expect("write");
+    mmap.flush().expect("flush is a no-op");
+}
+
+#[test]
+fn zero_sized_unnamed_mappings_rejected() {
+    assert!(MemoryMappedFile::anonymous(0).is_err());
+    assert!(MemoryMappedFile::temp(0).is_err());
+}
@@ -0,0 +1,168 @@
+//! Tests for read-write mappings that grow through a builder growth policy.
+
+use mmap_io::segment::SegmentMut;
+use mmap_io::{GrowthPolicy, MemoryMappedFile, MmapIoError, MmapMode};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+fn tmp_path(name: &str) -> PathBuf {
+    let mut p = std::env::temp_dir();
+    p.push(format!(
+        "mmap_io_growth_policy_test_{}_{}",
+        name,
+        std::process::id()
+    ));
+    p
+}
+
+#[test]
+fn writes_past_end_grow_and_shrink_to_fit_trims() {
+    let path = tmp_path("double");
+    let _ = fs::remove_file(&path);
+
+    let mmap = MemoryMappedFile::builder(&path)
+        .mode(MmapMode::ReadWrite)
+        .size(4096)
+        .growth_policy(GrowthPolicy::Double)
+        .create()
+        .expect("create");
+    assert_eq!(mmap.len_used(), 0);
+
+    mmap.update_region(0, b"head").expect("write");
+    assert_eq!(mmap.len(), 4096);
+    assert_eq!(mmap.len_used(), 4);
+
+    mmap.update_region(5000, b"grown").expect("write past end");
+    assert_eq!(mmap.len(), 8192);
+    assert_eq!(mmap.len_used(), 5005);
+
+    mmap.update_region(100_000, b"far")
+        .expect("write far past end");
+    assert_eq!(mmap.len(), 100_003);
+    {
+        let mut slice = mmap.as_slice_mut(100_003, 7).expect("slice past end");
+        slice.as_mut().copy_from_slice(b"trailer");
+    }
+    assert_eq!(mmap.len_used(), 100_010);
+
+    mmap.shrink_to_fit().expect("shrink");
+    assert_eq!(mmap.len(), 100_010);
+    mmap.flush().expect("flush");
+    drop(mmap);
+
+    assert_eq!(fs::metadata(&path).expect("metadata").len(), 100_010);
+    let ro = MemoryMappedFile::open_ro(&path).expect("open");
+    assert_eq!(ro.as_slice(5000, 5).expect("read"), b"grown");
+    assert_eq!(ro.as_slice(100_000, 10).expect("read"), b"fartrailer");
+
+    drop(ro);
+    fs::remove_file(&path).expect("cleanup");
+}
+
+#[test]
+fn fixed_steps_stop_at_max_size() {
+    let path = tmp_path("fixed");
+    let _ = fs::remove_file(&path);
+
+    let mmap = MemoryMappedFile::builder(&path)
+        .mode(MmapMode::ReadWrite)
+        .size(1000)
+        .growth_policy(GrowthPolicy::Fixed(1000))
+        .max_size(2500)
+        .create()
+        .expect("create");
+
+    mmap.update_region(1500, b"x").expect("grow one step");
+    assert_eq!(mmap.len(), 2000);
+    // The last step is clamped to max_size
+    mmap.update_region(2499, b"y").expect("grow to max");
+    assert_eq!(mmap.len(), 2500);
+
+    match mmap.update_region(2499, b"zz") {
+        Err(MmapIoError::OutOfBounds { total, .. }) => assert_eq!(total, 2500),
+        other => panic!("expected OutOfBounds, got {other:?}"),
+    }
+    assert_eq!(mmap.len(), 2500);
+    assert_eq!(mmap.len_used(), 2500);
+
+    drop(mmap);
+    fs::remove_file(&path).expect("cleanup");
+}
+
+#[test]
+fn segments_grow_and_reopen_counts_file_as_used() {
+    let path = tmp_path("segment");
+    let _ = fs::remove_file(&path);
+
+    fs::write(&path, vec![1u8; 64]).expect("seed file");
+    let quarter: fn(u64, u64) -> u64 = |current, _| current + current / 4;
+    let mmap = Arc::new(
+        MemoryMappedFile::builder(&path)
+            .mode(MmapMode::ReadWrite)
+            .growth_policy(GrowthPolicy::Custom(quarter))
+            .open()
+            .expect("open"),
+    );
+    assert_eq!(mmap.len_used(), 64);
+    mmap.shrink_to_fit().expect("nothing to trim");
+    assert_eq!(mmap.len(), 64);
+
+    let segment = SegmentMut::new(Arc::clone(&mmap), 70, 8).expect("segment past end");
+    segment.write(b"appended").expect("segment write");
+    assert_eq!(mmap.len(), 80);
+    assert_eq!(mmap.len_used(), 78);
+
+    drop(segment);
+    drop(mmap);
+    let plain = Arc::new(
+        MemoryMappedFile::builder(&path)
+            .mode(MmapMode::ReadWrite)
+            .open()
+            .expect("open"),
+    );
+    assert_eq!(plain.len_used(), 80);
+    // Without a growth policy a segment past the end is rejected
+    assert!(SegmentMut::new(Arc::clone(&plain), 78, 8).is_err());
+
+    drop(plain);
+    fs::remove_file(&path).expect("cleanup");
+}
+
+#[test]
+fn shrink_on_drop_trims_when_last_handle_goes() {
+    let path = tmp_path("shrink_on_drop");
+    let _ = fs::remove_file(&path);
+
+    let mmap = MemoryMappedFile::builder(&path)
+        .mode(MmapMode::ReadWrite)
+        .size(4096)
+        .growth_policy(GrowthPolicy::Fixed(64 * 1024))
+        .shrink_on_drop(true)
+        .create()
+        .expect("create");
+    mmap.update_region(4000, &[7u8; 1000]).expect("grow");
+    assert_eq!(mmap.len(), 64 * 1024 + 4096);
+
+    let clone = mmap.clone();
+    drop(mmap);
+    assert_eq!(
+        fs::metadata(&path).expect("metadata").len(),
+        64 * 1024 + 4096
+    );
+    drop(clone);
+    assert_eq!(fs::metadata(&path).expect("metadata").len(), 5000);
+    assert_eq!(fs::read(&path).expect("read")[4000..], [7u8; 1000]);
+
+    // Without the flag the capacity stays
+    let mmap = MemoryMappedFile::builder(&path)
+        .mode(MmapMode::ReadWrite)
+        .growth_policy(GrowthPolicy::Double)
+        .open()
+        .expect("open");
+    mmap.update_region(5000, b"more").expect("grow");
+    drop(mmap);
+    assert_eq!(fs::metadata(&path).expect("metadata").len(), 10_000);
+
+    fs::remove_file(&path).expect("cleanup");
+}
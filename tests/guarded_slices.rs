@@ -0,0 +1,103 @@
+//! Tests for the lock-holding slice views and the `Pod` marker.
+
+use mmap_io::{MemoryMappedFile, MmapIoError, MmapMode, Pod};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+fn tmp_path(name: &str) -> PathBuf {
+    let mut p = std::env::temp_dir();
+    p.push(format!(
+        "mmap_io_guarded_slices_test_{}_{}",
+        name,
+        std::process::id()
+    ));
+    p
+}
+
+#[test]
+fn guarded_view_works_in_every_mode_and_checks_bounds() {
+    let path = tmp_path("modes");
+    fs::write(&path, b"0123456789").expect("seed file");
+
+    let ro = MemoryMappedFile::open_ro(&path).expect("open ro");
+    assert_eq!(&*ro.as_slice_guarded(2, 3).expect("ro view"), b"234");
+    let rw = MemoryMappedFile::builder(&path)
+        .mode(MmapMode::ReadWrite)
+        .open()
+        .expect("open rw");
+    assert_eq!(&*rw.as_slice_guarded(7, 3).expect("rw view"), b"789");
+    assert_eq!(rw.as_slice_guarded(10, 0).expect("empty view").len(), 0);
+
+    for mmap in [&ro, &rw] {
+        assert!(matches!(
+            mmap.as_slice_guarded(8, 3),
+            Err(MmapIoError::OutOfBounds {
+                offset: 8,
+                len: 3,
+                total: 10
+            })
+        ));
+        assert!(mmap.as_slice_guarded(11, 0).is_err());
+    }
+
+    drop((ro, rw));
+    fs::remove_file(&path).expect("cleanup");
+}
+
+#[test]
+fn guarded_view_holds_off_writers_until_dropped() {
+    let mmap = Arc::new(MemoryMappedFile::anonymous(4096).expect("anonymous"));
+    mmap.update_region(0, b"before").expect("write");
+    let written = Arc::new(AtomicBool::new(false));
+
+    let view = mmap.as_slice_guarded(0, 6).expect("view");
+    let writer = {
+        let (mmap, written) = (Arc::clone(&mmap), Arc::clone(&written));
+        thread::spawn(move || {
+            mmap.update_region(0, b"after!").expect("write");
+            written.store(true, Ordering::SeqCst);
+        })
+    };
+    thread::sleep(Duration::from_millis(100));
+    assert!(!written.load(Ordering::SeqCst));
+    assert_eq!(&*view, b"before");
+
+    drop(view);
+    writer.join().expect("writer thread");
+    assert!(written.load(Ordering::SeqCst));
+    assert_eq!(&*mmap.as_slice_guarded(0, 6).expect("view"), b"after!");
+}
+
+#[test]
+fn mutable_view_is_exclusive_and_bounded() {
+    let mmap = Arc::new(MemoryMappedFile::anonymous(4096).expect("anonymous"));
+    assert!(matches!(
+        mmap.as_slice_mut(4000, 100),
+        Err(MmapIoError::OutOfBounds { .. })
+    ));
+
+    let mut slice = mmap.as_slice_mut(10, 4).expect("mutable view");
+    slice.as_mut().copy_from_slice(b"lock");
+    let reader = {
+        let mmap = Arc::clone(&mmap);
+        thread::spawn(move || mmap.as_slice_guarded(10, 4).expect("view").to_vec())
+    };
+    thread::sleep(Duration::from_millis(50));
+    assert!(!reader.is_finished());
+    drop(slice);
+    assert_eq!(reader.join().expect("reader thread"), b"lock");
+}
+
+#[test]
+fn pod_and_invalid_format_are_public() {
+    fn assert_pod<T: Pod>() {}
+    assert_pod::<u64>();
+    assert_pod::<[u32; 4]>();
+
+    let err = MmapIoError::InvalidFormat("bad magic".into());
+    assert!(err.to_string().contains("bad magic"));
+}
@@ -0,0 +1,105 @@
+//! Tests for disk block preallocation at create and resize time.
+
+use mmap_io::utils::available_space;
+use mmap_io::{MemoryMappedFile, MmapIoError, MmapMode};
+use std::fs;
+use std::path::PathBuf;
+
+fn tmp_path(name: &str) -> PathBuf {
+    let mut p = std::env::temp_dir();
+    p.push(format!(
+        "mmap_io_preallocate_test_{}_{}",
+        name,
+        std::process::id()
+    ));
+    p
+}
+
+#[cfg(unix)]
+fn allocated(path: &PathBuf) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    fs::metadata(path).expect("metadata").blocks() * 512
+}
+
+#[test]
+fn create_and_resize_allocate_blocks() {
+    let path = tmp_path("blocks");
+    let _ = fs::remove_file(&path);
+
+    let mmap = MemoryMappedFile::builder(&path)
+        .mode(MmapMode::ReadWrite)
+        .size(1 << 20)
+        .preallocate(true)
+        .create()
+        .expect("create");
+    assert_eq!(fs::metadata(&path).expect("metadata").len(), 1 << 20);
+    #[cfg(unix)]
+    assert!(allocated(&path) >= 1 << 20);
+
+    mmap.resize_preallocated(3 << 20).expect("grow");
+    assert_eq!(mmap.len(), 3 << 20);
+    assert_eq!(fs::metadata(&path).expect("metadata").len(), 3 << 20);
+    #[cfg(unix)]
+    assert!(allocated(&path) >= 3 << 20);
+    mmap.update_region((3 << 20) - 4, b"tail")
+        .expect("write at new end");
+
+    // Shrinking is a plain resize
+    mmap.resize_preallocated(4096).expect("shrink");
+    assert_eq!(fs::metadata(&path).expect("metadata").len(), 4096);
+
+    drop(mmap);
+    fs::remove_file(&path).expect("cleanup");
+}
+
+#[test]
+fn too_large_for_the_disk_is_enospc() {
+    let path = tmp_path("enospc");
+    let _ = fs::remove_file(&path);
+    let huge = 1u64 << 46;
+    if available_space(std::env::temp_dir()).expect("free space") >= huge {
+        return;
+    }
+
+    let created = MemoryMappedFile::builder(&path)
+        .mode(MmapMode::ReadWrite)
+        .size(huge)
+        .preallocate(true)
+        .create();
+    match created {
+        #[cfg(unix)]
+        Err(MmapIoError::Io(err)) => assert_eq!(err.raw_os_error(), Some(libc::ENOSPC)),
+        #[cfg(not(unix))]
+        Err(MmapIoError::Io(_)) => {}
+        other => panic!("expected Io error, got {other:?}"),
+    }
+    assert_eq!(fs::metadata(&path).expect("metadata").len(), 0);
+
+    let mmap = MemoryMappedFile::create_rw(&path, 4096).expect("create");
+    assert!(matches!(
+        mmap.resize_preallocated(huge),
+        Err(MmapIoError::Io(_))
+    ));
+    // The mapping is untouched
+    assert_eq!(mmap.len(), 4096);
+    mmap.update_region(0, b"still usable").expect("write");
+
+    drop(mmap);
+    fs::remove_file(&path).expect("cleanup");
+}
+
+#[test]
+fn resize_preallocated_requires_read_write() {
+    let path = tmp_path("mode");
+    fs::write(&path, vec![0u8; 4096]).expect("seed file");
+
+    let ro = MemoryMappedFile::open_ro(&path).expect("open");
+    assert!(matches!(
+        ro.resize_preallocated(8192),
+        Err(MmapIoError::InvalidMode(_))
+    ));
+    assert!(available_space(&path).expect("free space") > 0);
+
+    drop(ro);
+    fs::remove_file(&path).expect("cleanup");
+}
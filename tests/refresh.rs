@@ -0,0 +1,90 @@
+//! Tests for refreshing mappings after another handle changes the file length.
+
+use mmap_io::{LengthChange, MemoryMappedFile, MmapIoError, MmapMode};
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+fn tmp_path(name: &str) -> PathBuf {
+    let mut p = std::env::temp_dir();
+    p.push(format!(
+        "mmap_io_refresh_test_{}_{}",
+        name,
+        std::process::id()
+    ));
+    p
+}
+
+#[test]
+fn read_only_mapping_sees_appended_bytes() {
+    let path = tmp_path("grow");
+    fs::write(&path, b"first").expect("seed file");
+
+    let mut mmap = MemoryMappedFile::open_ro(&path).expect("open");
+    assert_eq!(mmap.refresh().expect("refresh"), LengthChange::Unchanged);
+
+    let mut file = fs::OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .expect("open for append");
+    file.write_all(b" second").expect("append");
+    assert_eq!(mmap.len(), 5);
+
+    assert_eq!(
+        mmap.refresh().expect("refresh"),
+        LengthChange::Grew { from: 5, to: 12 }
+    );
+    assert_eq!(mmap.as_slice(0, 12).expect("slice"), b"first second");
+
+    drop(mmap);
+    fs::remove_file(&path).expect("cleanup");
+}
+
+#[test]
+fn shrinkage_is_reported_and_remapped() {
+    let path = tmp_path("shrink");
+    fs::write(&path, vec![3u8; 8192]).expect("seed file");
+
+    let mut mmap = MemoryMappedFile::builder(&path)
+        .mode(MmapMode::ReadWrite)
+        .open()
+        .expect("open");
+    fs::OpenOptions::new()
+        .write(true)
+        .open(&path)
+        .expect("open for truncate")
+        .set_len(100)
+        .expect("truncate");
+
+    assert_eq!(
+        mmap.refresh().expect("refresh"),
+        LengthChange::Shrank {
+            from: 8192,
+            to: 100
+        }
+    );
+    assert_eq!(mmap.len(), 100);
+    assert!(mmap.update_region(100, b"x").is_err());
+    mmap.update_region(99, b"x").expect("write at new end");
+
+    drop(mmap);
+    fs::remove_file(&path).expect("cleanup");
+}
+
+#[test]
+fn refresh_requires_the_only_handle() {
+    let path = tmp_path("shared");
+    fs::write(&path, b"data").expect("seed file");
+
+    let mut mmap = MemoryMappedFile::open_ro(&path).expect("open");
+    let other = mmap.clone();
+    assert!(matches!(mmap.refresh(), Err(MmapIoError::InvalidMode(_))));
+    drop(other);
+    assert_eq!(mmap.refresh().expect("refresh"), LengthChange::Unchanged);
+
+    let mut anon = MemoryMappedFile::anonymous(4096).expect("anonymous");
+    assert!(matches!(anon.refresh(), Err(MmapIoError::InvalidMode(_))));
+
+    drop(mmap);
+    fs::remove_file(&path).expect("cleanup");
+}
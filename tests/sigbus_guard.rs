@@ -0,0 +1,153 @@
+//! Tests for recovering from SIGBUS when a mapped file is truncated underneath the mapping.
+#![cfg(unix)]
+
+use mmap_io::utils::page_size;
+use mmap_io::{sigbus, MemoryMappedFile, MmapIoError};
+use std::fs;
+use std::path::PathBuf;
+
+fn tmp_path(name: &str) -> PathBuf {
+    let mut p = std::env::temp_dir();
+    p.push(format!(
+        "mmap_io_sigbus_guard_test_{}_{}",
+        name,
+        std::process::id()
+    ));
+    p
+}
+
+// Map a four-page file of `0xab` bytes, then truncate it to one page behind the mapping's back
+fn truncated_mapping(name: &str) -> (MemoryMappedFile, PathBuf) {
+    sigbus::install_handler().expect("install handler");
+    let path = tmp_path(name);
+    let page = page_size();
+    fs::write(&path, vec![0xabu8; 4 * page]).expect("seed file");
+    let mmap = MemoryMappedFile::open_ro(&path).expect("open");
+    fs::OpenOptions::new()
+        .write(true)
+        .open(&path)
+        .expect("open for truncate")
+        .set_len(page as u64)
+        .expect("truncate");
+    (mmap, path)
+}
+
+#[test]
+fn read_into_reports_truncation_instead_of_crashing() {
+    let (mmap, path) = truncated_mapping("read_into");
+    let page = page_size() as u64;
+    assert!(sigbus::is_installed());
+
+    let mut buf = vec![0u8; 16];
+    mmap.read_into(0, &mut buf).expect("read backed page");
+    assert_eq!(buf, vec![0xab; 16]);
+    match mmap.read_into(2 * page, &mut buf) {
+        Err(MmapIoError::Truncated { offset, len }) => assert_eq!((offset, len), (2 * page, 16)),
+        other => panic!("expected Truncated, got {other:?}"),
+    }
+    // A read that straddles the new end is reported as well
+    let mut straddle = vec![0u8; page as usize];
+    assert!(matches!(
+        mmap.read_into(page / 2, &mut straddle),
+        Err(MmapIoError::Truncated { .. })
+    ));
+    // Recovered pages read as zeros from then on
+    mmap.read_into(2 * page, &mut buf)
+        .expect("read patched page");
+    assert_eq!(buf, vec![0; 16]);
+
+    drop(mmap);
+    fs::remove_file(&path).expect("cleanup");
+}
+
+#[test]
+fn closures_and_touch_report_truncation() {
+    let (mmap, path) = truncated_mapping("closure");
+    let page = page_size() as u64;
+
+    let sum = mmap
+        .read_with(0, 8, |bytes| {
+            bytes.iter().map(|&b| u64::from(b)).sum::<u64>()
+        })
+        .expect("read backed bytes");
+    assert_eq!(sum, 8 * 0xab);
+    assert!(matches!(
+        mmap.read_with(3 * page, 8, |bytes| bytes[0]),
+        Err(MmapIoError::Truncated { .. })
+    ));
+    assert!(matches!(
+        mmap.touch_pages(),
+        Err(MmapIoError::Truncated { .. })
+    ));
+
+    drop(mmap);
+    fs::remove_file(&path).expect("cleanup");
+}
+
+#[cfg(feature = "iterator")]
+#[test]
+fn iterators_report_truncation() {
+    let (mmap, path) = truncated_mapping("iterator");
+
+    // A failed page is yielded again on the next call, then reads as zeros
+    let results: Vec<_> = mmap.pages().collect();
+    assert!(results[0].is_ok());
+    let truncated = results
+        .iter()
+        .filter(|r| matches!(r, Err(MmapIoError::Truncated { .. })))
+        .count();
+    assert_eq!(truncated, 3);
+
+    drop(mmap);
+    fs::remove_file(&path).expect("cleanup");
+}
+
+#[test]
+fn writes_after_recovered_fault_fail_until_refresh() {
+    sigbus::install_handler().expect("install handler");
+    let path = tmp_path("rw_poison");
+    let page = page_size() as u64;
+    fs::write(&path, vec![0xabu8; 4 * page as usize]).expect("seed file");
+    let mut mmap = MemoryMappedFile::open_rw(&path).expect("open rw");
+    let file = fs::OpenOptions::new()
+        .write(true)
+        .open(&path)
+        .expect("open for truncate");
+    file.set_len(page).expect("truncate");
+
+    let mut buf = vec![0u8; 16];
+    assert!(matches!(
+        mmap.read_into(2 * page, &mut buf),
+        Err(MmapIoError::Truncated { .. })
+    ));
+    // The patched page is private memory: writes through it would never reach the file
+    assert!(matches!(
+        mmap.update_region(2 * page, b"lost"),
+        Err(MmapIoError::Truncated { .. })
+    ));
+    assert!(matches!(
+        mmap.update_region(0, b"also refused"),
+        Err(MmapIoError::Truncated { .. })
+    ));
+    assert!(matches!(
+        mmap.as_slice_mut(0, 4),
+        Err(MmapIoError::Truncated { .. })
+    ));
+    assert!(matches!(mmap.flush(), Err(MmapIoError::Truncated { .. })));
+    // Reads keep working
+    mmap.read_into(0, &mut buf).expect("read backed page");
+    assert_eq!(buf, vec![0xab; 16]);
+
+    // Once the file is back to its old length, refresh remaps it and writes persist again
+    file.set_len(4 * page).expect("regrow");
+    mmap.refresh().expect("refresh");
+    mmap.update_region(2 * page, b"kept")
+        .expect("write after refresh");
+    mmap.flush().expect("flush");
+    let bytes = fs::read(&path).expect("read file");
+    assert_eq!(&bytes[2 * page as usize..2 * page as usize + 4], b"kept");
+    assert_eq!(bytes[0], 0xab);
+
+    drop(mmap);
+    fs::remove_file(&path).expect("cleanup");
+}
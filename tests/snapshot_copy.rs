@@ -0,0 +1,162 @@
+//! Tests for snapshots of a mapping and copies between mappings.
+
+use mmap_io::{copy_range, copy_range_with_progress, MemoryMappedFile, MmapIoError, MmapMode};
+use std::fs;
+use std::path::PathBuf;
+
+fn tmp_path(name: &str) -> PathBuf {
+    let mut p = std::env::temp_dir();
+    p.push(format!(
+        "mmap_io_snapshot_copy_test_{}_{}",
+        name,
+        std::process::id()
+    ));
+    p
+}
+
+fn pattern(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i % 251) as u8).collect()
+}
+
+#[test]
+fn snapshot_is_a_point_in_time_copy() {
+    let path = tmp_path("snap_src");
+    let snap = tmp_path("snap_dst");
+    let window_snap = tmp_path("snap_window");
+    let _ = fs::remove_file(&path);
+
+    let data = pattern(3 * 4096 + 17);
+    let mmap = MemoryMappedFile::create_rw(&path, data.len() as u64).expect("create");
+    mmap.update_region(0, &data).expect("write");
+    mmap.snapshot_to(&snap).expect("snapshot");
+    mmap.update_region(0, b"changed later").expect("write");
+    mmap.flush().expect("flush");
+    assert_eq!(fs::read(&snap).expect("read snapshot"), data);
+
+    // Snapshotting onto the mapped file itself is refused
+    assert!(matches!(
+        mmap.snapshot_to(&path),
+        Err(MmapIoError::InvalidMode(_))
+    ));
+    drop(mmap);
+
+    let window = MemoryMappedFile::builder(&path)
+        .offset(5000)
+        .len(3000)
+        .open()
+        .expect("open window");
+    window.snapshot_to(&window_snap).expect("snapshot window");
+    assert_eq!(
+        fs::read(&window_snap).expect("read snapshot"),
+        data[5000..8000]
+    );
+
+    drop(window);
+    for p in [&path, &snap, &window_snap] {
+        fs::remove_file(p).expect("cleanup");
+    }
+}
+
+#[test]
+fn snapshot_of_anonymous_mapping_writes_its_bytes() {
+    let snap = tmp_path("anon_dst");
+    let mmap = MemoryMappedFile::anonymous(10_000).expect("create anonymous");
+    mmap.update_region(9_990, b"0123456789").expect("write");
+    mmap.snapshot_to(&snap).expect("snapshot");
+
+    let on_disk = fs::read(&snap).expect("read snapshot");
+    assert_eq!(on_disk.len(), 10_000);
+    assert_eq!(&on_disk[9_990..], b"0123456789");
+
+    drop(mmap);
+    fs::remove_file(&snap).expect("cleanup");
+}
+
+#[test]
+fn copy_range_between_mappings_with_progress() {
+    let src_path = tmp_path("range_src");
+    let dst_path = tmp_path("range_dst");
+    let _ = fs::remove_file(&dst_path);
+
+    let data = pattern(20 << 20);
+    fs::write(&src_path, &data).expect("seed file");
+    let src = MemoryMappedFile::open_ro(&src_path).expect("open source");
+    let dst = MemoryMappedFile::create_rw(&dst_path, 24 << 20).expect("create destination");
+
+    let mut calls = Vec::new();
+    copy_range_with_progress(&src, 1000, &dst, 4096, (20 << 20) - 1000, |done, total| {
+        calls.push((done, total))
+    })
+    .expect("copy");
+    assert!(calls.len() > 1);
+    assert!(calls.windows(2).all(|w| w[0].0 < w[1].0));
+    assert_eq!(calls.last(), Some(&((20 << 20) - 1000, (20 << 20) - 1000)));
+
+    let mut buf = vec![0u8; (20 << 20) - 1000];
+    dst.read_into(4096, &mut buf).expect("read");
+    assert_eq!(buf, data[1000..]);
+
+    assert!(matches!(
+        copy_range(&src, (20 << 20) - 10, &dst, 0, 11),
+        Err(MmapIoError::OutOfBounds { .. })
+    ));
+    assert!(matches!(
+        copy_range(&dst, 0, &src, 0, 1),
+        Err(MmapIoError::InvalidMode(_))
+    ));
+
+    drop((src, dst));
+    fs::remove_file(&src_path).expect("cleanup");
+    fs::remove_file(&dst_path).expect("cleanup");
+}
+
+#[test]
+fn copy_range_within_one_mapping_handles_overlap() {
+    let path = tmp_path("overlap");
+    let _ = fs::remove_file(&path);
+
+    let mmap = MemoryMappedFile::builder(&path)
+        .mode(MmapMode::ReadWrite)
+        .size(16)
+        .create()
+        .expect("create");
+    mmap.update_region(0, b"abcdefgh").expect("write");
+    let alias = mmap.clone();
+    copy_range(&mmap, 0, &alias, 4, 8).expect("overlapping copy");
+
+    let mut buf = [0u8; 12];
+    mmap.read_into(0, &mut buf).expect("read");
+    assert_eq!(&buf, b"abcdabcdefgh");
+
+    drop((mmap, alias));
+    fs::remove_file(&path).expect("cleanup");
+}
+
+#[test]
+fn copy_range_between_mappings_of_one_file_handles_overlap() {
+    let path = tmp_path("same_file");
+    let len = 24 << 20;
+    let data = pattern(len);
+    fs::write(&path, &data).expect("seed file");
+
+    let a = MemoryMappedFile::open_rw(&path).expect("open a");
+    let b = MemoryMappedFile::open_rw(&path).expect("open b");
+    // Forwards over several chunks, so later chunks read bytes earlier ones overwrote
+    copy_range(&a, 0, &b, 4 << 20, 16 << 20).expect("copy up");
+    let mut expected = data.clone();
+    expected.copy_within(0..16 << 20, 4 << 20);
+    assert_eq!(
+        &*a.as_slice_guarded(0, len as u64).expect("view"),
+        &expected[..]
+    );
+
+    copy_range(&b, 5, &a, 0, 1000).expect("copy down");
+    expected.copy_within(5..1005, 0);
+    assert_eq!(
+        &*b.as_slice_guarded(0, len as u64).expect("view"),
+        &expected[..]
+    );
+
+    drop((a, b));
+    fs::remove_file(&path).expect("cleanup");
+}
@@ -0,0 +1,117 @@
+//! Tests for punching holes and enumerating the allocated ranges of a mapping.
+
+use mmap_io::manager::copy_mmap;
+use mmap_io::{MemoryMappedFile, MmapIoError, MmapMode};
+use std::fs;
+use std::path::PathBuf;
+
+const BLOCK: u64 = 64 * 1024;
+
+fn tmp_path(name: &str) -> PathBuf {
+    let mut p = std::env::temp_dir();
+    p.push(format!(
+        "mmap_io_sparse_files_test_{}_{}",
+        name,
+        std::process::id()
+    ));
+    p
+}
+
+#[test]
+fn punch_hole_zeroes_range_and_frees_blocks() {
+    let path = tmp_path("punch");
+    let _ = fs::remove_file(&path);
+
+    let mmap = MemoryMappedFile::create_rw(&path, 8 * BLOCK).expect("create");
+    mmap.update_region(0, &vec![0xAB; (8 * BLOCK) as usize])
+        .expect("fill");
+    mmap.flush().expect("flush");
+    assert!(mmap.hole_ranges().expect("holes").is_empty());
+
+    mmap.punch_hole(2 * BLOCK, 4 * BLOCK).expect("punch");
+    assert_eq!(mmap.len(), 8 * BLOCK);
+    let mut buf = vec![0xFFu8; (4 * BLOCK + 2) as usize];
+    mmap.read_into(2 * BLOCK - 1, &mut buf).expect("read");
+    assert_eq!(buf[0], 0xAB);
+    assert!(buf[1..buf.len() - 1].iter().all(|&b| b == 0));
+    assert_eq!(buf[buf.len() - 1], 0xAB);
+    #[cfg(target_os = "linux")]
+    {
+        assert_eq!(
+            mmap.hole_ranges().expect("holes"),
+            vec![2 * BLOCK..6 * BLOCK]
+        );
+        assert_eq!(
+            mmap.allocated_ranges().expect("ranges"),
+            vec![0..2 * BLOCK, 6 * BLOCK..8 * BLOCK]
+        );
+    }
+
+    // The hole is writable again and the file length is unchanged
+    mmap.update_region(3 * BLOCK, b"refilled").expect("write");
+    mmap.flush().expect("flush");
+    drop(mmap);
+    let on_disk = fs::read(&path).expect("read file");
+    assert_eq!(on_disk.len() as u64, 8 * BLOCK);
+    assert_eq!(&on_disk[(3 * BLOCK) as usize..][..8], b"refilled");
+
+    fs::remove_file(&path).expect("cleanup");
+}
+
+#[test]
+fn punch_hole_checks_mode_and_bounds() {
+    let path = tmp_path("errors");
+    fs::write(&path, vec![1u8; BLOCK as usize]).expect("seed file");
+
+    let ro = MemoryMappedFile::open_ro(&path).expect("open");
+    assert!(matches!(
+        ro.punch_hole(0, 10),
+        Err(MmapIoError::InvalidMode(_))
+    ));
+    assert_eq!(ro.allocated_ranges().expect("ranges"), vec![0..BLOCK]);
+    drop(ro);
+
+    let window = MemoryMappedFile::builder(&path)
+        .mode(MmapMode::ReadWrite)
+        .offset(100)
+        .len(1000)
+        .open()
+        .expect("open window");
+    assert!(matches!(
+        window.punch_hole(500, 501),
+        Err(MmapIoError::OutOfBounds { .. })
+    ));
+    assert_eq!(window.allocated_ranges().expect("ranges"), vec![0..1000]);
+
+    drop(window);
+    fs::remove_file(&path).expect("cleanup");
+}
+
+#[test]
+fn copy_mmap_keeps_holes() {
+    let src = tmp_path("copy_src");
+    let dst = tmp_path("copy_dst");
+    let _ = fs::remove_file(&src);
+    let _ = fs::remove_file(&dst);
+
+    let mmap = MemoryMappedFile::create_rw(&src, 16 * BLOCK).expect("create");
+    mmap.update_region(0, b"head").expect("write");
+    mmap.update_region(16 * BLOCK - 4, b"tail").expect("write");
+    mmap.flush().expect("flush");
+    drop(mmap);
+
+    copy_mmap(&src, &dst).expect("copy");
+    assert_eq!(
+        fs::read(&dst).expect("read copy"),
+        fs::read(&src).expect("read source")
+    );
+    #[cfg(target_os = "linux")]
+    {
+        let copy = MemoryMappedFile::open_ro(&dst).expect("open copy");
+        let holes = copy.hole_ranges().expect("holes");
+        assert!(holes.iter().map(|r| r.end - r.start).sum::<u64>() >= 14 * BLOCK);
+    }
+
+    fs::remove_file(&src).expect("cleanup");
+    fs::remove_file(&dst).expect("cleanup");
+}
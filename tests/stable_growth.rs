@@ -0,0 +1,198 @@
+//! Tests for read-write mappings that grow in place inside reserved address space.
+#![cfg(unix)]
+
+use mmap_io::{MemoryMappedFile, MmapMode};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn tmp_path(name: &str) -> PathBuf {
+    let mut p = std::env::temp_dir();
+    p.push(format!(
+        "mmap_io_stable_growth_test_{}_{}",
+        name,
+        std::process::id()
+    ));
+    p
+}
+
+fn base_ptr(mmap: &MemoryMappedFile) -> usize {
+    mmap.as_slice_guarded(0, 1).expect("view").as_ptr() as usize
+}
+
+#[test]
+fn growth_keeps_base_address_and_open_views() {
+    let path = tmp_path("grow");
+    let _ = fs::remove_file(&path);
+
+    let mmap = MemoryMappedFile::builder(&path)
+        .mode(MmapMode::ReadWrite)
+        .size(4096)
+        .reserve_address_space(64 << 20)
+        .create()
+        .expect("create");
+    assert_eq!(mmap.reserved_len(), Some(64 << 20));
+    mmap.update_region(0, b"head").expect("write");
+    let base = base_ptr(&mmap);
+
+    // Growth does not take the write lock, so a view held across it stays usable
+    let view = mmap.as_slice_guarded(0, 4).expect("view");
+    for size in [10_000u64, 1 << 20, 40 << 20] {
+        mmap.resize(size).expect("grow");
+        assert_eq!(mmap.len(), size);
+        assert_eq!(fs::metadata(&path).expect("metadata").len(), size);
+    }
+    assert_eq!(&*view, b"head");
+    drop(view);
+    assert_eq!(base_ptr(&mmap), base);
+    mmap.update_region((1 << 20) - 4, b"tail").expect("write");
+    mmap.update_region((40 << 20) - 4, b"tail")
+        .expect("write at new end");
+
+    let mut buf = [0u8; 4];
+    mmap.read_into((1 << 20) - 4, &mut buf).expect("read");
+    assert_eq!(&buf, b"tail");
+    mmap.flush().expect("flush");
+    drop(mmap);
+
+    let mut on_disk = vec![0u8; 4];
+    let file = MemoryMappedFile::open_ro(&path).expect("open");
+    file.read_into((40 << 20) - 4, &mut on_disk).expect("read");
+    assert_eq!(on_disk, b"tail");
+
+    drop(file);
+    fs::remove_file(&path).expect("cleanup");
+}
+
+#[test]
+fn shrink_then_regrow_in_place() {
+    let path = tmp_path("shrink");
+    let _ = fs::remove_file(&path);
+
+    fs::write(&path, vec![7u8; 3 * 4096]).expect("seed file");
+    let mmap = MemoryMappedFile::builder(&path)
+        .mode(MmapMode::ReadWrite)
+        .reserve_address_space(1 << 20)
+        .open()
+        .expect("open");
+    let base = base_ptr(&mmap);
+
+    mmap.resize(100).expect("shrink");
+    assert_eq!(mmap.len(), 100);
+    assert!(mmap.read_into(100, &mut [0u8; 1]).is_err());
+
+    mmap.resize(5 * 4096).expect("regrow");
+    assert_eq!(base_ptr(&mmap), base);
+    let mut buf = [0xffu8; 8];
+    mmap.read_into(96, &mut buf)
+        .expect("read across the old end");
+    assert_eq!(buf, [7, 7, 7, 7, 0, 0, 0, 0]);
+
+    drop(mmap);
+    fs::remove_file(&path).expect("cleanup");
+}
+
+// Reservation used by the tests that grow past it.
+#[cfg(target_os = "linux")]
+const RESERVE: u64 = 4 << 20;
+
+// Open a reserved mapping of `path` that has at least `RESERVE` bytes of unmapped address
+// space after its reservation, so that `mremap` can extend it in place. New mappings are
+// usually placed right below an earlier one, so the earlier one is dropped to make room.
+#[cfg(target_os = "linux")]
+fn open_with_room_after(path: &Path) -> MemoryMappedFile {
+    let mut held: Vec<MemoryMappedFile> = Vec::new();
+    for _ in 0..16 {
+        let next = MemoryMappedFile::builder(path)
+            .mode(MmapMode::ReadWrite)
+            .reserve_address_space(RESERVE)
+            .open()
+            .expect("open");
+        let end = base_ptr(&next) + RESERVE as usize;
+        if let Some(i) = held.iter().position(|m| base_ptr(m) == end) {
+            held.swap_remove(i);
+        }
+        if is_unmapped(end, RESERVE as usize) {
+            return next;
+        }
+        held.push(next);
+    }
+    panic!("no reservation was followed by free address space");
+}
+
+#[cfg(target_os = "linux")]
+fn is_unmapped(addr: usize, len: usize) -> bool {
+    // SAFETY: MAP_FIXED_NOREPLACE fails instead of replacing an existing mapping, and the
+    // probe is unmapped again before anything can use it.
+    unsafe {
+        let ptr = libc::mmap(
+            addr as *mut libc::c_void,
+            len,
+            libc::PROT_NONE,
+            libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | libc::MAP_FIXED_NOREPLACE,
+            -1,
+            0,
+        );
+        if ptr == libc::MAP_FAILED {
+            return false;
+        }
+        libc::munmap(ptr, len);
+        // Kernels before 4.17 treat the flag as a hint and may map elsewhere
+        ptr as usize == addr
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn growth_past_reservation_extends_in_place() {
+    let path = tmp_path("past");
+    fs::write(&path, b"head").expect("seed file");
+
+    let mmap = open_with_room_after(&path);
+    let base = base_ptr(&mmap);
+    mmap.resize(RESERVE).expect("grow to the whole reservation");
+    mmap.resize(2 * RESERVE).expect("extend the reservation");
+    assert_eq!(base_ptr(&mmap), base);
+    assert_eq!(mmap.reserved_len(), Some(2 * RESERVE));
+
+    // The extension is reachable through every access path
+    mmap.update_region(2 * RESERVE - 4, b"tail").expect("write");
+    let mut buf = [0u8; 4];
+    mmap.read_into(2 * RESERVE - 4, &mut buf).expect("read");
+    assert_eq!(&buf, b"tail");
+    assert_eq!(
+        &mmap.as_slice_guarded(0, 2 * RESERVE).expect("view")[..4],
+        b"head"
+    );
+    mmap.flush().expect("flush");
+    drop(mmap);
+
+    let file = MemoryMappedFile::open_ro(&path).expect("open");
+    file.read_into(2 * RESERVE - 4, &mut buf).expect("read");
+    assert_eq!(&buf, b"tail");
+
+    drop(file);
+    fs::remove_file(&path).expect("cleanup");
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+#[test]
+fn growth_past_reservation_fails() {
+    let path = tmp_path("past");
+    let _ = fs::remove_file(&path);
+
+    let mmap = MemoryMappedFile::builder(&path)
+        .mode(MmapMode::ReadWrite)
+        .size(4096)
+        .reserve_address_space(8192)
+        .create()
+        .expect("create");
+    mmap.resize(8192).expect("grow within the reservation");
+    assert!(matches!(
+        mmap.resize(1 << 20),
+        Err(mmap_io::MmapIoError::ResizeFailed(_))
+    ));
+    assert_eq!(mmap.len(), 8192);
+
+    drop(mmap);
+    fs::remove_file(&path).expect("cleanup");
+}
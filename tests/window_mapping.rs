@@ -0,0 +1,114 @@
+//! Tests for mappings that cover only a window of the file.
+
+use mmap_io::{MemoryMappedFile, MmapIoError, MmapMode};
+use std::fs;
+use std::path::PathBuf;
+
+fn tmp_path(name: &str) -> PathBuf {
+    let mut p = std::env::temp_dir();
+    p.push(format!(
+        "mmap_io_window_mapping_test_{}_{}",
+        name,
+        std::process::id()
+    ));
+    p
+}
+
+fn pattern(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i % 251) as u8).collect()
+}
+
+#[test]
+fn read_only_window_at_unaligned_offset() {
+    let path = tmp_path("ro");
+    let data = pattern(3 * 4096);
+    fs::write(&path, &data).expect("seed file");
+
+    let mmap = MemoryMappedFile::builder(&path)
+        .mode(MmapMode::ReadOnly)
+        .offset(5000)
+        .len(3000)
+        .open()
+        .expect("open window");
+    assert_eq!(mmap.window_offset(), Some(5000));
+    assert_eq!(mmap.len(), 3000);
+    assert_eq!(mmap.as_slice(0, 3000).expect("slice"), &data[5000..8000]);
+    assert!(mmap.as_slice(2999, 2).is_err());
+
+    // Without a length the window runs to the end of the file
+    let tail = MemoryMappedFile::builder(&path)
+        .offset(10_000)
+        .open()
+        .expect("open tail");
+    assert_eq!(tail.len(), 3 * 4096 - 10_000);
+    let mut buf = [0u8; 4];
+    tail.read_into(0, &mut buf).expect("read");
+    assert_eq!(buf, data[10_000..10_004]);
+
+    drop((mmap, tail));
+    fs::remove_file(&path).expect("cleanup");
+}
+
+#[test]
+fn read_write_window_writes_at_file_offset_and_resizes() {
+    let path = tmp_path("rw");
+    let data = pattern(4 * 4096);
+    fs::write(&path, &data).expect("seed file");
+
+    let mmap = MemoryMappedFile::builder(&path)
+        .mode(MmapMode::ReadWrite)
+        .offset(4096 + 100)
+        .len(1000)
+        .open()
+        .expect("open window");
+    mmap.update_region(0, b"start").expect("write");
+    mmap.update_region(995, b"end!!").expect("write");
+    assert!(mmap.update_region(996, b"end!!").is_err());
+    mmap.flush().expect("flush");
+
+    let on_disk = fs::read(&path).expect("read file");
+    assert_eq!(&on_disk[4196..4201], b"start");
+    assert_eq!(&on_disk[5191..5196], b"end!!");
+    assert_eq!(on_disk[..4196], data[..4196]);
+    assert_eq!(on_disk[5196..], data[5196..]);
+
+    // Shrinking the window leaves the file alone; growing it extends the file
+    mmap.resize(10).expect("shrink window");
+    assert_eq!(fs::metadata(&path).expect("metadata").len(), 4 * 4096);
+    mmap.resize(20_000).expect("grow window");
+    assert_eq!(mmap.len(), 20_000);
+    assert_eq!(fs::metadata(&path).expect("metadata").len(), 24_196);
+    mmap.update_region(19_999, b"x").expect("write at new end");
+    let mut buf = [0u8; 5];
+    mmap.read_into(0, &mut buf).expect("read");
+    assert_eq!(&buf, b"start");
+
+    drop(mmap);
+    fs::remove_file(&path).expect("cleanup");
+}
+
+#[test]
+fn create_sizes_file_to_window_and_rejects_bad_windows() {
+    let path = tmp_path("create");
+    let _ = fs::remove_file(&path);
+
+    let mmap = MemoryMappedFile::builder(&path)
+        .mode(MmapMode::ReadWrite)
+        .offset(8192)
+        .len(512)
+        .create()
+        .expect("create window");
+    assert_eq!(mmap.len(), 512);
+    assert_eq!(fs::metadata(&path).expect("metadata").len(), 8192 + 512);
+    drop(mmap);
+
+    let past_end = MemoryMappedFile::builder(&path)
+        .offset(8000)
+        .len(1000)
+        .open();
+    assert!(matches!(past_end, Err(MmapIoError::OutOfBounds { .. })));
+    let empty = MemoryMappedFile::builder(&path).offset(8192 + 512).open();
+    assert!(matches!(empty, Err(MmapIoError::ResizeFailed(_))));
+
+    fs::remove_file(&path).expect("cleanup");
+}